@@ -20,6 +20,10 @@ fn build_diligent_engine(build_path: &PathBuf, install_prefix: &str) -> PathBuf
         "cargo::rustc-link-search=native={}/lib/DiligentCore/Debug",
         dst.display()
     );
+    // The archiver depends on DiligentCore: it must be linked first
+    if env::var_os("CARGO_FEATURE_ARCHIVER_SUPPORTED").is_some() {
+        println!("cargo::rustc-link-lib=static=Diligent-Archiver-static");
+    }
     println!("cargo::rustc-link-lib=static=DiligentCore");
     println!("cargo::rustc-link-lib=static=glslang");
     println!("cargo::rustc-link-lib=static=SPIRV");
@@ -27,6 +31,12 @@ fn build_diligent_engine(build_path: &PathBuf, install_prefix: &str) -> PathBuf
     println!("cargo::rustc-link-lib=static=SPIRV-Tools-opt");
     println!("cargo::rustc-link-lib=static=spirv-cross-core");
     println!("cargo:rustc-link-lib=dylib=stdc++");
+
+    if env::var_os("CARGO_FEATURE_OPENGL_SUPPORTED").is_some() {
+        println!("cargo::rustc-link-lib=static=glew-static");
+        println!("cargo::rustc-link-lib=dylib=GL");
+        println!("cargo::rustc-link-lib=dylib=X11");
+    }
     dst
 }
 
@@ -51,6 +61,24 @@ fn generate_diligent_c_bindings(diligent_install_dir: &PathBuf, out_dir: &PathBu
         }
     };
 
+    let builder = if env::var_os("CARGO_FEATURE_VULKAN_SUPPORTED").is_some() {
+        builder.clang_arg("-DVULKAN_SUPPORTED=1")
+    } else {
+        builder
+    };
+
+    let builder = if env::var_os("CARGO_FEATURE_OPENGL_SUPPORTED").is_some() {
+        builder.clang_arg("-DGL_SUPPORTED=1")
+    } else {
+        builder
+    };
+
+    let builder = if env::var_os("CARGO_FEATURE_ARCHIVER_SUPPORTED").is_some() {
+        builder.clang_arg("-DARCHIVER_SUPPORTED=1")
+    } else {
+        builder
+    };
+
     let bindings = builder.generate().expect("Unable to generate bindings");
 
     let diligent_bindings_filename = "diligent_bindings.rs";
@@ -0,0 +1,425 @@
+use bitflags::bitflags;
+
+use crate::bindings;
+
+use super::{
+    data_blob::DataBlob,
+    graphics_types::{DeviceFeatures, Version},
+    object::{AsObject, Object},
+    pipeline_resource_signature::PipelineResourceSignature,
+    pipeline_state::{
+        GraphicsPipelineStateCreateInfo, GraphicsPipelineStateCreateInfoWrapper, PipelineState,
+    },
+    render_device::RenderDevice,
+    render_pass::RenderPass,
+    shader::{Shader, ShaderCreateInfo},
+};
+
+bitflags! {
+    pub struct ArchiveDeviceDataFlags: bindings::_ARCHIVE_DEVICE_DATA_FLAGS {
+        const None        = bindings::ARCHIVE_DEVICE_DATA_FLAG_NONE;
+        const D3D11       = bindings::ARCHIVE_DEVICE_DATA_FLAG_D3D11;
+        const D3D12       = bindings::ARCHIVE_DEVICE_DATA_FLAG_D3D12;
+        const GL          = bindings::ARCHIVE_DEVICE_DATA_FLAG_GL;
+        const GLES        = bindings::ARCHIVE_DEVICE_DATA_FLAG_GLES;
+        const Vulkan      = bindings::ARCHIVE_DEVICE_DATA_FLAG_VULKAN;
+        const MetalMacOS  = bindings::ARCHIVE_DEVICE_DATA_FLAG_METAL_MACOS;
+        const MetalIOS    = bindings::ARCHIVE_DEVICE_DATA_FLAG_METAL_IOS;
+        const WebGPU      = bindings::ARCHIVE_DEVICE_DATA_FLAG_WEBGPU;
+    }
+}
+
+bitflags! {
+    pub struct PipelineStateArchiveFlags: bindings::_PSO_ARCHIVE_FLAGS {
+        const None               = bindings::PSO_ARCHIVE_FLAG_NONE;
+        // The shader reflection is not needed to create the pipeline from the archive
+        const StripReflection    = bindings::PSO_ARCHIVE_FLAG_STRIP_REFLECTION;
+        // The resource signatures must then be added to the archive separately
+        const DoNotPackSignatures = bindings::PSO_ARCHIVE_FLAG_DO_NOT_PACK_SIGNATURES;
+    }
+}
+
+pub struct SerializationDeviceCreateInfo {
+    pub features: DeviceFeatures,
+
+    pub d3d12_shader_version: Version,
+    pub vulkan_api_version: Version,
+    // The shaders compiled for Vulkan may use SPIR-V 1.4
+    pub vulkan_supports_spirv14: bool,
+}
+
+impl Default for SerializationDeviceCreateInfo {
+    fn default() -> Self {
+        SerializationDeviceCreateInfo {
+            features: DeviceFeatures::default(),
+            d3d12_shader_version: Version { major: 6, minor: 0 },
+            vulkan_api_version: Version { major: 1, minor: 0 },
+            vulkan_supports_spirv14: false,
+        }
+    }
+}
+
+impl From<&SerializationDeviceCreateInfo> for bindings::SerializationDeviceCreateInfo {
+    fn from(value: &SerializationDeviceCreateInfo) -> Self {
+        let mut create_info: bindings::SerializationDeviceCreateInfo =
+            unsafe { std::mem::zeroed() };
+
+        create_info.DeviceInfo.Features = bindings::DeviceFeatures::from(&value.features);
+        create_info.AdapterInfo.Features = bindings::DeviceFeatures::from(&value.features);
+
+        create_info.D3D12.ShaderVersion = bindings::Version {
+            Major: value.d3d12_shader_version.major,
+            Minor: value.d3d12_shader_version.minor,
+        };
+
+        create_info.Vulkan.ApiVersion = bindings::Version {
+            Major: value.vulkan_api_version.major,
+            Minor: value.vulkan_api_version.minor,
+        };
+        create_info.Vulkan.SupportsSpirv14 = value.vulkan_supports_spirv14;
+
+        create_info
+    }
+}
+
+// Creates the device objects to archive. The objects are compiled for all the backends of
+// the device data flags, without any graphics device: they can only be added to an archiver.
+pub struct SerializationDevice {
+    pub(crate) serialization_device: *mut bindings::ISerializationDevice,
+    virtual_functions: *mut bindings::ISerializationDeviceVtbl,
+
+    // The serialization device is a render device, but most of its methods are not implemented
+    render_device: RenderDevice,
+}
+
+impl AsObject for SerializationDevice {
+    fn as_object(&self) -> &Object {
+        self.render_device.as_object()
+    }
+}
+
+impl SerializationDevice {
+    pub(crate) fn new(serialization_device: *mut bindings::ISerializationDevice) -> Self {
+        SerializationDevice {
+            serialization_device,
+            virtual_functions: unsafe { (*serialization_device).pVtbl },
+            render_device: RenderDevice::new(serialization_device as *mut bindings::IRenderDevice),
+        }
+    }
+
+    // The shaders can only be used to create the pipelines of this device
+    pub fn create_shader(
+        &self,
+        shader_ci: &ShaderCreateInfo,
+        device_flags: ArchiveDeviceDataFlags,
+    ) -> Result<Shader, Option<DataBlob>> {
+        let mut shader_ptr: *mut bindings::IShader = std::ptr::null_mut();
+        let mut data_blob_ptr: *mut bindings::IDataBlob = std::ptr::null_mut();
+
+        let shader_ci = bindings::ShaderCreateInfo::from(shader_ci);
+        let archive_info = bindings::ShaderArchiveInfo {
+            DeviceFlags: device_flags.bits() as bindings::ARCHIVE_DEVICE_DATA_FLAGS,
+        };
+
+        unsafe {
+            (*self.virtual_functions)
+                .SerializationDevice
+                .CreateShader
+                .unwrap_unchecked()(
+                self.serialization_device,
+                std::ptr::addr_of!(shader_ci),
+                std::ptr::addr_of!(archive_info),
+                std::ptr::addr_of_mut!(shader_ptr),
+                std::ptr::addr_of_mut!(data_blob_ptr),
+            );
+        }
+
+        if shader_ptr.is_null() {
+            Err((!data_blob_ptr.is_null()).then(|| DataBlob::new(data_blob_ptr)))
+        } else {
+            Ok(Shader::new(shader_ptr))
+        }
+    }
+
+    // The pipeline must have a name, which is used to unpack it from the archive
+    pub fn create_graphics_pipeline_state(
+        &self,
+        pipeline_ci: &GraphicsPipelineStateCreateInfo,
+        pipeline_flags: PipelineStateArchiveFlags,
+        device_flags: ArchiveDeviceDataFlags,
+    ) -> Option<PipelineState> {
+        let mut pipeline_state_ptr = std::ptr::null_mut();
+
+        let pipeline_ci_wrapper = GraphicsPipelineStateCreateInfoWrapper::from(pipeline_ci);
+        let pipeline_ci = pipeline_ci_wrapper.get();
+
+        let archive_info = bindings::PipelineStateArchiveInfo {
+            PSOFlags: pipeline_flags.bits() as bindings::PSO_ARCHIVE_FLAGS,
+            DeviceFlags: device_flags.bits() as bindings::ARCHIVE_DEVICE_DATA_FLAGS,
+        };
+
+        unsafe {
+            (*self.virtual_functions)
+                .SerializationDevice
+                .CreateGraphicsPipelineState
+                .unwrap_unchecked()(
+                self.serialization_device,
+                std::ptr::addr_of!(pipeline_ci),
+                std::ptr::addr_of!(archive_info),
+                std::ptr::addr_of_mut!(pipeline_state_ptr),
+            );
+        }
+
+        if pipeline_state_ptr.is_null() {
+            None
+        } else {
+            Some(PipelineState::new(pipeline_state_ptr))
+        }
+    }
+
+    pub fn create_compute_pipeline_state(
+        &self,
+        pipeline_ci: &bindings::ComputePipelineStateCreateInfo,
+        pipeline_flags: PipelineStateArchiveFlags,
+        device_flags: ArchiveDeviceDataFlags,
+    ) -> Option<PipelineState> {
+        let mut pipeline_state_ptr = std::ptr::null_mut();
+
+        let archive_info = bindings::PipelineStateArchiveInfo {
+            PSOFlags: pipeline_flags.bits() as bindings::PSO_ARCHIVE_FLAGS,
+            DeviceFlags: device_flags.bits() as bindings::ARCHIVE_DEVICE_DATA_FLAGS,
+        };
+
+        unsafe {
+            (*self.virtual_functions)
+                .SerializationDevice
+                .CreateComputePipelineState
+                .unwrap_unchecked()(
+                self.serialization_device,
+                pipeline_ci,
+                std::ptr::addr_of!(archive_info),
+                std::ptr::addr_of_mut!(pipeline_state_ptr),
+            );
+        }
+
+        if pipeline_state_ptr.is_null() {
+            None
+        } else {
+            Some(PipelineState::new(pipeline_state_ptr))
+        }
+    }
+
+    pub fn create_pipeline_resource_signature(
+        &self,
+        signature_desc: &bindings::PipelineResourceSignatureDesc,
+        device_flags: ArchiveDeviceDataFlags,
+    ) -> Option<PipelineResourceSignature> {
+        let mut signature_ptr: *mut bindings::IPipelineResourceSignature = std::ptr::null_mut();
+
+        let archive_info = bindings::ResourceSignatureArchiveInfo {
+            DeviceFlags: device_flags.bits() as bindings::ARCHIVE_DEVICE_DATA_FLAGS,
+        };
+
+        unsafe {
+            (*self.virtual_functions)
+                .SerializationDevice
+                .CreatePipelineResourceSignature
+                .unwrap_unchecked()(
+                self.serialization_device,
+                signature_desc,
+                std::ptr::addr_of!(archive_info),
+                std::ptr::addr_of_mut!(signature_ptr),
+            );
+        }
+
+        if signature_ptr.is_null() {
+            None
+        } else {
+            Some(PipelineResourceSignature::new(signature_ptr))
+        }
+    }
+
+    // The render passes can only be used to create the pipelines of this device. They are
+    // archived with the pipelines that use them.
+    pub fn create_render_pass(
+        &self,
+        render_pass_desc: &bindings::RenderPassDesc,
+    ) -> Option<RenderPass> {
+        let mut render_pass_ptr: *mut bindings::IRenderPass = std::ptr::null_mut();
+
+        unsafe {
+            (*self.virtual_functions)
+                .SerializationDevice
+                .CreateRenderPass
+                .unwrap_unchecked()(
+                self.serialization_device,
+                render_pass_desc,
+                std::ptr::addr_of_mut!(render_pass_ptr),
+            );
+        }
+
+        if render_pass_ptr.is_null() {
+            None
+        } else {
+            Some(RenderPass::new(render_pass_ptr))
+        }
+    }
+
+    // The backends the device can compile the objects for
+    pub fn get_supported_device_flags(&self) -> ArchiveDeviceDataFlags {
+        ArchiveDeviceDataFlags::from_bits_retain(unsafe {
+            (*self.virtual_functions)
+                .SerializationDevice
+                .GetSupportedDeviceFlags
+                .unwrap_unchecked()(self.serialization_device)
+        } as bindings::_ARCHIVE_DEVICE_DATA_FLAGS)
+    }
+}
+
+// Collects the objects created by a serialization device and writes them to an archive, that
+// a `Dearchiver` can load at run time
+pub struct Archiver {
+    archiver: *mut bindings::IArchiver,
+    virtual_functions: *mut bindings::IArchiverVtbl,
+
+    object: Object,
+}
+
+impl AsObject for Archiver {
+    fn as_object(&self) -> &Object {
+        &self.object
+    }
+}
+
+impl Archiver {
+    pub(crate) fn new(archiver: *mut bindings::IArchiver) -> Self {
+        Archiver {
+            archiver,
+            virtual_functions: unsafe { (*archiver).pVtbl },
+            object: Object::new(archiver as *mut bindings::IObject),
+        }
+    }
+
+    pub fn add_shader(&self, shader: &Shader) -> bool {
+        unsafe {
+            (*self.virtual_functions)
+                .Archiver
+                .AddShader
+                .unwrap_unchecked()(self.archiver, shader.shader)
+        }
+    }
+
+    // Also adds the shaders and, unless they are not packed, the resource signatures of the
+    // pipeline
+    pub fn add_pipeline_state(&self, pipeline_state: &PipelineState) -> bool {
+        unsafe {
+            (*self.virtual_functions)
+                .Archiver
+                .AddPipelineState
+                .unwrap_unchecked()(self.archiver, pipeline_state.pipeline_state)
+        }
+    }
+
+    pub fn add_pipeline_resource_signature(&self, signature: &PipelineResourceSignature) -> bool {
+        unsafe {
+            (*self.virtual_functions)
+                .Archiver
+                .AddPipelineResourceSignature
+                .unwrap_unchecked()(self.archiver, signature.pipeline_resource_signature)
+        }
+    }
+
+    // The content version is checked by `Dearchiver::load_archive`
+    pub fn serialize_to_blob(&self, content_version: u32) -> Option<DataBlob> {
+        let mut data_blob_ptr: *mut bindings::IDataBlob = std::ptr::null_mut();
+
+        let serialized = unsafe {
+            (*self.virtual_functions)
+                .Archiver
+                .SerializeToBlob
+                .unwrap_unchecked()(
+                self.archiver,
+                content_version,
+                std::ptr::addr_of_mut!(data_blob_ptr),
+            )
+        };
+
+        if serialized && !data_blob_ptr.is_null() {
+            Some(DataBlob::new(data_blob_ptr))
+        } else {
+            None
+        }
+    }
+
+    // Removes all the objects from the archiver
+    pub fn reset(&self) {
+        unsafe { (*self.virtual_functions).Archiver.Reset.unwrap_unchecked()(self.archiver) }
+    }
+}
+
+pub struct ArchiverFactory {
+    archiver_factory: *mut bindings::IArchiverFactory,
+    virtual_functions: *mut bindings::IArchiverFactoryVtbl,
+}
+
+impl ArchiverFactory {
+    pub fn create_serialization_device(
+        &self,
+        create_info: &SerializationDeviceCreateInfo,
+    ) -> Option<SerializationDevice> {
+        let create_info = bindings::SerializationDeviceCreateInfo::from(create_info);
+
+        let mut serialization_device_ptr: *mut bindings::ISerializationDevice =
+            std::ptr::null_mut();
+        unsafe {
+            (*self.virtual_functions)
+                .ArchiverFactory
+                .CreateSerializationDevice
+                .unwrap_unchecked()(
+                self.archiver_factory,
+                std::ptr::addr_of!(create_info),
+                std::ptr::addr_of_mut!(serialization_device_ptr),
+            );
+        }
+
+        if serialization_device_ptr.is_null() {
+            None
+        } else {
+            Some(SerializationDevice::new(serialization_device_ptr))
+        }
+    }
+
+    pub fn create_archiver(&self, serialization_device: &SerializationDevice) -> Option<Archiver> {
+        let mut archiver_ptr: *mut bindings::IArchiver = std::ptr::null_mut();
+        unsafe {
+            (*self.virtual_functions)
+                .ArchiverFactory
+                .CreateArchiver
+                .unwrap_unchecked()(
+                self.archiver_factory,
+                serialization_device.serialization_device,
+                std::ptr::addr_of_mut!(archiver_ptr),
+            );
+        }
+
+        if archiver_ptr.is_null() {
+            None
+        } else {
+            Some(Archiver::new(archiver_ptr))
+        }
+    }
+}
+
+// The archiver factory is a singleton that is never released
+pub fn get_archiver_factory() -> Option<ArchiverFactory> {
+    let archiver_factory = unsafe { bindings::Diligent_GetArchiverFactory() };
+
+    if archiver_factory.is_null() {
+        None
+    } else {
+        Some(ArchiverFactory {
+            archiver_factory,
+            virtual_functions: unsafe { (*archiver_factory).pVtbl },
+        })
+    }
+}
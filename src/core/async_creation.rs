@@ -0,0 +1,162 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Condvar, Mutex, Once},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use super::{
+    pipeline_state::{PipelineState, PipelineStateStatus},
+    shader::{Shader, ShaderStatus},
+};
+
+#[derive(Debug)]
+pub enum AsyncCreationError {
+    // The engine rejected the create info. Contains the compile log of the shader, if any.
+    CreationFailed(Option<String>),
+    // The object was created but its asynchronous compilation failed
+    CompilationFailed,
+}
+
+impl fmt::Display for AsyncCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsyncCreationError::CreationFailed(Some(log)) => write!(f, "creation failed:\n{log}"),
+            AsyncCreationError::CreationFailed(None) => write!(f, "creation failed"),
+            AsyncCreationError::CompilationFailed => write!(f, "compilation failed"),
+        }
+    }
+}
+
+impl std::error::Error for AsyncCreationError {}
+
+// The futures below check the status of the object, without waiting, every time they are
+// polled. While the object is compiling they are woken up again after `POLL_INTERVAL` by a
+// thread shared by all the futures: e.g. an executor that is run once per frame on the render
+// thread resolves them a few frames later without ever blocking the frame, and an executor
+// that blocks on them does not spin.
+// The status is not checked by the shared thread because the objects of some backends, like GL,
+// can only be queried from the thread of their context.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+static PENDING_WAKERS: Mutex<Vec<Waker>> = Mutex::new(Vec::new());
+static WAKERS_PENDING: Condvar = Condvar::new();
+
+fn wake_later(waker: &Waker) {
+    static POLLER: Once = Once::new();
+    POLLER.call_once(|| {
+        std::thread::Builder::new()
+            .name("async creation poller".to_string())
+            .spawn(|| loop {
+                let mut wakers = PENDING_WAKERS.lock().unwrap();
+                while wakers.is_empty() {
+                    wakers = WAKERS_PENDING.wait(wakers).unwrap();
+                }
+                drop(wakers);
+
+                std::thread::sleep(POLL_INTERVAL);
+
+                // The wakers are called without the lock, they may poll the futures right away
+                let wakers = std::mem::take(&mut *PENDING_WAKERS.lock().unwrap());
+                for waker in wakers {
+                    waker.wake();
+                }
+            })
+            .expect("Failed to spawn the async creation poller thread");
+    });
+
+    let mut wakers = PENDING_WAKERS.lock().unwrap();
+    if !wakers
+        .iter()
+        .any(|pending_waker| pending_waker.will_wake(waker))
+    {
+        wakers.push(waker.clone());
+    }
+    WAKERS_PENDING.notify_one();
+}
+
+pub struct ShaderFuture {
+    shader: Option<Result<Shader, AsyncCreationError>>,
+}
+
+impl ShaderFuture {
+    pub(crate) fn new(shader: Result<Shader, AsyncCreationError>) -> Self {
+        ShaderFuture {
+            shader: Some(shader),
+        }
+    }
+
+    // The shader, while it is compiling. It can already be given to a pipeline created
+    // asynchronously.
+    pub fn get_shader(&self) -> Option<&Shader> {
+        self.shader.as_ref().and_then(|shader| shader.as_ref().ok())
+    }
+}
+
+impl Future for ShaderFuture {
+    type Output = Result<Shader, AsyncCreationError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let status = match self
+            .shader
+            .as_ref()
+            .expect("ShaderFuture polled after completion")
+        {
+            Ok(shader) => shader.get_status(false),
+            Err(_) => ShaderStatus::Failed,
+        };
+
+        match status {
+            ShaderStatus::Ready => Poll::Ready(self.shader.take().unwrap()),
+            ShaderStatus::Failed => Poll::Ready(match self.shader.take().unwrap() {
+                Ok(_) => Err(AsyncCreationError::CompilationFailed),
+                Err(error) => Err(error),
+            }),
+            ShaderStatus::Uninitialized | ShaderStatus::Compiling => {
+                wake_later(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+pub struct PipelineStateFuture {
+    pipeline_state: Option<Result<PipelineState, AsyncCreationError>>,
+}
+
+impl PipelineStateFuture {
+    pub(crate) fn new(pipeline_state: Result<PipelineState, AsyncCreationError>) -> Self {
+        PipelineStateFuture {
+            pipeline_state: Some(pipeline_state),
+        }
+    }
+}
+
+impl Future for PipelineStateFuture {
+    type Output = Result<PipelineState, AsyncCreationError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let status = match self
+            .pipeline_state
+            .as_ref()
+            .expect("PipelineStateFuture polled after completion")
+        {
+            Ok(pipeline_state) => pipeline_state.get_status(None),
+            Err(_) => PipelineStateStatus::Failed,
+        };
+
+        match status {
+            PipelineStateStatus::Ready => Poll::Ready(self.pipeline_state.take().unwrap()),
+            PipelineStateStatus::Failed => Poll::Ready(match self.pipeline_state.take().unwrap() {
+                Ok(_) => Err(AsyncCreationError::CompilationFailed),
+                Err(error) => Err(error),
+            }),
+            PipelineStateStatus::Uninitialized | PipelineStateStatus::Compiling => {
+                wake_later(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
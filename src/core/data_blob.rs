@@ -77,3 +77,13 @@ impl DataBlob {
         }
     }
 }
+
+// Compile logs are null-terminated strings
+pub(crate) fn compile_log(data_blob: &DataBlob) -> String {
+    let log = unsafe {
+        std::slice::from_raw_parts(data_blob.get_const_data_ptr::<u8>(0), data_blob.get_size())
+    };
+    String::from_utf8_lossy(log)
+        .trim_end_matches('\0')
+        .to_string()
+}
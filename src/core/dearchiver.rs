@@ -0,0 +1,168 @@
+use std::ffi::CStr;
+
+use crate::bindings;
+
+use super::{
+    data_blob::DataBlob,
+    object::{AsObject, Object},
+    pipeline_resource_signature::PipelineResourceSignature,
+    pipeline_state::{PipelineState, PipelineType},
+    render_device::RenderDevice,
+    render_pass::RenderPass,
+};
+
+// Unpacks the device objects of archives created offline with the archiver, without compiling
+// their shaders
+pub struct Dearchiver {
+    pub(crate) dearchiver: *mut bindings::IDearchiver,
+    virtual_functions: *mut bindings::IDearchiverVtbl,
+
+    object: Object,
+}
+
+impl AsObject for Dearchiver {
+    fn as_object(&self) -> &Object {
+        &self.object
+    }
+}
+
+impl Dearchiver {
+    pub(crate) fn new(dearchiver: *mut bindings::IDearchiver) -> Self {
+        Dearchiver {
+            dearchiver,
+            virtual_functions: unsafe { (*dearchiver).pVtbl },
+            object: Object::new(dearchiver as *mut bindings::IObject),
+        }
+    }
+
+    // Archives whose content version is not `content_version` are rejected. Without
+    // `make_copy`, the dearchiver keeps a reference to the archive instead of copying it.
+    pub fn load_archive(&self, archive: &DataBlob, content_version: u32, make_copy: bool) -> bool {
+        unsafe {
+            (*self.virtual_functions)
+                .Dearchiver
+                .LoadArchive
+                .unwrap_unchecked()(
+                self.dearchiver,
+                archive.as_object().object as *const bindings::IDataBlob,
+                content_version,
+                make_copy,
+            )
+        }
+    }
+
+    pub fn unpack_pipeline_state(
+        &self,
+        render_device: &RenderDevice,
+        name: &CStr,
+        pipeline_type: PipelineType,
+    ) -> Option<PipelineState> {
+        // The pipeline is created as it was archived: without a cache and without modifying
+        // its create info
+        let unpack_info = bindings::PipelineStateUnpackInfo {
+            pDevice: render_device.render_device,
+            Name: name.as_ptr(),
+            PipelineType: bindings::PIPELINE_TYPE::from(&pipeline_type),
+            SRBAllocationGranularity: 1,
+            ImmediateContextMask: 1,
+            ..unsafe { std::mem::zeroed() }
+        };
+
+        let mut pipeline_state_ptr: *mut bindings::IPipelineState = std::ptr::null_mut();
+        unsafe {
+            (*self.virtual_functions)
+                .Dearchiver
+                .UnpackPipelineState
+                .unwrap_unchecked()(
+                self.dearchiver,
+                std::ptr::addr_of!(unpack_info),
+                std::ptr::addr_of_mut!(pipeline_state_ptr),
+            );
+        }
+
+        if pipeline_state_ptr.is_null() {
+            None
+        } else {
+            Some(PipelineState::new(pipeline_state_ptr))
+        }
+    }
+
+    pub fn unpack_resource_signature(
+        &self,
+        render_device: &RenderDevice,
+        name: &CStr,
+    ) -> Option<PipelineResourceSignature> {
+        let unpack_info = bindings::ResourceSignatureUnpackInfo {
+            pDevice: render_device.render_device,
+            Name: name.as_ptr(),
+            SRBAllocationGranularity: 1,
+        };
+
+        let mut signature_ptr: *mut bindings::IPipelineResourceSignature = std::ptr::null_mut();
+        unsafe {
+            (*self.virtual_functions)
+                .Dearchiver
+                .UnpackResourceSignature
+                .unwrap_unchecked()(
+                self.dearchiver,
+                std::ptr::addr_of!(unpack_info),
+                std::ptr::addr_of_mut!(signature_ptr),
+            );
+        }
+
+        if signature_ptr.is_null() {
+            None
+        } else {
+            Some(PipelineResourceSignature::new(signature_ptr))
+        }
+    }
+
+    pub fn unpack_render_pass(
+        &self,
+        render_device: &RenderDevice,
+        name: &CStr,
+    ) -> Option<RenderPass> {
+        let unpack_info = bindings::RenderPassUnpackInfo {
+            pDevice: render_device.render_device,
+            Name: name.as_ptr(),
+            ..unsafe { std::mem::zeroed() }
+        };
+
+        let mut render_pass_ptr: *mut bindings::IRenderPass = std::ptr::null_mut();
+        unsafe {
+            (*self.virtual_functions)
+                .Dearchiver
+                .UnpackRenderPass
+                .unwrap_unchecked()(
+                self.dearchiver,
+                std::ptr::addr_of!(unpack_info),
+                std::ptr::addr_of_mut!(render_pass_ptr),
+            );
+        }
+
+        if render_pass_ptr.is_null() {
+            None
+        } else {
+            Some(RenderPass::new(render_pass_ptr))
+        }
+    }
+
+    // Unloads all the archives
+    pub fn reset(&self) {
+        unsafe {
+            (*self.virtual_functions)
+                .Dearchiver
+                .Reset
+                .unwrap_unchecked()(self.dearchiver)
+        }
+    }
+
+    pub fn get_content_version(&self) -> u32 {
+        unsafe {
+            (*self.virtual_functions)
+                .Dearchiver
+                .GetContentVersion
+                .unwrap_unchecked()(self.dearchiver)
+        }
+    }
+}
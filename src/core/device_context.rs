@@ -860,11 +860,37 @@ impl DeviceContext {
         }
     }
 
-    // TODO
-    //pub fn map_texture_subresource(&self, )
-    //{
-    //
-    //}
+    pub fn map_texture_subresource(
+        &self,
+        texture: &mut Texture,
+        mip_level: u32,
+        array_slice: u32,
+        map_type: MapType,
+        map_flags: MapFlags,
+        map_region: Option<&bindings::Box>,
+    ) -> bindings::MappedTextureSubresource {
+        let mut mapped_data = bindings::MappedTextureSubresource {
+            pData: std::ptr::null_mut(),
+            Stride: 0,
+            DepthStride: 0,
+        };
+        unsafe {
+            (*self.virtual_functions)
+                .DeviceContext
+                .MapTextureSubresource
+                .unwrap_unchecked()(
+                self.device_context,
+                texture.texture,
+                mip_level,
+                array_slice,
+                bindings::MAP_TYPE::from(&map_type),
+                map_flags.bits() as bindings::MAP_FLAGS,
+                map_region.map_or(std::ptr::null(), |region| std::ptr::from_ref(region)),
+                std::ptr::addr_of_mut!(mapped_data),
+            );
+        }
+        mapped_data
+    }
 
     pub fn unmap_texture_subresource(
         &self,
@@ -1,11 +1,14 @@
-use std::os::raw::c_void;
+use std::{ffi::CString, os::raw::c_void, path::Path};
 
 use crate::bindings;
 
 use super::{
     data_blob::DataBlob,
+    dearchiver::Dearchiver,
     graphics_types::{DeviceFeatures, GraphicsAdapterInfo, Version},
     object::Object,
+    shader_source_factory::ShaderSourceInputStreamFactory,
+    thread_pool::ThreadPoolObject,
 };
 
 pub struct EngineCreateInfo {
@@ -27,7 +30,10 @@ pub struct EngineCreateInfo {
 
     // TODO
     //struct IMemoryAllocator* pRawMemAllocator       DEFAULT_INITIALIZER(nullptr);
-    //IThreadPool* pAsyncShaderCompilationThreadPool DEFAULT_INITIALIZER(nullptr);
+
+    // Asynchronous shaders and pipelines are compiled on this thread pool. Without one, the
+    // engine creates its own with `num_async_shader_compilation_threads` threads.
+    pub async_shader_compilation_thread_pool: Option<ThreadPoolObject>,
     pub num_async_shader_compilation_threads: u32,
     // TODO
     //const OpenXRAttribs *pXRAttribs DEFAULT_INITIALIZER(nullptr);
@@ -52,6 +58,7 @@ impl Default for EngineCreateInfo {
 
             validation_flags: bindings::VALIDATION_FLAG_NONE,
 
+            async_shader_compilation_thread_pool: None,
             num_async_shader_compilation_threads: 0xFFFFFFFF,
         }
     }
@@ -75,7 +82,10 @@ impl From<&EngineCreateInfo> for bindings::EngineCreateInfo {
             EnableValidation: value.enable_validation,
             ValidationFlags: value.validation_flags,
             pRawMemAllocator: std::ptr::null_mut() as *mut bindings::IMemoryAllocator,
-            pAsyncShaderCompilationThreadPool: std::ptr::null_mut() as *mut bindings::IThreadPool,
+            pAsyncShaderCompilationThreadPool: value
+                .async_shader_compilation_thread_pool
+                .as_ref()
+                .map_or(std::ptr::null_mut(), |thread_pool| thread_pool.thread_pool),
             NumAsyncShaderCompilationThreads: value.num_async_shader_compilation_threads,
             Padding: 0,
             pXRAttribs: std::ptr::null() as *const bindings::OpenXRAttribs,
@@ -115,7 +125,38 @@ impl EngineFactory {
         }
     }
 
-    //fn create_default_shader_source_stream_factory(&self, search_directories: Vec<PathBuf>) -> bindings::IShaderSourceInputStreamFactory;
+    // Loads the files from the working directory, then from the search directories in order
+    pub fn create_default_shader_source_stream_factory(
+        &self,
+        search_directories: &[&Path],
+    ) -> Option<ShaderSourceInputStreamFactory> {
+        let search_directories = CString::new(
+            search_directories
+                .iter()
+                .map(|directory| directory.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(";"),
+        )
+        .ok()?;
+
+        let mut factory_ptr: *mut bindings::IShaderSourceInputStreamFactory = std::ptr::null_mut();
+        unsafe {
+            (*self.virtual_functions)
+                .EngineFactory
+                .CreateDefaultShaderSourceStreamFactory
+                .unwrap_unchecked()(
+                self.engine_factory,
+                search_directories.as_ptr(),
+                std::ptr::addr_of_mut!(factory_ptr),
+            );
+        }
+
+        if factory_ptr.is_null() {
+            None
+        } else {
+            Some(ShaderSourceInputStreamFactory::new(factory_ptr))
+        }
+    }
 
     pub fn create_data_blob<T>(&self, initial_size: usize, data: *const T) -> Option<DataBlob> {
         let mut data_blob_ptr: *mut bindings::IDataBlob = std::ptr::null_mut();
@@ -181,7 +222,28 @@ impl EngineFactory {
         }
     }
 
-    //pub fn create_dearchiver(&self, create_info : &bindings::DearchiverCreateInfo) -> bindings::IDearchiver;
+    pub fn create_dearchiver(&self) -> Option<Dearchiver> {
+        // The create info has no options yet
+        let create_info: bindings::DearchiverCreateInfo = unsafe { std::mem::zeroed() };
+
+        let mut dearchiver_ptr: *mut bindings::IDearchiver = std::ptr::null_mut();
+        unsafe {
+            (*self.virtual_functions)
+                .EngineFactory
+                .CreateDearchiver
+                .unwrap_unchecked()(
+                self.engine_factory,
+                std::ptr::addr_of!(create_info),
+                std::ptr::addr_of_mut!(dearchiver_ptr),
+            );
+        }
+
+        if dearchiver_ptr.is_null() {
+            None
+        } else {
+            Some(Dearchiver::new(dearchiver_ptr))
+        }
+    }
 
     pub fn set_message_callback(&self, callback: bindings::DebugMessageCallbackType) {
         unsafe {
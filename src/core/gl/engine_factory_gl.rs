@@ -0,0 +1,181 @@
+#[cfg(target_os = "linux")]
+use raw_window_handle::{HandleError, HasDisplayHandle, HasWindowHandle};
+
+use crate::bindings;
+use crate::core::device_context::DeviceContext;
+use crate::core::engine_factory::EngineCreateInfo;
+use crate::core::engine_factory::EngineFactory;
+
+use crate::core::engine_factory::AsEngineFactory;
+use crate::core::graphics_types::AdapterType;
+#[cfg(target_os = "linux")]
+use crate::core::native_window::get_native_window;
+use crate::core::render_device::RenderDevice;
+use crate::core::swap_chain::SwapChain;
+
+pub struct EngineGLCreateInfo {
+    engine_create_info: EngineCreateInfo,
+
+    window: Option<bindings::NativeWindow>,
+    zero_to_one_ndz: bool,
+    preferred_adapter_type: AdapterType,
+}
+
+impl EngineGLCreateInfo {
+    pub fn new(
+        engine_create_info: EngineCreateInfo,
+        window: Option<&bindings::NativeWindow>,
+    ) -> Self {
+        EngineGLCreateInfo {
+            engine_create_info,
+
+            window: window.copied(),
+            zero_to_one_ndz: false,
+            preferred_adapter_type: AdapterType::Unknown,
+        }
+    }
+
+    // The swap chain is created for a window created by another library
+    #[cfg(target_os = "linux")]
+    pub fn for_window<W>(
+        engine_create_info: EngineCreateInfo,
+        window: &W,
+    ) -> Result<Self, HandleError>
+    where
+        W: HasWindowHandle + HasDisplayHandle + ?Sized,
+    {
+        Ok(EngineGLCreateInfo::new(
+            engine_create_info,
+            Some(&get_native_window(window)?),
+        ))
+    }
+
+    pub fn zero_to_one_ndz(mut self, zero_to_one_ndz: bool) -> Self {
+        self.zero_to_one_ndz = zero_to_one_ndz;
+        self
+    }
+
+    pub fn preferred_adapter_type(mut self, preferred_adapter_type: AdapterType) -> Self {
+        self.preferred_adapter_type = preferred_adapter_type;
+        self
+    }
+}
+
+impl Default for EngineGLCreateInfo {
+    fn default() -> Self {
+        EngineGLCreateInfo::new(EngineCreateInfo::default(), None)
+    }
+}
+
+impl From<&EngineGLCreateInfo> for bindings::EngineGLCreateInfo {
+    fn from(value: &EngineGLCreateInfo) -> Self {
+        bindings::EngineGLCreateInfo {
+            _EngineCreateInfo: bindings::EngineCreateInfo::from(&value.engine_create_info),
+            Window: value.window.unwrap_or(bindings::NativeWindow {
+                WindowId: 0,
+                pDisplay: std::ptr::null_mut(),
+                pXCBConnection: std::ptr::null_mut(),
+                pWaylandSurface: std::ptr::null_mut(),
+            }),
+            ZeroToOneNDZ: value.zero_to_one_ndz,
+            PreferredAdapterType: bindings::ADAPTER_TYPE::from(&value.preferred_adapter_type),
+        }
+    }
+}
+
+pub struct EngineFactoryOpenGL {
+    engine_factory_gl: *mut bindings::IEngineFactoryOpenGL,
+    virtual_functions: *mut bindings::IEngineFactoryOpenGLVtbl,
+
+    engine_factory: EngineFactory,
+}
+
+impl AsEngineFactory for EngineFactoryOpenGL {
+    #[inline]
+    fn as_engine_factory(&self) -> &EngineFactory {
+        &self.engine_factory
+    }
+}
+
+pub fn get_engine_factory_gl() -> EngineFactoryOpenGL {
+    let engine_factory_gl = unsafe { bindings::Diligent_GetEngineFactoryOpenGL() };
+
+    EngineFactoryOpenGL {
+        engine_factory_gl,
+        virtual_functions: unsafe { (*engine_factory_gl).pVtbl },
+
+        engine_factory: EngineFactory::new(engine_factory_gl as *mut bindings::IEngineFactory),
+    }
+}
+
+impl EngineFactoryOpenGL {
+    // OpenGL does not support deferred contexts: the engine always creates a single immediate context.
+    pub fn create_device_and_swap_chain_gl(
+        &self,
+        create_info: &EngineGLCreateInfo,
+        swapchain_desc: &bindings::SwapChainDesc,
+    ) -> Option<(RenderDevice, DeviceContext, SwapChain)> {
+        let mut render_device_ptr = std::ptr::null_mut();
+        let mut device_context_ptr = std::ptr::null_mut();
+        let mut swap_chain_ptr = std::ptr::null_mut();
+
+        let create_info = bindings::EngineGLCreateInfo::from(create_info);
+
+        unsafe {
+            (*self.virtual_functions)
+                .EngineFactoryOpenGL
+                .CreateDeviceAndSwapChainGL
+                .unwrap_unchecked()(
+                self.engine_factory_gl,
+                std::ptr::addr_of!(create_info),
+                std::ptr::addr_of_mut!(render_device_ptr),
+                std::ptr::addr_of_mut!(device_context_ptr),
+                std::ptr::from_ref(swapchain_desc),
+                std::ptr::addr_of_mut!(swap_chain_ptr),
+            );
+        }
+
+        if render_device_ptr.is_null() || device_context_ptr.is_null() || swap_chain_ptr.is_null() {
+            None
+        } else {
+            Some((
+                RenderDevice::new(render_device_ptr),
+                DeviceContext::new(device_context_ptr),
+                SwapChain::new(swap_chain_ptr),
+            ))
+        }
+    }
+
+    // Attaches the engine to the GL context that is current on the calling thread.
+    // No swap chain is created: presenting is left to the application.
+    pub fn attach_to_active_gl_context(
+        &self,
+        create_info: &EngineGLCreateInfo,
+    ) -> Option<(RenderDevice, DeviceContext)> {
+        let mut render_device_ptr = std::ptr::null_mut();
+        let mut device_context_ptr = std::ptr::null_mut();
+
+        let create_info = bindings::EngineGLCreateInfo::from(create_info);
+
+        unsafe {
+            (*self.virtual_functions)
+                .EngineFactoryOpenGL
+                .AttachToActiveGLContext
+                .unwrap_unchecked()(
+                self.engine_factory_gl,
+                std::ptr::addr_of!(create_info),
+                std::ptr::addr_of_mut!(render_device_ptr),
+                std::ptr::addr_of_mut!(device_context_ptr),
+            );
+        }
+
+        if render_device_ptr.is_null() || device_context_ptr.is_null() {
+            None
+        } else {
+            Some((
+                RenderDevice::new(render_device_ptr),
+                DeviceContext::new(device_context_ptr),
+            ))
+        }
+    }
+}
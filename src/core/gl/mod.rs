@@ -0,0 +1 @@
+pub mod engine_factory_gl;
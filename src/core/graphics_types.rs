@@ -471,7 +471,7 @@ impl Version {
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum AdapterType {
     Unknown,
     Software,
@@ -490,6 +490,17 @@ impl AdapterType {
     }
 }
 
+impl From<&AdapterType> for bindings::ADAPTER_TYPE {
+    fn from(value: &AdapterType) -> Self {
+        (match value {
+            AdapterType::Unknown => bindings::ADAPTER_TYPE_UNKNOWN,
+            AdapterType::Software => bindings::ADAPTER_TYPE_SOFTWARE,
+            AdapterType::Integrated => bindings::ADAPTER_TYPE_INTEGRATED,
+            AdapterType::Discrete => bindings::ADAPTER_TYPE_DISCRETE,
+        }) as bindings::ADAPTER_TYPE
+    }
+}
+
 // Prefer Discrete over Integrated over Software
 const_assert!(
     AdapterType::Discrete.priority() > AdapterType::Integrated.priority()
@@ -1,25 +1,38 @@
 mod device_object;
-mod object;
+pub(crate) mod object;
 
+pub mod async_creation;
 pub mod buffer;
 pub mod buffer_view;
 pub mod data_blob;
+pub mod dearchiver;
 pub mod device_context;
 pub mod engine_factory;
 pub mod fence;
 pub mod graphics_types;
 pub mod input_layout;
+#[cfg(target_os = "linux")]
+pub mod native_window;
 pub mod pipeline_resource_signature;
 pub mod pipeline_state;
 pub mod render_device;
+pub mod render_pass;
 pub mod resource_mapping;
 pub mod sampler;
 pub mod shader;
 pub mod shader_resource_binding;
 pub mod shader_resource_variable;
+pub mod shader_source_factory;
 pub mod swap_chain;
 pub mod texture;
 pub mod texture_view;
+pub mod thread_pool;
 
 #[cfg(feature = "VULKAN_SUPPORTED")]
 pub mod vk;
+
+#[cfg(feature = "OPENGL_SUPPORTED")]
+pub mod gl;
+
+#[cfg(feature = "ARCHIVER_SUPPORTED")]
+pub mod archiver;
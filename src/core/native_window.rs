@@ -0,0 +1,51 @@
+use std::ptr::NonNull;
+
+use raw_window_handle::{
+    HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
+
+use crate::bindings;
+
+fn as_mut_ptr<T>(ptr: NonNull<T>) -> *mut std::ffi::c_void {
+    ptr.as_ptr() as *mut std::ffi::c_void
+}
+
+// Describes a window created by another library (winit, SDL...) to the engine. The window
+// must outlive the swap chains created for it.
+pub fn get_native_window<W>(window: &W) -> Result<bindings::NativeWindow, HandleError>
+where
+    W: HasWindowHandle + HasDisplayHandle + ?Sized,
+{
+    let window_handle = window.window_handle()?.as_raw();
+    let display_handle = window.display_handle()?.as_raw();
+
+    match (window_handle, display_handle) {
+        (RawWindowHandle::Xcb(window_handle), RawDisplayHandle::Xcb(display_handle)) => {
+            Ok(bindings::NativeWindow {
+                WindowId: window_handle.window.get(),
+                pDisplay: std::ptr::null_mut(),
+                pXCBConnection: as_mut_ptr(
+                    display_handle.connection.ok_or(HandleError::Unavailable)?,
+                ),
+                pWaylandSurface: std::ptr::null_mut(),
+            })
+        }
+        (RawWindowHandle::Xlib(window_handle), RawDisplayHandle::Xlib(display_handle)) => {
+            Ok(bindings::NativeWindow {
+                WindowId: window_handle.window as u32,
+                pDisplay: as_mut_ptr(display_handle.display.ok_or(HandleError::Unavailable)?),
+                pXCBConnection: std::ptr::null_mut(),
+                pWaylandSurface: std::ptr::null_mut(),
+            })
+        }
+        (RawWindowHandle::Wayland(window_handle), RawDisplayHandle::Wayland(display_handle)) => {
+            Ok(bindings::NativeWindow {
+                WindowId: 0,
+                pDisplay: as_mut_ptr(display_handle.display),
+                pXCBConnection: std::ptr::null_mut(),
+                pWaylandSurface: as_mut_ptr(window_handle.surface),
+            })
+        }
+        _ => Err(HandleError::NotSupported),
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
 use crate::bindings;
 
 pub(crate) struct Object {
@@ -31,3 +33,47 @@ impl Drop for Object {
         }
     }
 }
+
+// The functions below implement IObject for Diligent interfaces implemented in Rust: the layout
+// of these objects starts with the virtual function table pointer, like the C structs of the
+// interfaces. They are not queryable and have no reference counters object, the engine only
+// adds and releases references to them.
+
+pub(crate) unsafe extern "C" fn query_interface(
+    _object: *mut bindings::IObject,
+    _iid: *const bindings::INTERFACE_ID,
+    interface: *mut *mut bindings::IObject,
+) {
+    *interface = std::ptr::null_mut();
+}
+
+pub(crate) unsafe extern "C" fn get_reference_counters(
+    _object: *mut bindings::IObject,
+) -> *mut bindings::IReferenceCounters {
+    std::ptr::null_mut()
+}
+
+pub(crate) unsafe extern "C" fn add_ref<T: RustObject>(
+    object: *mut bindings::IObject,
+) -> bindings::ReferenceCounterValueType {
+    let object = &*(object as *const T);
+    (object.reference_count().fetch_add(1, Ordering::Relaxed) + 1)
+        as bindings::ReferenceCounterValueType
+}
+
+pub(crate) unsafe extern "C" fn release<T: RustObject>(
+    object: *mut bindings::IObject,
+) -> bindings::ReferenceCounterValueType {
+    let count = (*(object as *const T))
+        .reference_count()
+        .fetch_sub(1, Ordering::AcqRel)
+        - 1;
+    if count == 0 {
+        drop(Box::from_raw(object as *mut T));
+    }
+    count as bindings::ReferenceCounterValueType
+}
+
+pub(crate) trait RustObject {
+    fn reference_count(&self) -> &AtomicI64;
+}
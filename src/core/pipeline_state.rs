@@ -230,6 +230,26 @@ bitflags! {
     }
 }
 
+pub enum PipelineType {
+    Graphics,
+    Compute,
+    Mesh,
+    RayTracing,
+    Tile,
+}
+
+impl From<&PipelineType> for bindings::PIPELINE_TYPE {
+    fn from(value: &PipelineType) -> Self {
+        (match value {
+            PipelineType::Graphics => bindings::PIPELINE_TYPE_GRAPHICS,
+            PipelineType::Compute => bindings::PIPELINE_TYPE_COMPUTE,
+            PipelineType::Mesh => bindings::PIPELINE_TYPE_MESH,
+            PipelineType::RayTracing => bindings::PIPELINE_TYPE_RAY_TRACING,
+            PipelineType::Tile => bindings::PIPELINE_TYPE_TILE,
+        }) as bindings::PIPELINE_TYPE
+    }
+}
+
 bitflags! {
     pub struct PipelineStateObjectCreateFlags: bindings::_PSO_CREATE_FLAGS {
         const None                           = bindings::PSO_CREATE_FLAG_NONE;
@@ -387,6 +407,7 @@ struct PipelineStateCreateInfo<'a, const PIPELINE_TYPE: bindings::PIPELINE_TYPE>
 
 pub(crate) struct PipelineStateCreateInfoWrapper {
     _psd: PipelineStateDescWrapper,
+    _resource_signatures: Vec<*mut bindings::IPipelineResourceSignature>,
     ci: bindings::PipelineStateCreateInfo,
 }
 
@@ -402,25 +423,30 @@ impl<const PIPELINE_TYPE: bindings::PIPELINE_TYPE> From<&PipelineStateCreateInfo
     fn from(value: &PipelineStateCreateInfo<'_, PIPELINE_TYPE>) -> Self {
         let psd = PipelineStateDescWrapper::from(&value.pso_desc);
 
+        let mut resource_signatures: Vec<_> = value
+            .resource_signatures
+            .iter()
+            .map(|rs| rs.pipeline_resource_signature)
+            .collect();
+
         let ci = bindings::PipelineStateCreateInfo {
             PSODesc: psd.get(),
             Flags: value.flags.bits(),
-            ResourceSignaturesCount: value.resource_signatures.len() as u32,
-            ppResourceSignatures: if value.resource_signatures.is_empty() {
+            ResourceSignaturesCount: resource_signatures.len() as u32,
+            ppResourceSignatures: if resource_signatures.is_empty() {
                 std::ptr::null_mut()
             } else {
-                value
-                    .resource_signatures
-                    .iter()
-                    .map(|rs| rs.pipeline_resource_signature)
-                    .collect::<Vec<*mut bindings::IPipelineResourceSignature>>()
-                    .as_mut_ptr()
+                resource_signatures.as_mut_ptr()
             },
             pPSOCache: std::ptr::null_mut(), // TODO
             pInternalData: std::ptr::null_mut(),
         };
 
-        PipelineStateCreateInfoWrapper { _psd: psd, ci }
+        PipelineStateCreateInfoWrapper {
+            _psd: psd,
+            _resource_signatures: resource_signatures,
+            ci,
+        }
     }
 }
 
@@ -1045,6 +1071,20 @@ impl<'a> GraphicsPipelineStateCreateInfo<'a> {
         self.mesh_shader = Some(shader);
         self
     }
+
+    pub fn shaders(&self) -> impl Iterator<Item = &'a Shader> {
+        [
+            self.vertex_shader,
+            self.pixel_shader,
+            self.domain_shader,
+            self.hull_shader,
+            self.geometry_shader,
+            self.amplification_shader,
+            self.mesh_shader,
+        ]
+        .into_iter()
+        .flatten()
+    }
 }
 
 pub(crate) struct GraphicsPipelineStateCreateInfoWrapper {
@@ -1097,6 +1137,25 @@ impl From<&GraphicsPipelineStateCreateInfo<'_>> for GraphicsPipelineStateCreateI
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PipelineStateStatus {
+    Uninitialized,
+    Compiling,
+    Ready,
+    Failed,
+}
+
+impl From<bindings::PIPELINE_STATE_STATUS> for PipelineStateStatus {
+    fn from(value: bindings::PIPELINE_STATE_STATUS) -> Self {
+        match value as bindings::_PIPELINE_STATE_STATUS {
+            bindings::PIPELINE_STATE_STATUS_COMPILING => PipelineStateStatus::Compiling,
+            bindings::PIPELINE_STATE_STATUS_READY => PipelineStateStatus::Ready,
+            bindings::PIPELINE_STATE_STATUS_FAILED => PipelineStateStatus::Failed,
+            _ => PipelineStateStatus::Uninitialized,
+        }
+    }
+}
+
 pub struct PipelineState {
     pub(crate) pipeline_state: *mut bindings::IPipelineState,
     virtual_functions: *mut bindings::IPipelineStateVtbl,
@@ -1277,14 +1336,14 @@ impl PipelineState {
         todo!()
     }
 
-    pub fn get_status(&self, wait_for_completion: Option<bool>) -> bindings::PIPELINE_STATE_STATUS {
-        unsafe {
+    pub fn get_status(&self, wait_for_completion: Option<bool>) -> PipelineStateStatus {
+        PipelineStateStatus::from(unsafe {
             (*self.virtual_functions)
                 .PipelineState
                 .GetStatus
                 .unwrap_unchecked()(
                 self.pipeline_state, wait_for_completion.unwrap_or(false)
             )
-        }
+        })
     }
 }
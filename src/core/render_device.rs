@@ -1,17 +1,19 @@
 use crate::bindings;
 
+use super::async_creation::{AsyncCreationError, PipelineStateFuture, ShaderFuture};
 use super::buffer::{Buffer, BufferDesc};
-use super::data_blob::DataBlob;
+use super::data_blob::{compile_log, DataBlob};
 use super::device_context::DeviceContext;
 use super::fence::Fence;
 use super::graphics_types::RenderDeviceType;
 use super::object::{AsObject, Object};
 use super::pipeline_state::{
     GraphicsPipelineStateCreateInfo, GraphicsPipelineStateCreateInfoWrapper, PipelineState,
+    PipelineStateObjectCreateFlags,
 };
 use super::resource_mapping::ResourceMapping;
 use super::sampler::Sampler;
-use super::shader::{Shader, ShaderCreateInfo};
+use super::shader::{Shader, ShaderCompileFlags, ShaderCreateInfo};
 use super::texture::{Texture, TextureDesc, TextureSubResource};
 
 pub struct RenderDeviceInfo {
@@ -81,11 +83,17 @@ impl RenderDevice {
         }
     }
 
-    pub fn create_shader(&self, shader_ci: &ShaderCreateInfo) -> Result<Shader, DataBlob> {
+    // The compile flags are added to the ones of `shader_ci`
+    fn create_shader_with_flags(
+        &self,
+        shader_ci: &ShaderCreateInfo,
+        compile_flags: ShaderCompileFlags,
+    ) -> Result<Shader, Option<DataBlob>> {
         let mut shader_ptr: *mut bindings::IShader = std::ptr::null_mut();
         let mut data_blob_ptr: *mut bindings::IDataBlob = std::ptr::null_mut();
 
-        let shader_ci = bindings::ShaderCreateInfo::from(shader_ci);
+        let mut shader_ci = bindings::ShaderCreateInfo::from(shader_ci);
+        shader_ci.CompileFlags |= compile_flags.bits();
 
         unsafe {
             (*self.virtual_functions)
@@ -100,12 +108,27 @@ impl RenderDevice {
         }
 
         if shader_ptr.is_null() {
-            Err(DataBlob::new(data_blob_ptr))
+            Err((!data_blob_ptr.is_null()).then(|| DataBlob::new(data_blob_ptr)))
         } else {
             Ok(Shader::new(shader_ptr))
         }
     }
 
+    // The error contains the compile log, if the compiler was run: there is none when e.g. the
+    // source file is missing
+    pub fn create_shader(&self, shader_ci: &ShaderCreateInfo) -> Result<Shader, Option<DataBlob>> {
+        self.create_shader_with_flags(shader_ci, ShaderCompileFlags::None)
+    }
+
+    // The shader is compiled in the background, by the thread pool of the engine if it has one.
+    // The shader can be used to create pipelines before it is ready.
+    pub fn create_shader_async(&self, shader_ci: &ShaderCreateInfo) -> ShaderFuture {
+        ShaderFuture::new(
+            self.create_shader_with_flags(shader_ci, ShaderCompileFlags::Asynchronous)
+                .map_err(|log| AsyncCreationError::CreationFailed(log.as_ref().map(compile_log))),
+        )
+    }
+
     pub fn create_texture(
         &self,
         texture_desc: &TextureDesc,
@@ -192,14 +215,17 @@ impl RenderDevice {
         }
     }
 
-    pub fn create_graphics_pipeline_state(
+    // The flags are added to the ones of `pipeline_ci`
+    fn create_graphics_pipeline_state_with_flags(
         &self,
         pipeline_ci: &GraphicsPipelineStateCreateInfo,
+        flags: PipelineStateObjectCreateFlags,
     ) -> Option<PipelineState> {
         let mut pipeline_state_ptr = std::ptr::null_mut();
 
         let pipeline_ci_wrapper = GraphicsPipelineStateCreateInfoWrapper::from(pipeline_ci);
-        let pipeline_ci = pipeline_ci_wrapper.get();
+        let mut pipeline_ci = pipeline_ci_wrapper.get();
+        pipeline_ci._PipelineStateCreateInfo.Flags |= flags.bits();
 
         unsafe {
             (*self.virtual_functions)
@@ -218,6 +244,30 @@ impl RenderDevice {
         }
     }
 
+    pub fn create_graphics_pipeline_state(
+        &self,
+        pipeline_ci: &GraphicsPipelineStateCreateInfo,
+    ) -> Option<PipelineState> {
+        self.create_graphics_pipeline_state_with_flags(
+            pipeline_ci,
+            PipelineStateObjectCreateFlags::None,
+        )
+    }
+
+    // The shaders of the pipeline may still be compiling asynchronously
+    pub fn create_graphics_pipeline_state_async(
+        &self,
+        pipeline_ci: &GraphicsPipelineStateCreateInfo,
+    ) -> PipelineStateFuture {
+        PipelineStateFuture::new(
+            self.create_graphics_pipeline_state_with_flags(
+                pipeline_ci,
+                PipelineStateObjectCreateFlags::Asynchronous,
+            )
+            .ok_or(AsyncCreationError::CreationFailed(None)),
+        )
+    }
+
     pub fn create_compute_pipeline_state(
         &self,
         pipeline_ci: &bindings::ComputePipelineStateCreateInfo,
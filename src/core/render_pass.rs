@@ -0,0 +1,38 @@
+use crate::bindings;
+
+use super::device_object::{AsDeviceObject, DeviceObject};
+
+pub struct RenderPass {
+    pub(crate) render_pass: *mut bindings::IRenderPass,
+    virtual_functions: *mut bindings::IRenderPassVtbl,
+
+    device_object: DeviceObject,
+}
+
+impl AsDeviceObject for RenderPass {
+    fn as_device_object(&self) -> &DeviceObject {
+        &self.device_object
+    }
+}
+
+impl RenderPass {
+    pub(crate) fn new(render_pass_ptr: *mut bindings::IRenderPass) -> Self {
+        RenderPass {
+            render_pass: render_pass_ptr,
+            virtual_functions: unsafe { (*render_pass_ptr).pVtbl },
+            device_object: DeviceObject::new(render_pass_ptr as *mut bindings::IDeviceObject),
+        }
+    }
+
+    pub fn get_desc(&self) -> &bindings::RenderPassDesc {
+        unsafe {
+            ((*self.virtual_functions)
+                .DeviceObject
+                .GetDesc
+                .unwrap_unchecked()(self.render_pass as *mut bindings::IDeviceObject)
+                as *const bindings::RenderPassDesc)
+                .as_ref()
+                .unwrap_unchecked()
+        }
+    }
+}
@@ -1,19 +1,37 @@
 use std::{
-    os::{raw::c_void, unix::ffi::OsStrExt},
+    ffi::{CStr, CString},
+    os::{
+        raw::{c_char, c_void},
+        unix::ffi::OsStrExt,
+    },
     path::PathBuf,
 };
 
+use bitflags::bitflags;
+
 use crate::bindings::{self, Version};
 
 use super::{
     device_object::{AsDeviceObject, DeviceObject},
     graphics_types::ShaderType,
+    shader_source_factory::ShaderSourceInputStreamFactory,
 };
 
 pub enum ShaderSource<'a> {
-    FilePath(PathBuf), // TODO Option<IShaderSourceInputStreamFactory>
+    // Loaded through the source stream factory of the create info
+    FilePath(PathBuf),
     SourceCode(&'a str),
-    ByteCode(*const c_void, usize),
+    ByteCode(&'a [u8]),
+    // E.g. bytecode loaded from a file
+    OwnedByteCode(Vec<u8>),
+}
+
+impl<'a> ShaderSource<'a> {
+    pub fn from_spirv(code: &'a [u32]) -> Self {
+        ShaderSource::ByteCode(unsafe {
+            std::slice::from_raw_parts(code.as_ptr() as *const u8, std::mem::size_of_val(code))
+        })
+    }
 }
 
 pub enum ShaderLanguage {
@@ -42,6 +60,17 @@ impl From<&ShaderLanguage> for bindings::SHADER_SOURCE_LANGUAGE {
     }
 }
 
+bitflags! {
+    pub struct ShaderCompileFlags: bindings::SHADER_COMPILE_FLAGS {
+        const None                    = bindings::SHADER_COMPILE_FLAG_NONE;
+        const EnableUnboundedArrays   = bindings::SHADER_COMPILE_FLAG_ENABLE_UNBOUNDED_ARRAYS;
+        const SkipReflection          = bindings::SHADER_COMPILE_FLAG_SKIP_REFLECTION;
+        const Asynchronous            = bindings::SHADER_COMPILE_FLAG_ASYNCHRONOUS;
+        const PackMatrixRowMajor      = bindings::SHADER_COMPILE_FLAG_PACK_MATRIX_ROW_MAJOR;
+        const HlslToSpirvViaGlsl      = bindings::SHADER_COMPILE_FLAG_HLSL_TO_SPIRV_VIA_GLSL;
+    }
+}
+
 pub enum ShaderCompiler {
     Default,
     GLSLANG,
@@ -70,22 +99,222 @@ impl Default for bindings::ShaderResourceDesc {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShaderStatus {
+    Uninitialized,
+    Compiling,
+    Ready,
+    Failed,
+}
+
+impl From<bindings::SHADER_STATUS> for ShaderStatus {
+    fn from(value: bindings::SHADER_STATUS) -> Self {
+        match value as bindings::_SHADER_STATUS {
+            bindings::SHADER_STATUS_COMPILING => ShaderStatus::Compiling,
+            bindings::SHADER_STATUS_READY => ShaderStatus::Ready,
+            bindings::SHADER_STATUS_FAILED => ShaderStatus::Failed,
+            _ => ShaderStatus::Uninitialized,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShaderResourceType {
+    Unknown,
+    ConstantBuffer,
+    TextureSRV,
+    BufferSRV,
+    TextureUAV,
+    BufferUAV,
+    Sampler,
+    InputAttachment,
+    AccelStruct,
+}
+
+impl From<bindings::SHADER_RESOURCE_TYPE> for ShaderResourceType {
+    fn from(value: bindings::SHADER_RESOURCE_TYPE) -> Self {
+        match value as bindings::_SHADER_RESOURCE_TYPE {
+            bindings::SHADER_RESOURCE_TYPE_CONSTANT_BUFFER => ShaderResourceType::ConstantBuffer,
+            bindings::SHADER_RESOURCE_TYPE_TEXTURE_SRV => ShaderResourceType::TextureSRV,
+            bindings::SHADER_RESOURCE_TYPE_BUFFER_SRV => ShaderResourceType::BufferSRV,
+            bindings::SHADER_RESOURCE_TYPE_TEXTURE_UAV => ShaderResourceType::TextureUAV,
+            bindings::SHADER_RESOURCE_TYPE_BUFFER_UAV => ShaderResourceType::BufferUAV,
+            bindings::SHADER_RESOURCE_TYPE_SAMPLER => ShaderResourceType::Sampler,
+            bindings::SHADER_RESOURCE_TYPE_INPUT_ATTACHMENT => ShaderResourceType::InputAttachment,
+            bindings::SHADER_RESOURCE_TYPE_ACCEL_STRUCT => ShaderResourceType::AccelStruct,
+            _ => ShaderResourceType::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ShaderResourceDesc {
+    pub name: String,
+    pub resource_type: ShaderResourceType,
+    pub array_size: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShaderCodeVariableClass {
+    Unknown,
+    Scalar,
+    Vector,
+    MatrixRows,
+    MatrixColumns,
+    Struct,
+}
+
+impl From<bindings::SHADER_CODE_VARIABLE_CLASS> for ShaderCodeVariableClass {
+    fn from(value: bindings::SHADER_CODE_VARIABLE_CLASS) -> Self {
+        match value as bindings::_SHADER_CODE_VARIABLE_CLASS {
+            bindings::SHADER_CODE_VARIABLE_CLASS_SCALAR => ShaderCodeVariableClass::Scalar,
+            bindings::SHADER_CODE_VARIABLE_CLASS_VECTOR => ShaderCodeVariableClass::Vector,
+            bindings::SHADER_CODE_VARIABLE_CLASS_MATRIX_ROWS => ShaderCodeVariableClass::MatrixRows,
+            bindings::SHADER_CODE_VARIABLE_CLASS_MATRIX_COLUMNS => {
+                ShaderCodeVariableClass::MatrixColumns
+            }
+            bindings::SHADER_CODE_VARIABLE_CLASS_STRUCT => ShaderCodeVariableClass::Struct,
+            _ => ShaderCodeVariableClass::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShaderCodeBasicType {
+    Unknown,
+    Void,
+    Bool,
+    Int,
+    Int8,
+    Int16,
+    Int64,
+    Uint,
+    Uint8,
+    Uint16,
+    Uint64,
+    Float,
+    Float16,
+    Double,
+    Min8Float,
+    Min10Float,
+    Min16Float,
+    Min12Int,
+    Min16Int,
+    Min16Uint,
+    String,
+}
+
+impl From<bindings::SHADER_CODE_BASIC_TYPE> for ShaderCodeBasicType {
+    fn from(value: bindings::SHADER_CODE_BASIC_TYPE) -> Self {
+        match value as bindings::_SHADER_CODE_BASIC_TYPE {
+            bindings::SHADER_CODE_BASIC_TYPE_VOID => ShaderCodeBasicType::Void,
+            bindings::SHADER_CODE_BASIC_TYPE_BOOL => ShaderCodeBasicType::Bool,
+            bindings::SHADER_CODE_BASIC_TYPE_INT => ShaderCodeBasicType::Int,
+            bindings::SHADER_CODE_BASIC_TYPE_INT8 => ShaderCodeBasicType::Int8,
+            bindings::SHADER_CODE_BASIC_TYPE_INT16 => ShaderCodeBasicType::Int16,
+            bindings::SHADER_CODE_BASIC_TYPE_INT64 => ShaderCodeBasicType::Int64,
+            bindings::SHADER_CODE_BASIC_TYPE_UINT => ShaderCodeBasicType::Uint,
+            bindings::SHADER_CODE_BASIC_TYPE_UINT8 => ShaderCodeBasicType::Uint8,
+            bindings::SHADER_CODE_BASIC_TYPE_UINT16 => ShaderCodeBasicType::Uint16,
+            bindings::SHADER_CODE_BASIC_TYPE_UINT64 => ShaderCodeBasicType::Uint64,
+            bindings::SHADER_CODE_BASIC_TYPE_FLOAT => ShaderCodeBasicType::Float,
+            bindings::SHADER_CODE_BASIC_TYPE_FLOAT16 => ShaderCodeBasicType::Float16,
+            bindings::SHADER_CODE_BASIC_TYPE_DOUBLE => ShaderCodeBasicType::Double,
+            bindings::SHADER_CODE_BASIC_TYPE_MIN8FLOAT => ShaderCodeBasicType::Min8Float,
+            bindings::SHADER_CODE_BASIC_TYPE_MIN10FLOAT => ShaderCodeBasicType::Min10Float,
+            bindings::SHADER_CODE_BASIC_TYPE_MIN16FLOAT => ShaderCodeBasicType::Min16Float,
+            bindings::SHADER_CODE_BASIC_TYPE_MIN12INT => ShaderCodeBasicType::Min12Int,
+            bindings::SHADER_CODE_BASIC_TYPE_MIN16INT => ShaderCodeBasicType::Min16Int,
+            bindings::SHADER_CODE_BASIC_TYPE_MIN16UINT => ShaderCodeBasicType::Min16Uint,
+            bindings::SHADER_CODE_BASIC_TYPE_STRING => ShaderCodeBasicType::String,
+            _ => ShaderCodeBasicType::Unknown,
+        }
+    }
+}
+
+// A variable of a constant buffer. Struct variables have members, whose offsets are relative
+// to the offset of the struct.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ShaderCodeVariableDesc {
+    pub name: String,
+    pub type_name: String,
+    pub class: ShaderCodeVariableClass,
+    pub basic_type: ShaderCodeBasicType,
+    pub num_rows: u8,
+    pub num_columns: u8,
+    pub offset: u32,
+    // 0 for variables that are not arrays
+    pub array_size: u32,
+    pub members: Vec<ShaderCodeVariableDesc>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ShaderCodeBufferDesc {
+    pub size: u32,
+    pub variables: Vec<ShaderCodeVariableDesc>,
+}
+
+fn string_from_ptr(string: *const c_char) -> String {
+    if string.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(string) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+fn code_variables_from_raw(
+    variables: *const bindings::ShaderCodeVariableDesc,
+    count: u32,
+) -> Vec<ShaderCodeVariableDesc> {
+    if variables.is_null() {
+        return Vec::new();
+    }
+
+    unsafe { std::slice::from_raw_parts(variables, count as usize) }
+        .iter()
+        .map(|variable| ShaderCodeVariableDesc {
+            name: string_from_ptr(variable.Name),
+            type_name: string_from_ptr(variable.TypeName),
+            class: ShaderCodeVariableClass::from(variable.Class),
+            basic_type: ShaderCodeBasicType::from(variable.BasicType),
+            num_rows: variable.NumRows,
+            num_columns: variable.NumColumns,
+            offset: variable.Offset,
+            array_size: variable.ArraySize,
+            members: code_variables_from_raw(variable.pMembers, variable.NumMembers),
+        })
+        .collect()
+}
+
 pub struct ShaderDesc<'a> {
     name: &'a std::ffi::CStr,
     shader_type: ShaderType,
     use_combined_texture_samplers: bool,
-    combined_sampler_suffix: std::ffi::CString,
+    combined_sampler_suffix: &'a std::ffi::CStr,
 }
 
 pub struct ShaderCreateInfo<'a> {
-    source: ShaderSource<'a>,
-    // TODO IShaderSourceInputStreamFactory
+    pub(crate) source: ShaderSource<'a>,
+    pub(crate) file_path: Option<CString>,
+    // Also resolves the `#include` directives of the source
+    pub(crate) source_stream_factory: Option<&'a ShaderSourceInputStreamFactory>,
     entry_point: &'a std::ffi::CStr,
-    macros: Vec<(&'a std::ffi::CStr, &'a std::ffi::CStr)>,
+    // Kept in the layout of the engine, which reads them when the shader is created
+    macros: Vec<bindings::ShaderMacro>,
     desc: ShaderDesc<'a>,
     source_language: ShaderLanguage,
     compiler: ShaderCompiler,
-    language_version: Version,
+    hlsl_version: Version,
+    glsl_version: Version,
+    glessl_version: Version,
+    msl_version: Version,
+    compile_flags: ShaderCompileFlags,
+    load_constant_buffer_reflection: bool,
+    // Appended to the GLSL source after the version directive
+    glsl_extensions: Option<&'a std::ffi::CStr>,
+    webgpu_emulated_array_index_suffix: Option<&'a std::ffi::CStr>,
 }
 
 impl<'a> ShaderCreateInfo<'a> {
@@ -94,14 +323,28 @@ impl<'a> ShaderCreateInfo<'a> {
         source: ShaderSource<'a>,
         shader_type: ShaderType,
     ) -> Self {
+        let file_path = match &source {
+            ShaderSource::FilePath(path) => CString::new(path.as_os_str().as_bytes()).ok(),
+            _ => None,
+        };
+
         ShaderCreateInfo {
             source: source,
+            file_path,
+            source_stream_factory: None,
             entry_point: c"main",
             macros: Vec::new(),
             desc: ShaderDesc::new(name, shader_type),
             source_language: ShaderLanguage::Default,
             compiler: ShaderCompiler::Default,
-            language_version: Version { Major: 0, Minor: 0 },
+            hlsl_version: Version { Major: 0, Minor: 0 },
+            glsl_version: Version { Major: 0, Minor: 0 },
+            glessl_version: Version { Major: 0, Minor: 0 },
+            msl_version: Version { Major: 0, Minor: 0 },
+            compile_flags: ShaderCompileFlags::None,
+            load_constant_buffer_reflection: false,
+            glsl_extensions: None,
+            webgpu_emulated_array_index_suffix: None,
         }
     }
 
@@ -110,12 +353,23 @@ impl<'a> ShaderCreateInfo<'a> {
         self
     }
 
+    pub fn source_stream_factory(
+        mut self,
+        source_stream_factory: &'a ShaderSourceInputStreamFactory,
+    ) -> ShaderCreateInfo<'a> {
+        self.source_stream_factory = Some(source_stream_factory);
+        self
+    }
+
     pub fn add_macro(
         mut self,
         name: &'a std::ffi::CStr,
         definition: &'a std::ffi::CStr,
     ) -> ShaderCreateInfo<'a> {
-        self.macros.push((name, definition));
+        self.macros.push(bindings::ShaderMacro {
+            Name: name.as_ptr(),
+            Definition: definition.as_ptr(),
+        });
         self
     }
 
@@ -127,6 +381,15 @@ impl<'a> ShaderCreateInfo<'a> {
         self
     }
 
+    // "_sampler" by default
+    pub fn combined_sampler_suffix(
+        mut self,
+        combined_sampler_suffix: &'a std::ffi::CStr,
+    ) -> ShaderCreateInfo<'a> {
+        self.desc.combined_sampler_suffix = combined_sampler_suffix;
+        self
+    }
+
     pub fn language(mut self, language: ShaderLanguage) -> ShaderCreateInfo<'a> {
         self.source_language = language;
         self
@@ -137,48 +400,131 @@ impl<'a> ShaderCreateInfo<'a> {
         self
     }
 
-    pub fn language_version(mut self, version: Version) -> ShaderCreateInfo<'a> {
-        self.language_version = version;
+    // Sets the version of every language
+    pub fn language_version(self, version: Version) -> ShaderCreateInfo<'a> {
+        self.hlsl_version(version)
+            .glsl_version(version)
+            .glessl_version(version)
+            .msl_version(version)
+    }
+
+    pub fn hlsl_version(mut self, version: Version) -> ShaderCreateInfo<'a> {
+        self.hlsl_version = version;
+        self
+    }
+
+    pub fn glsl_version(mut self, version: Version) -> ShaderCreateInfo<'a> {
+        self.glsl_version = version;
         self
     }
+
+    pub fn glessl_version(mut self, version: Version) -> ShaderCreateInfo<'a> {
+        self.glessl_version = version;
+        self
+    }
+
+    pub fn msl_version(mut self, version: Version) -> ShaderCreateInfo<'a> {
+        self.msl_version = version;
+        self
+    }
+
+    pub fn compile_flags(mut self, compile_flags: ShaderCompileFlags) -> ShaderCreateInfo<'a> {
+        self.compile_flags = compile_flags;
+        self
+    }
+
+    pub fn glsl_extensions(mut self, glsl_extensions: &'a std::ffi::CStr) -> ShaderCreateInfo<'a> {
+        self.glsl_extensions = Some(glsl_extensions);
+        self
+    }
+
+    pub fn webgpu_emulated_array_index_suffix(
+        mut self,
+        suffix: &'a std::ffi::CStr,
+    ) -> ShaderCreateInfo<'a> {
+        self.webgpu_emulated_array_index_suffix = Some(suffix);
+        self
+    }
+
+    // Required by `Shader::get_constant_buffer_desc`
+    pub fn load_constant_buffer_reflection(
+        mut self,
+        load_constant_buffer_reflection: bool,
+    ) -> ShaderCreateInfo<'a> {
+        self.load_constant_buffer_reflection = load_constant_buffer_reflection;
+        self
+    }
+
+    // Writes everything the compiled shader depends on, except the source and the files it
+    // includes
+    pub(crate) fn write_compile_options(&self, key: &mut Vec<u8>) {
+        let mut write = |bytes: &[u8]| {
+            key.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            key.extend_from_slice(bytes);
+        };
+
+        write(self.entry_point.to_bytes());
+        for shader_macro in &self.macros {
+            unsafe {
+                write(CStr::from_ptr(shader_macro.Name).to_bytes());
+                write(CStr::from_ptr(shader_macro.Definition).to_bytes());
+            }
+        }
+        write(&bindings::SHADER_TYPE::from(&self.desc.shader_type).to_le_bytes());
+        write(&[self.desc.use_combined_texture_samplers as u8]);
+        write(self.desc.combined_sampler_suffix.to_bytes());
+        write(&bindings::SHADER_SOURCE_LANGUAGE::from(&self.source_language).to_le_bytes());
+        write(&bindings::SHADER_COMPILER::from(&self.compiler).to_le_bytes());
+        for version in [
+            &self.hlsl_version,
+            &self.glsl_version,
+            &self.glessl_version,
+            &self.msl_version,
+        ] {
+            write(&version.Major.to_le_bytes());
+            write(&version.Minor.to_le_bytes());
+        }
+        write(&self.compile_flags.bits().to_le_bytes());
+        write(self.glsl_extensions.map(CStr::to_bytes).unwrap_or_default());
+        write(
+            self.webgpu_emulated_array_index_suffix
+                .map(CStr::to_bytes)
+                .unwrap_or_default(),
+        );
+    }
 }
 
 impl From<&ShaderCreateInfo<'_>> for bindings::ShaderCreateInfo {
     fn from(value: &ShaderCreateInfo<'_>) -> Self {
-        let macros = Vec::from_iter(
-            value
-                .macros
-                .iter()
-                .map(|(name, def)| bindings::ShaderMacro {
-                    Name: name.as_ptr(),
-                    Definition: def.as_ptr(),
-                }),
-        );
         bindings::ShaderCreateInfo {
-            FilePath: match &value.source {
-                ShaderSource::FilePath(path) => path.as_os_str().as_bytes().as_ptr() as *const i8,
-                _ => std::ptr::null(),
-            },
-            pShaderSourceStreamFactory: std::ptr::null_mut(), // TODO
+            FilePath: value
+                .file_path
+                .as_ref()
+                .map_or(std::ptr::null(), |path| path.as_ptr()),
+            pShaderSourceStreamFactory: value
+                .source_stream_factory
+                .map_or(std::ptr::null_mut(), |factory| factory.factory),
             Source: match value.source {
                 ShaderSource::SourceCode(code) => code.as_ptr() as *const i8,
                 _ => std::ptr::null(),
             },
-            ByteCode: match value.source {
-                ShaderSource::ByteCode(code, _) => code,
+            ByteCode: match &value.source {
+                ShaderSource::ByteCode(code) => code.as_ptr() as *const c_void,
+                ShaderSource::OwnedByteCode(code) => code.as_ptr() as *const c_void,
                 _ => std::ptr::null(),
             },
             __bindgen_anon_1: bindings::ShaderCreateInfo__bindgen_ty_1 {
-                ByteCodeSize: match value.source {
-                    ShaderSource::ByteCode(_, size) => size,
+                ByteCodeSize: match &value.source {
+                    ShaderSource::ByteCode(code) => code.len(),
+                    ShaderSource::OwnedByteCode(code) => code.len(),
                     ShaderSource::SourceCode(code) => code.len(),
                     _ => 0,
                 },
             },
             EntryPoint: value.entry_point.as_ptr(),
             Macros: bindings::ShaderMacroArray {
-                Elements: macros.as_ptr(),
-                Count: macros.len() as u32,
+                Elements: value.macros.as_ptr(),
+                Count: value.macros.len() as u32,
             },
             Desc: bindings::ShaderDesc {
                 _DeviceObjectAttribs: {
@@ -193,26 +539,29 @@ impl From<&ShaderCreateInfo<'_>> for bindings::ShaderCreateInfo {
             SourceLanguage: bindings::SHADER_SOURCE_LANGUAGE::from(&value.source_language),
             ShaderCompiler: bindings::SHADER_COMPILER::from(&value.compiler),
             HLSLVersion: bindings::ShaderVersion {
-                Major: value.language_version.Major,
-                Minor: value.language_version.Minor,
+                Major: value.hlsl_version.Major,
+                Minor: value.hlsl_version.Minor,
             },
             GLSLVersion: bindings::ShaderVersion {
-                Major: value.language_version.Major,
-                Minor: value.language_version.Minor,
+                Major: value.glsl_version.Major,
+                Minor: value.glsl_version.Minor,
             },
             GLESSLVersion: bindings::ShaderVersion {
-                Major: value.language_version.Major,
-                Minor: value.language_version.Minor,
+                Major: value.glessl_version.Major,
+                Minor: value.glessl_version.Minor,
             },
             MSLVersion: bindings::ShaderVersion {
-                Major: value.language_version.Major,
-                Minor: value.language_version.Minor,
+                Major: value.msl_version.Major,
+                Minor: value.msl_version.Minor,
             },
-            // TODO
-            CompileFlags: 0,
-            LoadConstantBufferReflection: false,
-            GLSLExtensions: std::ptr::null(),
-            WebGPUEmulatedArrayIndexSuffix: std::ptr::null(),
+            CompileFlags: value.compile_flags.bits(),
+            LoadConstantBufferReflection: value.load_constant_buffer_reflection,
+            GLSLExtensions: value
+                .glsl_extensions
+                .map_or(std::ptr::null(), |extensions| extensions.as_ptr()),
+            WebGPUEmulatedArrayIndexSuffix: value
+                .webgpu_emulated_array_index_suffix
+                .map_or(std::ptr::null(), |suffix| suffix.as_ptr()),
         }
     }
 }
@@ -223,7 +572,7 @@ impl<'a> ShaderDesc<'a> {
             name: name,
             shader_type: shader_type,
             use_combined_texture_samplers: false,
-            combined_sampler_suffix: std::ffi::CString::new("_sampler").unwrap(),
+            combined_sampler_suffix: c"_sampler",
         }
     }
 }
@@ -260,38 +609,69 @@ impl Shader {
         }
     }
 
-    pub fn get_resources(&self) -> Vec<bindings::ShaderResourceDesc> {
+    pub fn get_resource_count(&self) -> u32 {
         unsafe {
-            let num_resources = (*self.virtual_functions)
+            (*self.virtual_functions)
                 .Shader
                 .GetResourceCount
-                .unwrap_unchecked()(self.shader);
+                .unwrap_unchecked()(self.shader)
+        }
+    }
 
-            let mut resources = Vec::with_capacity(num_resources as usize);
+    pub fn get_resource_desc(&self, index: u32) -> ShaderResourceDesc {
+        let mut resource_desc = bindings::ShaderResourceDesc::default();
+        unsafe {
+            (*self.virtual_functions)
+                .Shader
+                .GetResourceDesc
+                .unwrap_unchecked()(
+                self.shader, index, std::ptr::addr_of_mut!(resource_desc)
+            );
+        }
 
-            for index in 0..num_resources {
-                let resources_ptr = std::ptr::null_mut();
-                (*self.virtual_functions)
-                    .Shader
-                    .GetResourceDesc
-                    .unwrap_unchecked()(self.shader, index, resources_ptr);
-                resources.push(*resources_ptr);
-            }
-            resources
+        ShaderResourceDesc {
+            name: string_from_ptr(resource_desc.Name),
+            resource_type: ShaderResourceType::from(resource_desc.Type),
+            array_size: resource_desc.ArraySize,
         }
     }
 
-    pub fn get_constant_buffer_desc(&self, index: u32) -> Option<&bindings::ShaderCodeBufferDesc> {
-        unsafe {
+    pub fn get_resources(&self) -> Vec<ShaderResourceDesc> {
+        (0..self.get_resource_count())
+            .map(|index| self.get_resource_desc(index))
+            .collect()
+    }
+
+    // `index` is the index of the resource. Returns None if the resource is not a constant
+    // buffer or if the shader was not created with `load_constant_buffer_reflection`.
+    pub fn get_constant_buffer_desc(&self, index: u32) -> Option<ShaderCodeBufferDesc> {
+        let buffer_desc = unsafe {
             (*self.virtual_functions)
                 .Shader
                 .GetConstantBufferDesc
                 .unwrap_unchecked()(self.shader, index)
             .as_ref()
-        }
+        }?;
+
+        Some(ShaderCodeBufferDesc {
+            size: buffer_desc.Size,
+            variables: code_variables_from_raw(buffer_desc.pVariables, buffer_desc.NumVariables),
+        })
+    }
+
+    pub fn get_constant_buffer_desc_by_name(&self, name: &str) -> Option<ShaderCodeBufferDesc> {
+        (0..self.get_resource_count())
+            .find(|&index| {
+                let resource_desc = self.get_resource_desc(index);
+                resource_desc.resource_type == ShaderResourceType::ConstantBuffer
+                    && resource_desc.name == name
+            })
+            .and_then(|index| self.get_constant_buffer_desc(index))
     }
 
-    pub fn get_bytecode(&self, bytecode: *mut *const u8) -> u64 {
+    // Empty if the backend does not compile the shader to bytecode, like OpenGL
+    pub fn bytecode(&self) -> &[u8] {
+        let mut bytecode: *const c_void = std::ptr::null();
         let mut size: u64 = 0;
         unsafe {
             (*self.virtual_functions)
@@ -299,19 +679,24 @@ impl Shader {
                 .GetBytecode
                 .unwrap_unchecked()(
                 self.shader,
-                bytecode as *mut *const c_void,
+                std::ptr::addr_of_mut!(bytecode),
                 std::ptr::addr_of_mut!(size),
             );
         }
-        size
+
+        if bytecode.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(bytecode as *const u8, size as usize) }
+        }
     }
 
-    pub fn get_status(&self, wait_for_completion: bool) -> bindings::SHADER_STATUS {
-        unsafe {
+    pub fn get_status(&self, wait_for_completion: bool) -> ShaderStatus {
+        ShaderStatus::from(unsafe {
             (*self.virtual_functions)
                 .Shader
                 .GetStatus
                 .unwrap_unchecked()(self.shader, wait_for_completion)
-        }
+        })
     }
 }
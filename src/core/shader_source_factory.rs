@@ -0,0 +1,440 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    ffi::{CStr, CString},
+    hash::Hash,
+    os::raw::{c_char, c_int, c_void},
+    sync::atomic::AtomicI64,
+};
+
+use crate::bindings;
+
+use super::object::{
+    add_ref, get_reference_counters, query_interface, release, AsObject, Object, RustObject,
+};
+
+// Loads the shader source files and the files they include
+pub trait ShaderSourceProvider: Send + Sync {
+    // `name` is the path given to `ShaderSource::FilePath` or to an `#include` directive
+    fn load(&self, name: &str) -> Option<Vec<u8>>;
+}
+
+impl<F> ShaderSourceProvider for F
+where
+    F: Fn(&str) -> Option<Vec<u8>> + Send + Sync,
+{
+    fn load(&self, name: &str) -> Option<Vec<u8>> {
+        self(name)
+    }
+}
+
+// E.g. a map of `include_str!`s
+impl<K, V> ShaderSourceProvider for HashMap<K, V>
+where
+    K: Borrow<str> + Hash + Eq + Send + Sync,
+    V: AsRef<[u8]> + Send + Sync,
+{
+    fn load(&self, name: &str) -> Option<Vec<u8>> {
+        self.get(name).map(|source| source.as_ref().to_vec())
+    }
+}
+
+// The first provider that has the file is used
+impl ShaderSourceProvider for Vec<Box<dyn ShaderSourceProvider>> {
+    fn load(&self, name: &str) -> Option<Vec<u8>> {
+        self.iter().find_map(|provider| provider.load(name))
+    }
+}
+
+pub struct ShaderSourceInputStreamFactory {
+    pub(crate) factory: *mut bindings::IShaderSourceInputStreamFactory,
+
+    object: Object,
+}
+
+// The engine resolves the includes of asynchronous shaders on its compilation threads, so the
+// factories must be thread safe: the factories of the engine are, and the Rust providers are
+// Send + Sync. The reference counting of the objects is atomic.
+unsafe impl Send for ShaderSourceInputStreamFactory {}
+unsafe impl Sync for ShaderSourceInputStreamFactory {}
+
+impl AsObject for ShaderSourceInputStreamFactory {
+    fn as_object(&self) -> &Object {
+        &self.object
+    }
+}
+
+impl ShaderSourceInputStreamFactory {
+    pub(crate) fn new(factory: *mut bindings::IShaderSourceInputStreamFactory) -> Self {
+        ShaderSourceInputStreamFactory {
+            factory,
+            object: Object::new(factory as *mut bindings::IObject),
+        }
+    }
+
+    // Exposes the provider to the engine through the IShaderSourceInputStreamFactory interface
+    pub fn from_provider<P>(provider: P) -> Self
+    where
+        P: ShaderSourceProvider + 'static,
+    {
+        let factory = Box::into_raw(Box::new(ProviderFactory {
+            virtual_functions: std::ptr::addr_of!(PROVIDER_FACTORY_VTBL)
+                as *mut bindings::IShaderSourceInputStreamFactoryVtbl,
+            reference_count: AtomicI64::new(1),
+            provider: Box::new(provider),
+        }));
+
+        Self::new(factory as *mut bindings::IShaderSourceInputStreamFactory)
+    }
+
+    // Reads a whole file through the factory
+    pub fn load(&self, name: &str) -> Option<Vec<u8>> {
+        let name = CString::new(name).ok()?;
+
+        let mut stream: *mut bindings::IFileStream = std::ptr::null_mut();
+        unsafe {
+            (*(*self.factory).pVtbl)
+                .ShaderSourceInputStreamFactory
+                .CreateInputStream
+                .unwrap_unchecked()(
+                self.factory, name.as_ptr(), std::ptr::addr_of_mut!(stream)
+            );
+        }
+
+        if stream.is_null() {
+            return None;
+        }
+
+        // Releases the stream
+        let _object = Object::new(stream as *mut bindings::IObject);
+
+        unsafe {
+            let virtual_functions = (*stream).pVtbl;
+            let size = (*virtual_functions).FileStream.GetSize.unwrap_unchecked()(stream);
+            let mut data = vec![0u8; size];
+            (*virtual_functions).FileStream.Read.unwrap_unchecked()(
+                stream,
+                data.as_mut_ptr() as *mut c_void,
+                size,
+            )
+            .then_some(data)
+        }
+    }
+}
+
+#[repr(C)]
+struct ProviderFactory {
+    virtual_functions: *mut bindings::IShaderSourceInputStreamFactoryVtbl,
+    reference_count: AtomicI64,
+    provider: Box<dyn ShaderSourceProvider>,
+}
+
+#[repr(C)]
+struct MemoryFileStream {
+    virtual_functions: *mut bindings::IFileStreamVtbl,
+    reference_count: AtomicI64,
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl RustObject for ProviderFactory {
+    fn reference_count(&self) -> &AtomicI64 {
+        &self.reference_count
+    }
+}
+
+impl RustObject for MemoryFileStream {
+    fn reference_count(&self) -> &AtomicI64 {
+        &self.reference_count
+    }
+}
+
+unsafe extern "C" fn create_input_stream(
+    factory: *mut bindings::IShaderSourceInputStreamFactory,
+    name: *const c_char,
+    stream: *mut *mut bindings::IFileStream,
+) {
+    let factory = &*(factory as *const ProviderFactory);
+
+    *stream = match CStr::from_ptr(name)
+        .to_str()
+        .ok()
+        .and_then(|name| factory.provider.load(name))
+    {
+        Some(data) => Box::into_raw(Box::new(MemoryFileStream {
+            virtual_functions: std::ptr::addr_of!(MEMORY_FILE_STREAM_VTBL)
+                as *mut bindings::IFileStreamVtbl,
+            reference_count: AtomicI64::new(1),
+            data,
+            position: 0,
+        })) as *mut bindings::IFileStream,
+        None => std::ptr::null_mut(),
+    };
+}
+
+// The flags only silence the error the engine reports for missing files
+unsafe extern "C" fn create_input_stream2(
+    factory: *mut bindings::IShaderSourceInputStreamFactory,
+    name: *const c_char,
+    _flags: bindings::CREATE_SHADER_SOURCE_INPUT_STREAM_FLAGS,
+    stream: *mut *mut bindings::IFileStream,
+) {
+    create_input_stream(factory, name, stream)
+}
+
+static PROVIDER_FACTORY_VTBL: bindings::IShaderSourceInputStreamFactoryVtbl =
+    bindings::IShaderSourceInputStreamFactoryVtbl {
+        Object: bindings::IObjectMethods {
+            QueryInterface: Some(query_interface),
+            AddRef: Some(add_ref::<ProviderFactory>),
+            Release: Some(release::<ProviderFactory>),
+            GetReferenceCounters: Some(get_reference_counters),
+        },
+        ShaderSourceInputStreamFactory: bindings::IShaderSourceInputStreamFactoryMethods {
+            CreateInputStream: Some(create_input_stream),
+            CreateInputStream2: Some(create_input_stream2),
+        },
+    };
+
+unsafe fn as_memory_file_stream<'a>(
+    stream: *mut bindings::IFileStream,
+) -> &'a mut MemoryFileStream {
+    &mut *(stream as *mut MemoryFileStream)
+}
+
+unsafe extern "C" fn read(
+    stream: *mut bindings::IFileStream,
+    data: *mut c_void,
+    size: usize,
+) -> bool {
+    let stream = as_memory_file_stream(stream);
+    if stream.data.len() - stream.position < size {
+        return false;
+    }
+
+    std::ptr::copy_nonoverlapping(
+        stream.data.as_ptr().add(stream.position),
+        data as *mut u8,
+        size,
+    );
+    stream.position += size;
+    true
+}
+
+// Reads the rest of the stream
+unsafe extern "C" fn read_blob(
+    stream: *mut bindings::IFileStream,
+    data_blob: *mut bindings::IDataBlob,
+) {
+    let size = {
+        let stream = as_memory_file_stream(stream);
+        stream.data.len() - stream.position
+    };
+
+    let virtual_functions = (*data_blob).pVtbl;
+    (*virtual_functions).DataBlob.Resize.unwrap_unchecked()(data_blob, size);
+    let data = (*virtual_functions).DataBlob.GetDataPtr.unwrap_unchecked()(data_blob, 0);
+
+    read(stream, data, size);
+}
+
+unsafe extern "C" fn write(
+    _stream: *mut bindings::IFileStream,
+    _data: *const c_void,
+    _size: usize,
+) -> bool {
+    false
+}
+
+unsafe extern "C" fn get_size(stream: *mut bindings::IFileStream) -> usize {
+    as_memory_file_stream(stream).data.len()
+}
+
+unsafe extern "C" fn get_pos(stream: *mut bindings::IFileStream) -> usize {
+    as_memory_file_stream(stream).position
+}
+
+// `origin` is one of SEEK_SET, SEEK_CUR and SEEK_END
+unsafe extern "C" fn set_pos(
+    stream: *mut bindings::IFileStream,
+    offset: usize,
+    origin: c_int,
+) -> bool {
+    let stream = as_memory_file_stream(stream);
+    let position = match origin {
+        0 => Some(offset),
+        1 => stream.position.checked_add(offset),
+        2 => stream.data.len().checked_add(offset),
+        _ => None,
+    };
+
+    match position {
+        Some(position) if position <= stream.data.len() => {
+            stream.position = position;
+            true
+        }
+        _ => false,
+    }
+}
+
+unsafe extern "C" fn is_valid(_stream: *mut bindings::IFileStream) -> bool {
+    true
+}
+
+static MEMORY_FILE_STREAM_VTBL: bindings::IFileStreamVtbl = bindings::IFileStreamVtbl {
+    Object: bindings::IObjectMethods {
+        QueryInterface: Some(query_interface),
+        AddRef: Some(add_ref::<MemoryFileStream>),
+        Release: Some(release::<MemoryFileStream>),
+        GetReferenceCounters: Some(get_reference_counters),
+    },
+    FileStream: bindings::IFileStreamMethods {
+        Read: Some(read),
+        ReadBlob: Some(read_blob),
+        Write: Some(write),
+        GetSize: Some(get_size),
+        GetPos: Some(get_pos),
+        SetPos: Some(set_pos),
+        IsValid: Some(is_valid),
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Releases the stream when dropped
+    struct Stream {
+        stream: *mut bindings::IFileStream,
+        _object: Object,
+    }
+
+    impl Stream {
+        fn open(factory: &ShaderSourceInputStreamFactory, name: &CStr) -> Option<Stream> {
+            let mut stream: *mut bindings::IFileStream = std::ptr::null_mut();
+            unsafe {
+                (*(*factory.factory).pVtbl)
+                    .ShaderSourceInputStreamFactory
+                    .CreateInputStream
+                    .unwrap_unchecked()(
+                    factory.factory,
+                    name.as_ptr(),
+                    std::ptr::addr_of_mut!(stream),
+                );
+            }
+
+            (!stream.is_null()).then(|| Stream {
+                stream,
+                _object: Object::new(stream as *mut bindings::IObject),
+            })
+        }
+
+        fn methods(&self) -> &bindings::IFileStreamMethods {
+            unsafe { &(*(*self.stream).pVtbl).FileStream }
+        }
+
+        fn read(&self, size: usize) -> Option<Vec<u8>> {
+            let mut data = vec![0u8; size];
+            unsafe {
+                self.methods().Read.unwrap_unchecked()(
+                    self.stream,
+                    data.as_mut_ptr() as *mut c_void,
+                    size,
+                )
+            }
+            .then_some(data)
+        }
+
+        fn get_pos(&self) -> usize {
+            unsafe { self.methods().GetPos.unwrap_unchecked()(self.stream) }
+        }
+
+        fn set_pos(&self, offset: usize, origin: c_int) -> bool {
+            unsafe { self.methods().SetPos.unwrap_unchecked()(self.stream, offset, origin) }
+        }
+    }
+
+    fn factory() -> ShaderSourceInputStreamFactory {
+        ShaderSourceInputStreamFactory::from_provider(HashMap::from([
+            ("shader.psh", "0123456789"),
+            ("empty.fxh", ""),
+        ]))
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let factory = factory();
+
+        assert_eq!(factory.load("shader.psh"), Some(b"0123456789".to_vec()));
+        assert_eq!(factory.load("empty.fxh"), Some(Vec::new()));
+        assert_eq!(factory.load("missing.fxh"), None);
+        assert_eq!(factory.load("shader\0.psh"), None);
+
+        assert!(Stream::open(&factory, c"missing.fxh").is_none());
+    }
+
+    #[test]
+    fn first_provider_with_the_file_is_used() {
+        let providers: Vec<Box<dyn ShaderSourceProvider>> = vec![
+            Box::new(HashMap::from([("common.fxh", "first")])),
+            Box::new(|name: &str| Some(name.as_bytes().to_vec())),
+        ];
+        let factory = ShaderSourceInputStreamFactory::from_provider(providers);
+
+        assert_eq!(factory.load("common.fxh"), Some(b"first".to_vec()));
+        assert_eq!(factory.load("other.fxh"), Some(b"other.fxh".to_vec()));
+    }
+
+    #[test]
+    fn reads_advance_the_position() {
+        let factory = factory();
+        let stream = Stream::open(&factory, c"shader.psh").unwrap();
+
+        assert_eq!(stream.read(4), Some(b"0123".to_vec()));
+        assert_eq!(stream.get_pos(), 4);
+        assert_eq!(stream.read(6), Some(b"456789".to_vec()));
+        assert_eq!(stream.get_pos(), 10);
+        assert_eq!(stream.read(0), Some(Vec::new()));
+    }
+
+    #[test]
+    fn short_reads_fail_without_moving() {
+        let factory = factory();
+        let stream = Stream::open(&factory, c"shader.psh").unwrap();
+
+        assert_eq!(stream.read(11), None);
+        assert_eq!(stream.get_pos(), 0);
+
+        assert!(stream.read(8).is_some());
+        assert_eq!(stream.read(3), None);
+        assert_eq!(stream.get_pos(), 8);
+        assert_eq!(stream.read(2), Some(b"89".to_vec()));
+    }
+
+    #[test]
+    fn set_pos_bounds() {
+        let factory = factory();
+        let stream = Stream::open(&factory, c"shader.psh").unwrap();
+
+        assert!(stream.set_pos(10, 0));
+        assert_eq!(stream.get_pos(), 10);
+        assert!(!stream.set_pos(11, 0));
+        assert_eq!(stream.get_pos(), 10);
+
+        assert!(stream.set_pos(4, 0));
+        assert!(stream.set_pos(6, 1));
+        assert_eq!(stream.get_pos(), 10);
+        assert!(!stream.set_pos(1, 1));
+        assert!(!stream.set_pos(usize::MAX, 1));
+
+        assert!(stream.set_pos(0, 2));
+        assert_eq!(stream.get_pos(), 10);
+        assert!(!stream.set_pos(1, 2));
+
+        assert!(!stream.set_pos(0, 3));
+        assert_eq!(stream.get_pos(), 10);
+
+        assert!(stream.set_pos(2, 0));
+        assert_eq!(stream.read(3), Some(b"234".to_vec()));
+    }
+}
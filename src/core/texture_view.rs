@@ -4,6 +4,7 @@ use super::sampler::Sampler;
 use super::texture::Texture;
 
 use super::device_object::{AsDeviceObject, DeviceObject};
+use super::object::AsObject;
 
 pub enum TextureViewType {
     ShaderResource,
@@ -74,8 +75,30 @@ impl TextureView {
         todo!()
     }
 
+    // None for views that were not created from a `Texture`, see `query_texture`
     #[inline]
-    pub fn get_texture(&self) -> &Texture {
-        unsafe { self.texture.as_ref().unwrap_unchecked() }
+    pub fn get_texture(&self) -> Option<&Texture> {
+        unsafe { self.texture.as_ref() }
+    }
+
+    // Another reference to the same view, which keeps it alive. The reference can outlive the
+    // `Texture` the view was created from, so it does not point to it.
+    pub(crate) fn new_reference(&self) -> TextureView {
+        let texture_view = TextureView::new(self.texture_view, std::ptr::null());
+        texture_view.as_device_object().as_object().add_ref();
+        texture_view
+    }
+
+    // Views of the swap chain buffers are not created from a `Texture`, so the texture
+    // has to be queried from the view itself.
+    pub fn query_texture(&self) -> Texture {
+        let texture = Texture::new(unsafe {
+            (*self.virtual_functions)
+                .TextureView
+                .GetTexture
+                .unwrap_unchecked()(self.texture_view)
+        });
+        texture.as_device_object().as_object().add_ref();
+        texture
     }
 }
@@ -0,0 +1,639 @@
+use std::sync::{atomic::AtomicI64, Arc};
+
+use crate::bindings;
+
+use super::object::{
+    add_ref, get_reference_counters, query_interface, release, AsObject, Object, RustObject,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AsyncTaskStatus {
+    Unknown,
+    NotStarted,
+    Running,
+    Cancelled,
+    Complete,
+}
+
+impl From<bindings::ASYNC_TASK_STATUS> for AsyncTaskStatus {
+    fn from(value: bindings::ASYNC_TASK_STATUS) -> Self {
+        match value as bindings::_ASYNC_TASK_STATUS {
+            bindings::ASYNC_TASK_STATUS_NOT_STARTED => AsyncTaskStatus::NotStarted,
+            bindings::ASYNC_TASK_STATUS_RUNNING => AsyncTaskStatus::Running,
+            bindings::ASYNC_TASK_STATUS_CANCELLED => AsyncTaskStatus::Cancelled,
+            bindings::ASYNC_TASK_STATUS_COMPLETE => AsyncTaskStatus::Complete,
+            _ => AsyncTaskStatus::Unknown,
+        }
+    }
+}
+
+impl From<AsyncTaskStatus> for bindings::ASYNC_TASK_STATUS {
+    fn from(value: AsyncTaskStatus) -> Self {
+        (match value {
+            AsyncTaskStatus::Unknown => bindings::ASYNC_TASK_STATUS_UNKNOWN,
+            AsyncTaskStatus::NotStarted => bindings::ASYNC_TASK_STATUS_NOT_STARTED,
+            AsyncTaskStatus::Running => bindings::ASYNC_TASK_STATUS_RUNNING,
+            AsyncTaskStatus::Cancelled => bindings::ASYNC_TASK_STATUS_CANCELLED,
+            AsyncTaskStatus::Complete => bindings::ASYNC_TASK_STATUS_COMPLETE,
+        }) as bindings::ASYNC_TASK_STATUS
+    }
+}
+
+// A task the engine gives to the thread pool, e.g. the compilation of a shader. The tasks are
+// thread safe, they can be run on any thread.
+pub struct AsyncTask {
+    task: *mut bindings::IAsyncTask,
+    virtual_functions: *mut bindings::IAsyncTaskVtbl,
+
+    // The tasks that must be finished before this one is run
+    prerequisites: Vec<AsyncTask>,
+
+    object: Object,
+}
+
+unsafe impl Send for AsyncTask {}
+unsafe impl Sync for AsyncTask {}
+
+impl AsObject for AsyncTask {
+    fn as_object(&self) -> &Object {
+        &self.object
+    }
+}
+
+impl PartialEq for AsyncTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.task == other.task
+    }
+}
+
+impl AsyncTask {
+    // Adds a reference to the task, which is owned by the engine
+    fn new(task: *mut bindings::IAsyncTask, prerequisites: Vec<AsyncTask>) -> Self {
+        let async_task = AsyncTask {
+            task,
+            virtual_functions: unsafe { (*task).pVtbl },
+            prerequisites,
+            object: Object::new(task as *mut bindings::IObject),
+        };
+        async_task.as_object().add_ref();
+        async_task
+    }
+
+    // Runs the task if it has not been cancelled and its prerequisites are finished. Returns
+    // `AsyncTaskStatus::NotStarted` when the task has to be run again later: it should then be
+    // enqueued again.
+    pub fn run(&self, thread_id: u32) -> AsyncTaskStatus {
+        if self.get_status() == AsyncTaskStatus::Cancelled {
+            return AsyncTaskStatus::Cancelled;
+        }
+
+        if !self.are_prerequisites_finished() {
+            return AsyncTaskStatus::NotStarted;
+        }
+
+        self.set_status(AsyncTaskStatus::Running);
+
+        let status = AsyncTaskStatus::from(unsafe {
+            (*self.virtual_functions).AsyncTask.Run.unwrap_unchecked()(self.task, thread_id)
+        });
+
+        self.set_status(status);
+        status
+    }
+
+    pub fn are_prerequisites_finished(&self) -> bool {
+        self.prerequisites
+            .iter()
+            .all(|prerequisite| prerequisite.is_finished())
+    }
+
+    pub fn cancel(&self) {
+        unsafe {
+            (*self.virtual_functions)
+                .AsyncTask
+                .Cancel
+                .unwrap_unchecked()(self.task)
+        }
+    }
+
+    fn set_status(&self, status: AsyncTaskStatus) {
+        unsafe {
+            (*self.virtual_functions)
+                .AsyncTask
+                .SetStatus
+                .unwrap_unchecked()(self.task, status.into())
+        }
+    }
+
+    pub fn get_status(&self) -> AsyncTaskStatus {
+        AsyncTaskStatus::from(unsafe {
+            (*self.virtual_functions)
+                .AsyncTask
+                .GetStatus
+                .unwrap_unchecked()(self.task)
+        })
+    }
+
+    // Tasks with a higher priority should be run first
+    pub fn get_priority(&self) -> f32 {
+        unsafe {
+            (*self.virtual_functions)
+                .AsyncTask
+                .GetPriority
+                .unwrap_unchecked()(self.task)
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        unsafe {
+            (*self.virtual_functions)
+                .AsyncTask
+                .IsFinished
+                .unwrap_unchecked()(self.task)
+        }
+    }
+}
+
+// A thread pool the engine can run its asynchronous tasks on, e.g. the job system of the
+// application. The engine calls it from any thread.
+pub trait ThreadPool: Send + Sync {
+    // The task should be run with `AsyncTask::run` and enqueued again if that returns
+    // `AsyncTaskStatus::NotStarted`
+    fn enqueue_task(&self, task: AsyncTask);
+
+    // The priority of the task has changed. Returns false if the task is not in the queue.
+    fn reprioritize_task(&self, _task: &AsyncTask) -> bool {
+        false
+    }
+
+    fn reprioritize_all_tasks(&self) {}
+
+    // Removes the task from the queue, without running it. Returns false if the task is not in
+    // the queue.
+    fn remove_task(&self, _task: &AsyncTask) -> bool {
+        false
+    }
+
+    // Blocks until all the enqueued tasks are finished
+    fn wait_for_all_tasks(&self);
+
+    fn get_queue_size(&self) -> u32 {
+        0
+    }
+
+    fn get_running_task_count(&self) -> u32 {
+        0
+    }
+
+    fn stop_threads(&self) {}
+
+    // Runs a task of the queue on the calling thread. Returns false if no task was run.
+    fn process_task(&self, _thread_id: u32, _wait_for_task: bool) -> bool {
+        false
+    }
+}
+
+// The application may keep the thread pool it gives to the engine
+impl<T: ThreadPool + ?Sized> ThreadPool for Arc<T> {
+    fn enqueue_task(&self, task: AsyncTask) {
+        self.as_ref().enqueue_task(task)
+    }
+
+    fn reprioritize_task(&self, task: &AsyncTask) -> bool {
+        self.as_ref().reprioritize_task(task)
+    }
+
+    fn reprioritize_all_tasks(&self) {
+        self.as_ref().reprioritize_all_tasks()
+    }
+
+    fn remove_task(&self, task: &AsyncTask) -> bool {
+        self.as_ref().remove_task(task)
+    }
+
+    fn wait_for_all_tasks(&self) {
+        self.as_ref().wait_for_all_tasks()
+    }
+
+    fn get_queue_size(&self) -> u32 {
+        self.as_ref().get_queue_size()
+    }
+
+    fn get_running_task_count(&self) -> u32 {
+        self.as_ref().get_running_task_count()
+    }
+
+    fn stop_threads(&self) {
+        self.as_ref().stop_threads()
+    }
+
+    fn process_task(&self, thread_id: u32, wait_for_task: bool) -> bool {
+        self.as_ref().process_task(thread_id, wait_for_task)
+    }
+}
+
+// Exposes a `ThreadPool` to the engine through the IThreadPool interface
+pub struct ThreadPoolObject {
+    pub(crate) thread_pool: *mut bindings::IThreadPool,
+
+    object: Object,
+}
+
+impl AsObject for ThreadPoolObject {
+    fn as_object(&self) -> &Object {
+        &self.object
+    }
+}
+
+impl ThreadPoolObject {
+    pub fn new<T>(thread_pool: T) -> Self
+    where
+        T: ThreadPool + 'static,
+    {
+        let thread_pool = Box::into_raw(Box::new(RustThreadPool {
+            virtual_functions: std::ptr::addr_of!(RUST_THREAD_POOL_VTBL)
+                as *mut bindings::IThreadPoolVtbl,
+            reference_count: AtomicI64::new(1),
+            thread_pool: Box::new(thread_pool),
+        })) as *mut bindings::IThreadPool;
+
+        ThreadPoolObject {
+            thread_pool,
+            object: Object::new(thread_pool as *mut bindings::IObject),
+        }
+    }
+}
+
+#[repr(C)]
+struct RustThreadPool {
+    virtual_functions: *mut bindings::IThreadPoolVtbl,
+    reference_count: AtomicI64,
+    thread_pool: Box<dyn ThreadPool>,
+}
+
+impl RustObject for RustThreadPool {
+    fn reference_count(&self) -> &AtomicI64 {
+        &self.reference_count
+    }
+}
+
+unsafe fn as_thread_pool<'a>(thread_pool: *mut bindings::IThreadPool) -> &'a dyn ThreadPool {
+    (*(thread_pool as *const RustThreadPool))
+        .thread_pool
+        .as_ref()
+}
+
+unsafe extern "C" fn enqueue_task(
+    thread_pool: *mut bindings::IThreadPool,
+    task: *mut bindings::IAsyncTask,
+    prerequisites: *mut *mut bindings::IAsyncTask,
+    num_prerequisites: u32,
+) {
+    let prerequisites = if prerequisites.is_null() {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(prerequisites, num_prerequisites as usize)
+            .iter()
+            .filter(|prerequisite| !prerequisite.is_null())
+            .map(|&prerequisite| AsyncTask::new(prerequisite, Vec::new()))
+            .collect()
+    };
+
+    as_thread_pool(thread_pool).enqueue_task(AsyncTask::new(task, prerequisites));
+}
+
+unsafe extern "C" fn reprioritize_task(
+    thread_pool: *mut bindings::IThreadPool,
+    task: *mut bindings::IAsyncTask,
+) -> bool {
+    as_thread_pool(thread_pool).reprioritize_task(&AsyncTask::new(task, Vec::new()))
+}
+
+unsafe extern "C" fn reprioritize_all_tasks(thread_pool: *mut bindings::IThreadPool) {
+    as_thread_pool(thread_pool).reprioritize_all_tasks()
+}
+
+unsafe extern "C" fn remove_task(
+    thread_pool: *mut bindings::IThreadPool,
+    task: *mut bindings::IAsyncTask,
+) -> bool {
+    as_thread_pool(thread_pool).remove_task(&AsyncTask::new(task, Vec::new()))
+}
+
+unsafe extern "C" fn wait_for_all_tasks(thread_pool: *mut bindings::IThreadPool) {
+    as_thread_pool(thread_pool).wait_for_all_tasks()
+}
+
+unsafe extern "C" fn get_queue_size(thread_pool: *mut bindings::IThreadPool) -> u32 {
+    as_thread_pool(thread_pool).get_queue_size()
+}
+
+unsafe extern "C" fn get_running_task_count(thread_pool: *mut bindings::IThreadPool) -> u32 {
+    as_thread_pool(thread_pool).get_running_task_count()
+}
+
+unsafe extern "C" fn stop_threads(thread_pool: *mut bindings::IThreadPool) {
+    as_thread_pool(thread_pool).stop_threads()
+}
+
+unsafe extern "C" fn process_task(
+    thread_pool: *mut bindings::IThreadPool,
+    thread_id: u32,
+    wait_for_task: bool,
+) -> bool {
+    as_thread_pool(thread_pool).process_task(thread_id, wait_for_task)
+}
+
+static RUST_THREAD_POOL_VTBL: bindings::IThreadPoolVtbl = bindings::IThreadPoolVtbl {
+    Object: bindings::IObjectMethods {
+        QueryInterface: Some(query_interface),
+        AddRef: Some(add_ref::<RustThreadPool>),
+        Release: Some(release::<RustThreadPool>),
+        GetReferenceCounters: Some(get_reference_counters),
+    },
+    ThreadPool: bindings::IThreadPoolMethods {
+        EnqueueTask: Some(enqueue_task),
+        ReprioritizeTask: Some(reprioritize_task),
+        ReprioritizeAllTasks: Some(reprioritize_all_tasks),
+        RemoveTask: Some(remove_task),
+        WaitForAllTasks: Some(wait_for_all_tasks),
+        GetQueueSize: Some(get_queue_size),
+        GetRunningTaskCount: Some(get_running_task_count),
+        StopThreads: Some(stop_threads),
+        ProcessTask: Some(process_task),
+    },
+};
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    };
+
+    use super::*;
+
+    type RunFn = dyn Fn(u32) -> AsyncTaskStatus + Send + Sync;
+
+    // An IAsyncTask implemented in Rust. `run` is given the number of previous runs.
+    #[repr(C)]
+    struct FakeTask {
+        virtual_functions: *mut bindings::IAsyncTaskVtbl,
+        reference_count: AtomicI64,
+        status: Mutex<AsyncTaskStatus>,
+        priority: Mutex<f32>,
+        run_count: AtomicU32,
+        run: Box<RunFn>,
+    }
+
+    impl RustObject for FakeTask {
+        fn reference_count(&self) -> &AtomicI64 {
+            &self.reference_count
+        }
+    }
+
+    unsafe fn as_fake_task<'a>(task: *mut bindings::IAsyncTask) -> &'a FakeTask {
+        &*(task as *const FakeTask)
+    }
+
+    unsafe extern "C" fn run(
+        task: *mut bindings::IAsyncTask,
+        _thread_id: u32,
+    ) -> bindings::ASYNC_TASK_STATUS {
+        let task = as_fake_task(task);
+        let run_count = task.run_count.fetch_add(1, Ordering::SeqCst);
+        (task.run)(run_count).into()
+    }
+
+    unsafe extern "C" fn cancel(task: *mut bindings::IAsyncTask) {
+        set_status(task, AsyncTaskStatus::Cancelled.into())
+    }
+
+    unsafe extern "C" fn set_status(
+        task: *mut bindings::IAsyncTask,
+        status: bindings::ASYNC_TASK_STATUS,
+    ) {
+        *as_fake_task(task).status.lock().unwrap() = AsyncTaskStatus::from(status)
+    }
+
+    unsafe extern "C" fn get_status(
+        task: *mut bindings::IAsyncTask,
+    ) -> bindings::ASYNC_TASK_STATUS {
+        (*as_fake_task(task).status.lock().unwrap()).into()
+    }
+
+    unsafe extern "C" fn set_priority(task: *mut bindings::IAsyncTask, priority: f32) {
+        *as_fake_task(task).priority.lock().unwrap() = priority
+    }
+
+    unsafe extern "C" fn get_priority(task: *mut bindings::IAsyncTask) -> f32 {
+        *as_fake_task(task).priority.lock().unwrap()
+    }
+
+    unsafe extern "C" fn is_finished(task: *mut bindings::IAsyncTask) -> bool {
+        matches!(
+            AsyncTaskStatus::from(get_status(task)),
+            AsyncTaskStatus::Cancelled | AsyncTaskStatus::Complete
+        )
+    }
+
+    unsafe extern "C" fn wait(_task: *mut bindings::IAsyncTask) {
+        unimplemented!()
+    }
+
+    static FAKE_TASK_VTBL: bindings::IAsyncTaskVtbl = bindings::IAsyncTaskVtbl {
+        Object: bindings::IObjectMethods {
+            QueryInterface: Some(query_interface),
+            AddRef: Some(add_ref::<FakeTask>),
+            Release: Some(release::<FakeTask>),
+            GetReferenceCounters: Some(get_reference_counters),
+        },
+        AsyncTask: bindings::IAsyncTaskMethods {
+            Run: Some(run),
+            Cancel: Some(cancel),
+            SetStatus: Some(set_status),
+            GetStatus: Some(get_status),
+            SetPriority: Some(set_priority),
+            GetPriority: Some(get_priority),
+            IsFinished: Some(is_finished),
+            WaitForCompletion: Some(wait),
+            WaitUntilRunning: Some(wait),
+        },
+    };
+
+    // The reference of the test to a fake task
+    pub(crate) struct TestTask {
+        task: *mut bindings::IAsyncTask,
+        _object: Object,
+    }
+
+    impl TestTask {
+        pub(crate) fn new<F>(run: F) -> Self
+        where
+            F: Fn(u32) -> AsyncTaskStatus + Send + Sync + 'static,
+        {
+            let task = Box::into_raw(Box::new(FakeTask {
+                virtual_functions: std::ptr::addr_of!(FAKE_TASK_VTBL)
+                    as *mut bindings::IAsyncTaskVtbl,
+                reference_count: AtomicI64::new(1),
+                status: Mutex::new(AsyncTaskStatus::NotStarted),
+                priority: Mutex::new(0.0),
+                run_count: AtomicU32::new(0),
+                run: Box::new(run),
+            })) as *mut bindings::IAsyncTask;
+
+            TestTask {
+                task,
+                _object: Object::new(task as *mut bindings::IObject),
+            }
+        }
+
+        pub(crate) fn complete() -> Self {
+            Self::new(|_| AsyncTaskStatus::Complete)
+        }
+
+        // The task as the engine gives it to the thread pool
+        pub(crate) fn async_task(&self, prerequisites: &[&TestTask]) -> AsyncTask {
+            AsyncTask::new(
+                self.task,
+                prerequisites
+                    .iter()
+                    .map(|prerequisite| AsyncTask::new(prerequisite.task, Vec::new()))
+                    .collect(),
+            )
+        }
+
+        fn fake_task(&self) -> &FakeTask {
+            unsafe { as_fake_task(self.task) }
+        }
+
+        pub(crate) fn run_count(&self) -> u32 {
+            self.fake_task().run_count.load(Ordering::SeqCst)
+        }
+
+        pub(crate) fn status(&self) -> AsyncTaskStatus {
+            AsyncTaskStatus::from(unsafe { get_status(self.task) })
+        }
+
+        pub(crate) fn set_priority(&self, priority: f32) {
+            unsafe { set_priority(self.task, priority) }
+        }
+
+        pub(crate) fn reference_count(&self) -> i64 {
+            self.fake_task().reference_count.load(Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn run_sets_the_status() {
+        let task = TestTask::new(|_| AsyncTaskStatus::Complete);
+        let async_task = task.async_task(&[]);
+
+        assert_eq!(async_task.run(0), AsyncTaskStatus::Complete);
+        assert_eq!(task.status(), AsyncTaskStatus::Complete);
+        assert!(async_task.is_finished());
+        assert_eq!(task.run_count(), 1);
+    }
+
+    #[test]
+    fn cancelled_tasks_are_not_run() {
+        let task = TestTask::complete();
+        let async_task = task.async_task(&[]);
+
+        async_task.cancel();
+        assert_eq!(async_task.run(0), AsyncTaskStatus::Cancelled);
+        assert_eq!(task.run_count(), 0);
+    }
+
+    #[test]
+    fn prerequisites_must_be_finished() {
+        let prerequisite = TestTask::complete();
+        let task = TestTask::complete();
+        let async_task = task.async_task(&[&prerequisite]);
+
+        assert_eq!(async_task.run(0), AsyncTaskStatus::NotStarted);
+        assert_eq!(task.run_count(), 0);
+        assert_eq!(task.status(), AsyncTaskStatus::NotStarted);
+
+        prerequisite.async_task(&[]).run(0);
+        assert_eq!(async_task.run(0), AsyncTaskStatus::Complete);
+    }
+
+    #[test]
+    fn yielding_tasks_are_not_started() {
+        let task = TestTask::new(|run_count| match run_count {
+            0 => AsyncTaskStatus::NotStarted,
+            _ => AsyncTaskStatus::Complete,
+        });
+        let async_task = task.async_task(&[]);
+
+        assert_eq!(async_task.run(0), AsyncTaskStatus::NotStarted);
+        assert!(!async_task.is_finished());
+        assert_eq!(async_task.run(0), AsyncTaskStatus::Complete);
+        assert_eq!(task.run_count(), 2);
+    }
+
+    #[derive(Default)]
+    struct QueueThreadPool {
+        tasks: Mutex<Vec<AsyncTask>>,
+    }
+
+    impl ThreadPool for QueueThreadPool {
+        fn enqueue_task(&self, task: AsyncTask) {
+            self.tasks.lock().unwrap().push(task);
+        }
+
+        fn remove_task(&self, task: &AsyncTask) -> bool {
+            let mut tasks = self.tasks.lock().unwrap();
+            let count = tasks.len();
+            tasks.retain(|queued_task| queued_task != task);
+            tasks.len() != count
+        }
+
+        fn wait_for_all_tasks(&self) {}
+    }
+
+    #[test]
+    fn tasks_enqueued_through_the_interface() {
+        let queue = Arc::new(QueueThreadPool::default());
+        let thread_pool = ThreadPoolObject::new(queue.clone());
+        let methods = unsafe { &(*(*thread_pool.thread_pool).pVtbl).ThreadPool };
+
+        let prerequisite = TestTask::complete();
+        let task = TestTask::complete();
+
+        let mut prerequisites = [prerequisite.task, std::ptr::null_mut()];
+        unsafe {
+            methods.EnqueueTask.unwrap_unchecked()(
+                thread_pool.thread_pool,
+                task.task,
+                prerequisites.as_mut_ptr(),
+                prerequisites.len() as u32,
+            );
+        }
+
+        // The null prerequisite is ignored
+        {
+            let tasks = queue.tasks.lock().unwrap();
+            assert_eq!(tasks.len(), 1);
+            assert_eq!(tasks[0].prerequisites.len(), 1);
+        }
+        assert_eq!(task.reference_count(), 2);
+        assert_eq!(prerequisite.reference_count(), 2);
+
+        unsafe {
+            assert!(methods.RemoveTask.unwrap_unchecked()(
+                thread_pool.thread_pool,
+                task.task
+            ));
+            assert!(!methods.RemoveTask.unwrap_unchecked()(
+                thread_pool.thread_pool,
+                task.task
+            ));
+        }
+
+        assert!(queue.tasks.lock().unwrap().is_empty());
+        assert_eq!(task.reference_count(), 1);
+        assert_eq!(prerequisite.reference_count(), 1);
+    }
+}
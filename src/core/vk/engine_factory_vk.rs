@@ -2,12 +2,15 @@ use std::ffi::c_void;
 use std::os::unix::ffi::OsStrExt;
 use std::path::PathBuf;
 
+use raw_window_handle::{HandleError, HasDisplayHandle, HasWindowHandle};
+
 use crate::bindings;
 use crate::core::device_context::DeviceContext;
 use crate::core::engine_factory::EngineCreateInfo;
 use crate::core::engine_factory::EngineFactory;
 
 use crate::core::engine_factory::AsEngineFactory;
+use crate::core::native_window::get_native_window;
 use crate::core::render_device::RenderDevice;
 use crate::core::swap_chain::SwapChain;
 
@@ -299,4 +302,26 @@ impl EngineFactoryVk {
             Some(SwapChain::new(swap_chain_ptr))
         }
     }
+
+    // Creates a swap chain for a window created by another library. The error is returned when
+    // the handles of the window are unavailable or not supported, and `None` when the engine
+    // fails to create the swap chain.
+    pub fn create_swap_chain_for_window<W>(
+        &self,
+        device: &RenderDevice,
+        immediate_context: &DeviceContext,
+        swapchain_desc: &bindings::SwapChainDesc,
+        window: &W,
+    ) -> Result<Option<SwapChain>, HandleError>
+    where
+        W: HasWindowHandle + HasDisplayHandle + ?Sized,
+    {
+        let native_window = get_native_window(window)?;
+        Ok(self.create_swap_chain(
+            device,
+            immediate_context,
+            swapchain_desc,
+            Some(&native_window),
+        ))
+    }
 }
@@ -1,6 +1,9 @@
 use crate::{
-    core::{device_context::DeviceContext, render_device::RenderDevice, swap_chain::SwapChain},
-    tools::native_app::events::EventResult,
+    core::{device_context::DeviceContext, render_device::RenderDevice},
+    tools::{
+        native_app::events::{CursorState, EventResult},
+        render_surface::RenderSurface,
+    },
 };
 
 pub trait SampleBase {
@@ -8,18 +11,23 @@ pub trait SampleBase {
         render_device: RenderDevice,
         immediate_contexts: Vec<DeviceContext>,
         deferred_contexts: Vec<DeviceContext>,
-        swap_chain: &SwapChain,
+        render_surface: &dyn RenderSurface,
     ) -> Self;
 
     fn get_render_device(&self) -> &RenderDevice;
 
     fn get_immediate_context(&self) -> &DeviceContext;
 
-    fn render(&self, _swap_chain: &SwapChain) {}
+    fn render(&self, _render_surface: &dyn RenderSurface) {}
     fn update(&self, _current_time: f64, _elapsed_time: f64) {}
     fn get_name() -> &'static str;
     fn pre_window_resize(&mut self) {}
     fn window_resize(&mut self, _width: u32, _height: u32) {}
 
     fn handle_event(&mut self, _event: EventResult) {}
+
+    // Read every frame, e.g. to enable the relative mouse mode while the camera is controlled
+    fn get_cursor_state(&self) -> CursorState {
+        CursorState::default()
+    }
 }
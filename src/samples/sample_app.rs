@@ -1,40 +1,53 @@
+use std::{path::PathBuf, process::ExitCode};
+
 use imgui::{Ui, WindowFlags};
 
 use crate::{
     bindings::{self, NativeWindow},
     core::{
         device_context::ResourceStateTransitionMode,
-        engine_factory::{AsEngineFactory, EngineCreateInfo},
-        graphics_types::{AdapterType, GraphicsAdapterInfo, RenderDeviceType},
-        swap_chain::SwapChain,
-        vk::engine_factory_vk::{get_engine_factory_vk, EngineVkCreateInfo},
+        engine_factory::{AsEngineFactory, EngineCreateInfo, EngineFactory},
+        graphics_types::{AdapterType, DeviceFeatureState, GraphicsAdapterInfo, RenderDeviceType},
     },
     tools::{
+        image_utils::{count_mismatched_pixels, load_png, save_png},
         imgui::{
             events::imgui_handle_event,
             renderer::{ImguiRenderer, ImguiRendererCreateInfo},
         },
         native_app::{
-            app::{App, GoldenImageMode},
-            events::{EventHandler, EventResult},
+            app::{App, AppSettings, GoldenImageMode, WindowDesc},
+            events::{CursorState, EventHandler, EventResult},
         },
+        render_surface::{OffscreenSurface, RenderSurface},
+        screen_capture::ScreenCapture,
+        texture_reader::TextureReader,
+        timer::Timer,
     },
 };
 
+#[cfg(feature = "VULKAN_SUPPORTED")]
+use crate::core::vk::engine_factory_vk::{get_engine_factory_vk, EngineVkCreateInfo};
+
+#[cfg(feature = "OPENGL_SUPPORTED")]
+use crate::core::gl::engine_factory_gl::{get_engine_factory_gl, EngineGLCreateInfo};
+
 use super::sample::SampleBase;
 
 pub struct SampleApp<Sample: SampleBase> {
-    _app_title: String,
-    swap_chain: SwapChain,
+    app_title: String,
+    // The swap chain of the window, or an offscreen render target when running headless
+    surface: Box<dyn RenderSurface>,
 
-    _golden_image_mode: GoldenImageMode,
-    _golden_pixel_tolerance: u32,
+    golden_image_mode: GoldenImageMode,
+    golden_pixel_tolerance: u32,
 
     sample: Sample,
 
     vsync: bool,
 
     current_time: f64,
+    fixed_time_step: Option<f64>,
 
     _width: u16,
     _height: u16,
@@ -42,19 +55,37 @@ pub struct SampleApp<Sample: SampleBase> {
     imgui_renderer: ImguiRenderer,
 
     graphics_adapter: Option<GraphicsAdapterInfo>,
+
+    screen_capture: Option<ScreenCapture>,
+    texture_reader: TextureReader,
+    frame_index: u32,
+
+    exit_code: ExitCode,
+}
+
+fn get_render_device_type_short_string(device_type: &RenderDeviceType) -> &'static str {
+    match device_type {
+        RenderDeviceType::D3D11 => "D3D11",
+        RenderDeviceType::D3D12 => "D3D12",
+        RenderDeviceType::GL => "GL",
+        RenderDeviceType::GLES => "GLES",
+        RenderDeviceType::VULKAN => "VK",
+        RenderDeviceType::METAL => "MTL",
+        RenderDeviceType::WEBGPU => "WEBGPU",
+    }
 }
 
 impl<GenericSample: SampleBase> SampleApp<GenericSample> {
-    fn _get_title(&self) -> &str {
-        self._app_title.as_str()
+    fn get_title(&self) -> &str {
+        self.app_title.as_str()
     }
 
     fn window_resize(&mut self, width: u32, height: u32) {
         self.sample.pre_window_resize();
 
-        self.swap_chain.resize(width, height, None);
+        self.surface.resize(width, height);
 
-        let swap_chain_desc = self.swap_chain.get_desc();
+        let swap_chain_desc = self.surface.get_desc();
 
         self.sample
             .window_resize(swap_chain_desc.Width, swap_chain_desc.Height);
@@ -71,7 +102,7 @@ impl<GenericSample: SampleBase> SampleApp<GenericSample> {
     fn update_ui(&mut self) -> &mut Ui {
         let ui = self.imgui_renderer.new_frame();
 
-        let swap_chain_desc = self.swap_chain.get_desc();
+        let swap_chain_desc = self.surface.get_desc();
 
         let adapters_wnd_width = swap_chain_desc.Width.min(330);
 
@@ -108,35 +139,142 @@ impl<GenericSample: SampleBase> SampleApp<GenericSample> {
         let context = self.sample.get_immediate_context();
         context.clear_stats();
 
-        let rtv = self.swap_chain.get_current_back_buffer_rtv();
-        let dsv = self.swap_chain.get_depth_buffer_dsv();
+        let rtv = self.surface.get_current_back_buffer_rtv();
+        let dsv = self.surface.get_depth_buffer_dsv();
 
         context.set_render_targets(&[&rtv], Some(&dsv), ResourceStateTransitionMode::Transition);
 
-        self.sample.render(&self.swap_chain);
+        self.sample.render(self.surface.as_ref());
 
         // Restore default render target in case the sample has changed it
         context.set_render_targets(&[&rtv], Some(&dsv), ResourceStateTransitionMode::Transition);
     }
 
+    fn get_golden_image_path(&self) -> PathBuf {
+        let device_info = self.sample.get_render_device().get_device_info();
+
+        let app_name: String = self
+            .app_title
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        PathBuf::from(format!(
+            "{app_name}_gi_{}.png",
+            get_render_device_type_short_string(device_info.device_type())
+        ))
+    }
+
+    fn process_golden_image(&mut self) {
+        if matches!(self.golden_image_mode, GoldenImageMode::None) {
+            return;
+        }
+
+        let rtv = self.surface.get_current_back_buffer_rtv();
+
+        let Some((width, height, pixels)) = self.texture_reader.read(
+            self.sample.get_render_device(),
+            self.sample.get_immediate_context(),
+            &rtv,
+        ) else {
+            println!("Failed to read back the golden image");
+            self.exit_code = ExitCode::FAILURE;
+            return;
+        };
+
+        let golden_image_path = self.get_golden_image_path();
+
+        if let GoldenImageMode::Compare | GoldenImageMode::CompareUpdate = self.golden_image_mode {
+            let num_bad_pixels = match load_png(&golden_image_path) {
+                Ok((golden_width, golden_height, golden_pixels))
+                    if golden_width == width && golden_height == height =>
+                {
+                    count_mismatched_pixels(&golden_pixels, &pixels, self.golden_pixel_tolerance)
+                }
+                Ok((golden_width, golden_height, _)) => {
+                    println!(
+                        "Golden image dimensions ({golden_width}x{golden_height}) do not match the \
+                         frame dimensions ({width}x{height})"
+                    );
+                    (width * height) as usize
+                }
+                Err(error) => {
+                    println!(
+                        "Failed to load golden image {}: {error}",
+                        golden_image_path.display()
+                    );
+                    (width * height) as usize
+                }
+            };
+
+            if num_bad_pixels == 0 {
+                println!("Golden image validation PASSED");
+            } else {
+                println!("Golden image validation FAILED: {num_bad_pixels} inconsistent pixels");
+                self.exit_code = ExitCode::FAILURE;
+            }
+        }
+
+        if let GoldenImageMode::Capture | GoldenImageMode::CompareUpdate = self.golden_image_mode {
+            if let Err(error) = save_png(&golden_image_path, width, height, &pixels) {
+                println!(
+                    "Failed to save golden image {}: {error}",
+                    golden_image_path.display()
+                );
+                self.exit_code = ExitCode::FAILURE;
+            }
+        }
+    }
+
     fn present(&mut self) {
-        // TODO screen capture
+        // The back buffer must be read before it is presented
+        self.process_golden_image();
 
-        self.swap_chain.present(if self.vsync { 1 } else { 0 });
+        if let Some(screen_capture) = &mut self.screen_capture {
+            screen_capture.capture(
+                self.sample.get_immediate_context(),
+                &self.surface.get_current_back_buffer_rtv(),
+                self.frame_index,
+            );
 
-        // TODO screen capture
+            for error in screen_capture.take_errors() {
+                eprintln!("{error}");
+            }
+        }
+
+        self.surface.present(if self.vsync { 1 } else { 0 });
+
+        self.frame_index += 1;
     }
 }
 
 impl<GenericSample: SampleBase> App for SampleApp<GenericSample> {
+    fn get_window_desc(settings: &AppSettings) -> WindowDesc {
+        let mut window_desc =
+            WindowDesc::new(GenericSample::get_name(), settings.width, settings.height);
+        window_desc.fullscreen = settings.fullscreen;
+        window_desc
+    }
+
     fn new(
-        device_type: RenderDeviceType,
+        settings: &AppSettings,
         mut engine_create_info: EngineCreateInfo,
         window: Option<&NativeWindow>,
-        initial_width: u16,
-        initial_height: u16,
     ) -> Self {
-        let swap_chain_desc = bindings::SwapChainDesc::default();
+        // The back buffer is copied for golden images and frame dumps
+        let swap_chain_desc = bindings::SwapChainDesc {
+            Usage: bindings::SWAP_CHAIN_USAGE_RENDER_TARGET
+                | bindings::SWAP_CHAIN_USAGE_COPY_SOURCE,
+            ..Default::default()
+        };
+
+        if let Some(validation) = settings.validation {
+            engine_create_info.enable_validation = validation;
+        }
+
+        if settings.non_separable_programs {
+            engine_create_info.features.separable_programs = DeviceFeatureState::Disabled;
+        }
 
         //#[cfg(any(
         //    feature = "D3D11_SUPPORTED",
@@ -206,27 +344,79 @@ impl<GenericSample: SampleBase> App for SampleApp<GenericSample> {
             adapter_index
         }
 
+        fn select_adapter(
+            engine_factory: &EngineFactory,
+            engine_create_info: &mut EngineCreateInfo,
+            settings: &AppSettings,
+        ) -> Option<GraphicsAdapterInfo> {
+            let adapters =
+                engine_factory.enumerate_adapters(&engine_create_info.graphics_api_version);
+
+            if let Some(adapter_index) = find_adapter(
+                settings.adapter_index,
+                settings.adapter_type,
+                adapters.as_slice(),
+            ) {
+                engine_create_info.adapter_index.replace(adapter_index);
+                adapters.into_iter().nth(adapter_index)
+            } else {
+                None
+            }
+        }
+
         let (render_device, immediate_contexts, deferred_contexts, swap_chain, adapter) =
-            match device_type {
+            match settings.device_type {
                 RenderDeviceType::D3D11 => panic!(),
                 RenderDeviceType::D3D12 => panic!(),
+                #[cfg(feature = "OPENGL_SUPPORTED")]
+                RenderDeviceType::GL => {
+                    let engine_factory = get_engine_factory_gl();
+
+                    let chosen_adapter = select_adapter(
+                        engine_factory.as_engine_factory(),
+                        &mut engine_create_info,
+                        settings,
+                    );
+
+                    let engine_gl_create_info = EngineGLCreateInfo::new(engine_create_info, window);
+
+                    // Without a window, the engine attaches to the GL context created by the
+                    // headless runner
+                    let (render_device, immediate_context, swap_chain) = if window.is_some() {
+                        let (render_device, immediate_context, swap_chain) = engine_factory
+                            .create_device_and_swap_chain_gl(
+                                &engine_gl_create_info,
+                                &swap_chain_desc,
+                            )
+                            .unwrap();
+                        (render_device, immediate_context, Some(swap_chain))
+                    } else {
+                        let (render_device, immediate_context) = engine_factory
+                            .attach_to_active_gl_context(&engine_gl_create_info)
+                            .unwrap();
+                        (render_device, immediate_context, None)
+                    };
+
+                    (
+                        render_device,
+                        vec![immediate_context],
+                        Vec::new(),
+                        swap_chain,
+                        chosen_adapter,
+                    )
+                }
+                #[cfg(not(feature = "OPENGL_SUPPORTED"))]
                 RenderDeviceType::GL => panic!(),
                 RenderDeviceType::GLES => panic!(),
+                #[cfg(feature = "VULKAN_SUPPORTED")]
                 RenderDeviceType::VULKAN => {
                     let engine_factory = get_engine_factory_vk();
 
-                    let adapters = engine_factory
-                        .as_engine_factory()
-                        .enumerate_adapters(&engine_create_info.graphics_api_version);
-
-                    let chosen_adapter = if let Some(adapter_index) =
-                        find_adapter(None, AdapterType::Unknown, adapters.as_slice())
-                    {
-                        engine_create_info.adapter_index.replace(adapter_index);
-                        adapters.into_iter().nth(adapter_index)
-                    } else {
-                        None
-                    };
+                    let chosen_adapter = select_adapter(
+                        engine_factory.as_engine_factory(),
+                        &mut engine_create_info,
+                        settings,
+                    );
 
                     let engine_vk_create_info = EngineVkCreateInfo::new(engine_create_info);
 
@@ -234,14 +424,16 @@ impl<GenericSample: SampleBase> App for SampleApp<GenericSample> {
                         .create_device_and_contexts(&engine_vk_create_info)
                         .unwrap();
 
-                    let swap_chain = engine_factory
-                        .create_swap_chain(
-                            &render_device,
-                            immediate_contexts.first().unwrap(),
-                            &swap_chain_desc,
-                            window,
-                        )
-                        .unwrap();
+                    let swap_chain = window.map(|window| {
+                        engine_factory
+                            .create_swap_chain(
+                                &render_device,
+                                immediate_contexts.first().unwrap(),
+                                &swap_chain_desc,
+                                Some(window),
+                            )
+                            .unwrap()
+                    });
 
                     (
                         render_device,
@@ -251,51 +443,103 @@ impl<GenericSample: SampleBase> App for SampleApp<GenericSample> {
                         chosen_adapter,
                     )
                 }
+                #[cfg(not(feature = "VULKAN_SUPPORTED"))]
+                RenderDeviceType::VULKAN => panic!(),
                 RenderDeviceType::METAL => panic!(),
                 RenderDeviceType::WEBGPU => panic!(),
             };
 
+        let surface: Box<dyn RenderSurface> = match swap_chain {
+            Some(swap_chain) => Box::new(swap_chain),
+            None => Box::new(
+                OffscreenSurface::new(
+                    &render_device,
+                    &bindings::SwapChainDesc {
+                        Width: settings.width as u32,
+                        Height: settings.height as u32,
+                        ..swap_chain_desc
+                    },
+                )
+                .unwrap(),
+            ),
+        };
+
         let sample = GenericSample::new(
             render_device,
             immediate_contexts,
             deferred_contexts,
-            &swap_chain,
+            surface.as_ref(),
         );
 
         let imgui_renderer = ImguiRenderer::new(ImguiRendererCreateInfo::new(
             sample.get_render_device(),
-            swap_chain.get_desc().ColorBufferFormat,
-            swap_chain.get_desc().DepthBufferFormat,
-            initial_width,
-            initial_height,
+            surface.get_desc().ColorBufferFormat,
+            surface.get_desc().DepthBufferFormat,
+            settings.width,
+            settings.height,
         ));
 
+        let screen_capture = settings.capture_path.as_ref().and_then(|capture_path| {
+            ScreenCapture::to_directory(sample.get_render_device(), capture_path.clone())
+                .inspect_err(|error| eprintln!("{error}"))
+                .ok()
+        });
+
         SampleApp::<GenericSample> {
-            _app_title: GenericSample::get_name().to_string(),
-            swap_chain,
+            app_title: GenericSample::get_name().to_string(),
+            surface,
 
-            _golden_image_mode: GoldenImageMode::None,
-            _golden_pixel_tolerance: 0,
+            golden_image_mode: settings.golden_image_mode,
+            golden_pixel_tolerance: settings.golden_pixel_tolerance,
 
             sample,
 
-            vsync: false,
+            vsync: settings.vsync,
 
             current_time: 0.0,
+            fixed_time_step: settings.fixed_time_step,
 
-            _width: initial_width,
-            _height: initial_height,
+            _width: settings.width,
+            _height: settings.height,
 
             imgui_renderer,
 
             graphics_adapter: adapter,
+
+            screen_capture,
+            texture_reader: TextureReader::new(),
+            frame_index: 0,
+
+            exit_code: ExitCode::SUCCESS,
         }
     }
 
-    fn run<EH>(mut self, mut event_handler: EH) -> Result<(), std::io::Error>
+    fn set_frame_dump_directory(&mut self, directory: PathBuf) {
+        self.screen_capture =
+            ScreenCapture::to_directory(self.sample.get_render_device(), directory)
+                .inspect_err(|error| eprintln!("{error}"))
+                .ok();
+    }
+
+    fn set_golden_image_mode(&mut self, mode: GoldenImageMode, pixel_tolerance: u32) {
+        self.golden_image_mode = mode;
+        self.golden_pixel_tolerance = pixel_tolerance;
+    }
+
+    fn run<EH>(mut self, mut event_handler: EH) -> Result<ExitCode, std::io::Error>
     where
         EH: EventHandler,
     {
+        let timer = Timer::new();
+        let mut previous_time = 0.0;
+
+        // The frame rate shown in the title is averaged over this period, in seconds
+        const FPS_UPDATE_INTERVAL: f64 = 0.5;
+        let mut fps_frame_count = 0;
+        let mut fps_last_update_time = 0.0;
+
+        let mut cursor_state = CursorState::default();
+
         'main: loop {
             while let Some(event) = event_handler.poll_event() {
                 let event = event_handler.handle_event(&event);
@@ -313,22 +557,63 @@ impl<GenericSample: SampleBase> App for SampleApp<GenericSample> {
                 self.sample.handle_event(event);
             }
 
-            // TODO implement timer
-            self.update(0.0, 0.0);
+            let requested_cursor_state = self.sample.get_cursor_state();
+            event_handler.set_cursor_state(&cursor_state, &requested_cursor_state);
+            cursor_state = requested_cursor_state;
+
+            let time = event_handler
+                .get_frame_time()
+                .unwrap_or_else(|| timer.get_elapsed_time());
+
+            match self.fixed_time_step {
+                Some(time_step) => self.update(self.frame_index as f64 * time_step, time_step),
+                None => self.update(time, time - previous_time),
+            }
+
+            previous_time = time;
 
             self.render();
 
-            self.update_ui();
-            self.imgui_renderer.render(
-                self.sample.get_immediate_context(),
-                self.sample.get_render_device(),
-            );
+            // The UI shows adapter specific information, keep it out of golden images
+            if matches!(self.golden_image_mode, GoldenImageMode::None) {
+                self.update_ui();
+                self.imgui_renderer.render(
+                    self.sample.get_immediate_context(),
+                    self.sample.get_render_device(),
+                );
+            }
 
             self.present();
 
-            //TODO update title
+            // A single frame is rendered in golden image mode
+            if !matches!(self.golden_image_mode, GoldenImageMode::None) {
+                break 'main;
+            }
+
+            fps_frame_count += 1;
+            if time - fps_last_update_time >= FPS_UPDATE_INTERVAL {
+                let frame_time = (time - fps_last_update_time) / fps_frame_count as f64;
+
+                event_handler.set_title(&format!(
+                    "{} - {:.1} ms ({:.1} fps)",
+                    self.get_title(),
+                    frame_time * 1000.0,
+                    1.0 / frame_time
+                ));
+
+                fps_frame_count = 0;
+                fps_last_update_time = time;
+            }
+        }
+
+        if let Some(screen_capture) = &mut self.screen_capture {
+            screen_capture.flush(self.sample.get_immediate_context());
+
+            for error in screen_capture.take_errors() {
+                eprintln!("{error}");
+            }
         }
 
-        Ok(())
+        Ok(self.exit_code)
     }
 }
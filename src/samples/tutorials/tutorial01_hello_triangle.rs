@@ -1,3 +1,5 @@
+use std::process::ExitCode;
+
 use diligent::bindings;
 use diligent::core::device_context::DeviceContext;
 use diligent::core::device_context::DrawAttribs;
@@ -15,10 +17,10 @@ use diligent::core::render_device::RenderDevice;
 use diligent::core::shader::ShaderCreateInfo;
 use diligent::core::shader::ShaderLanguage;
 use diligent::core::shader::ShaderSource;
-use diligent::core::swap_chain::SwapChain;
 use diligent::samples::sample::SampleBase;
 use diligent::samples::sample_app::SampleApp;
 use diligent::tools::native_app;
+use diligent::tools::render_surface::RenderSurface;
 
 struct HelloTriangle {
     render_device: RenderDevice,
@@ -41,7 +43,7 @@ impl SampleBase for HelloTriangle {
         render_device: RenderDevice,
         immediate_contexts: Vec<DeviceContext>,
         deferred_contexts: Vec<DeviceContext>,
-        swap_chain: &SwapChain,
+        render_surface: &dyn RenderSurface,
     ) -> Self {
         let vertex_shader = {
             let shader_source_code = r#"
@@ -125,9 +127,9 @@ void main(in PSInput PSIn, out PSOutput PSOut)
         // This tutorial will render to a single render target
         .num_render_targets(1)
         // Set render target format which is the format of the swap chain's color buffer
-        .rtv_format::<0>(swap_chain.get_desc().ColorBufferFormat as bindings::_TEXTURE_FORMAT)
+        .rtv_format::<0>(render_surface.get_desc().ColorBufferFormat as bindings::_TEXTURE_FORMAT)
         // Use the depth buffer format from the swap chain
-        .dsv_format(swap_chain.get_desc().DepthBufferFormat as bindings::_TEXTURE_FORMAT)
+        .dsv_format(render_surface.get_desc().DepthBufferFormat as bindings::_TEXTURE_FORMAT)
         // Primitive topology defines what kind of primitives will be rendered by this pipeline state
         .primitive_topology(PrimitiveTopology::TriangleList);
 
@@ -149,11 +151,11 @@ void main(in PSInput PSIn, out PSOutput PSOut)
         }
     }
 
-    fn render(&self, swap_chain: &SwapChain) {
+    fn render(&self, render_surface: &dyn RenderSurface) {
         let immediate_context = self.get_immediate_context();
 
-        let mut rtv = swap_chain.get_current_back_buffer_rtv();
-        let mut dsv = swap_chain.get_depth_buffer_dsv();
+        let mut rtv = render_surface.get_current_back_buffer_rtv();
+        let mut dsv = render_surface.get_depth_buffer_dsv();
 
         // Clear the back buffer
         // Let the engine perform required state transitions
@@ -178,6 +180,6 @@ void main(in PSInput PSIn, out PSOutput PSOut)
     }
 }
 
-fn main() {
+fn main() -> ExitCode {
     native_app::main::<SampleApp<HelloTriangle>>().unwrap()
 }
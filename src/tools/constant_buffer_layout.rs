@@ -0,0 +1,627 @@
+use std::{fmt, marker::PhantomData};
+
+use crate::{
+    bindings,
+    core::{
+        buffer::Buffer,
+        device_context::{DeviceContext, ResourceStateTransitionMode},
+        graphics_types::{MapFlags, MapType},
+        pipeline_state::GraphicsPipelineStateCreateInfo,
+        shader::{
+            Shader, ShaderCodeBasicType, ShaderCodeVariableClass, ShaderCodeVariableDesc,
+            ShaderResourceType,
+        },
+    },
+};
+
+// Size of the registers constant buffers are packed into, in bytes
+const REGISTER_SIZE: u32 = 16;
+
+pub struct FieldLayout {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+    // The fields of a nested struct, with offsets relative to the struct. Empty for the fields
+    // that are not declared as nested structs.
+    pub members: Vec<FieldLayout>,
+}
+
+// Describes the fields of a `#[repr(C)]` struct that is copied into a constant buffer.
+// Implemented with the `constant_buffer_layout!` macro.
+pub trait ConstantBufferLayout: Sized {
+    fn fields() -> Vec<FieldLayout>;
+}
+
+#[doc(hidden)]
+pub fn size_of_field<T, F>(_field: fn(&T) -> &F) -> usize {
+    std::mem::size_of::<F>()
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __constant_buffer_members {
+    () => {
+        Vec::new()
+    };
+    ($nested:ty) => {
+        <$nested as $crate::tools::constant_buffer_layout::ConstantBufferLayout>::fields()
+    };
+}
+
+// The fields are named like the variables of the constant buffer, unless the name of the
+// variable is given. The members of nested structs, or of the elements of arrays of structs,
+// are only checked if the type of the struct, which must implement `ConstantBufferLayout`
+// too, is given:
+//
+// constant_buffer_layout!(Constants { world_view_proj as "g_WorldViewProj", lights: Light });
+#[macro_export]
+macro_rules! constant_buffer_layout {
+    ($type:ty { $($field:ident $(as $name:literal)? $(: $nested:ty)?),* $(,)? }) => {
+        impl $crate::tools::constant_buffer_layout::ConstantBufferLayout for $type {
+            fn fields() -> Vec<$crate::tools::constant_buffer_layout::FieldLayout> {
+                vec![$(
+                    $crate::tools::constant_buffer_layout::FieldLayout {
+                        name: [$($name,)? stringify!($field)][0],
+                        offset: std::mem::offset_of!($type, $field),
+                        size: $crate::tools::constant_buffer_layout::size_of_field(
+                            |value: &$type| &value.$field,
+                        ),
+                        members: $crate::__constant_buffer_members!($($nested)?),
+                    },
+                )*]
+            }
+        }
+    };
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LayoutMismatch {
+    // The shader does not have this constant buffer, or was not created with
+    // `load_constant_buffer_reflection`
+    MissingReflection {
+        buffer: String,
+    },
+    BufferTooSmall {
+        buffer: String,
+        size: usize,
+        expected_size: u32,
+    },
+    MissingField {
+        variable: String,
+    },
+    WrongOffset {
+        variable: String,
+        offset: usize,
+        expected_offset: u32,
+    },
+    FieldTooSmall {
+        variable: String,
+        size: usize,
+        expected_size: u32,
+    },
+    // The buffer can neither be updated nor mapped for writing
+    NotWritable {
+        buffer: String,
+    },
+}
+
+impl fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutMismatch::MissingReflection { buffer } => {
+                write!(f, "no reflection for constant buffer '{buffer}'")
+            }
+            LayoutMismatch::BufferTooSmall {
+                buffer,
+                size,
+                expected_size,
+            } => write!(
+                f,
+                "the struct is {size} bytes but constant buffer '{buffer}' is {expected_size} bytes"
+            ),
+            LayoutMismatch::MissingField { variable } => {
+                write!(f, "no field for variable '{variable}'")
+            }
+            LayoutMismatch::WrongOffset {
+                variable,
+                offset,
+                expected_offset,
+            } => write!(
+                f,
+                "field '{variable}' is at offset {offset} but the variable is at offset {expected_offset}"
+            ),
+            LayoutMismatch::FieldTooSmall {
+                variable,
+                size,
+                expected_size,
+            } => write!(
+                f,
+                "field '{variable}' is {size} bytes but the variable is {expected_size} bytes"
+            ),
+            LayoutMismatch::NotWritable { buffer } => write!(
+                f,
+                "constant buffer '{buffer}' must have the default usage, or the dynamic usage and \
+                 CPU write access"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LayoutMismatches(pub Vec<LayoutMismatch>);
+
+impl fmt::Display for LayoutMismatches {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, mismatch) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{mismatch}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LayoutMismatches {}
+
+fn basic_type_size(basic_type: ShaderCodeBasicType) -> Option<u32> {
+    match basic_type {
+        ShaderCodeBasicType::Int8 | ShaderCodeBasicType::Uint8 => Some(1),
+        ShaderCodeBasicType::Int16 | ShaderCodeBasicType::Uint16 | ShaderCodeBasicType::Float16 => {
+            Some(2)
+        }
+        // Minimum precision types are stored as 32 bit values
+        ShaderCodeBasicType::Bool
+        | ShaderCodeBasicType::Int
+        | ShaderCodeBasicType::Uint
+        | ShaderCodeBasicType::Float
+        | ShaderCodeBasicType::Min8Float
+        | ShaderCodeBasicType::Min10Float
+        | ShaderCodeBasicType::Min16Float
+        | ShaderCodeBasicType::Min12Int
+        | ShaderCodeBasicType::Min16Int
+        | ShaderCodeBasicType::Min16Uint => Some(4),
+        ShaderCodeBasicType::Int64 | ShaderCodeBasicType::Uint64 | ShaderCodeBasicType::Double => {
+            Some(8)
+        }
+        ShaderCodeBasicType::Unknown | ShaderCodeBasicType::Void | ShaderCodeBasicType::String => {
+            None
+        }
+    }
+}
+
+// Size of the variable with the HLSL packing rules: every matrix row or column, array element
+// and struct starts a new 16 byte register, and the last one is not padded.
+fn variable_size(variable: &ShaderCodeVariableDesc) -> Option<u32> {
+    let rows = variable.num_rows.max(1) as u32;
+    let columns = variable.num_columns.max(1) as u32;
+
+    let element_size = match variable.class {
+        ShaderCodeVariableClass::Scalar | ShaderCodeVariableClass::Vector => {
+            rows * columns * basic_type_size(variable.basic_type)?
+        }
+        ShaderCodeVariableClass::MatrixRows => {
+            (rows - 1) * REGISTER_SIZE + columns * basic_type_size(variable.basic_type)?
+        }
+        ShaderCodeVariableClass::MatrixColumns => {
+            (columns - 1) * REGISTER_SIZE + rows * basic_type_size(variable.basic_type)?
+        }
+        ShaderCodeVariableClass::Struct => {
+            let mut size = 0;
+            for member in &variable.members {
+                size = size.max(member.offset + variable_size(member)?);
+            }
+            size
+        }
+        ShaderCodeVariableClass::Unknown => return None,
+    };
+
+    if variable.array_size > 1 {
+        let stride = element_size.div_ceil(REGISTER_SIZE) * REGISTER_SIZE;
+        Some((variable.array_size - 1) * stride + element_size)
+    } else {
+        Some(element_size)
+    }
+}
+
+// Members of nested structs are named `<variable>.<member>`
+fn validate_variables(
+    fields: &[FieldLayout],
+    variables: &[ShaderCodeVariableDesc],
+    prefix: &str,
+    mismatches: &mut Vec<LayoutMismatch>,
+) {
+    for variable in variables {
+        let name = format!("{prefix}{}", variable.name);
+
+        let Some(field) = fields.iter().find(|field| field.name == variable.name) else {
+            mismatches.push(LayoutMismatch::MissingField { variable: name });
+            continue;
+        };
+
+        if field.offset != variable.offset as usize {
+            mismatches.push(LayoutMismatch::WrongOffset {
+                variable: name.clone(),
+                offset: field.offset,
+                expected_offset: variable.offset,
+            });
+        }
+
+        // Larger fields are padded, which is fine as long as the offsets match
+        if let Some(expected_size) = variable_size(variable) {
+            if field.size < expected_size as usize {
+                mismatches.push(LayoutMismatch::FieldTooSmall {
+                    variable: name.clone(),
+                    size: field.size,
+                    expected_size,
+                });
+            }
+        }
+
+        if !field.members.is_empty() {
+            validate_variables(
+                &field.members,
+                &variable.members,
+                &format!("{name}."),
+                mismatches,
+            );
+        }
+    }
+}
+
+// Checks the offsets and sizes of the fields of `T` against the reflection of the constant
+// buffer `buffer_name` of `shader`. The shader must be created with
+// `load_constant_buffer_reflection`. Fields that are not variables of the buffer, like padding,
+// are ignored.
+//
+// Layouts are only checked by the functions of this module: creating a pipeline does not check
+// them, `validate_pipeline_constant_buffer_layout` should be called before.
+pub fn validate_constant_buffer_layout<T: ConstantBufferLayout>(
+    shader: &Shader,
+    buffer_name: &str,
+) -> Result<(), LayoutMismatches> {
+    let Some(buffer_desc) = shader.get_constant_buffer_desc_by_name(buffer_name) else {
+        return Err(LayoutMismatches(vec![LayoutMismatch::MissingReflection {
+            buffer: buffer_name.to_string(),
+        }]));
+    };
+
+    let mut mismatches = Vec::new();
+
+    if std::mem::size_of::<T>() < buffer_desc.size as usize {
+        mismatches.push(LayoutMismatch::BufferTooSmall {
+            buffer: buffer_name.to_string(),
+            size: std::mem::size_of::<T>(),
+            expected_size: buffer_desc.size,
+        });
+    }
+
+    validate_variables(&T::fields(), &buffer_desc.variables, "", &mut mismatches);
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(LayoutMismatches(mismatches))
+    }
+}
+
+fn validate_shaders<T: ConstantBufferLayout>(
+    shaders: &[&Shader],
+    buffer_name: &str,
+    mismatches: &mut Vec<LayoutMismatch>,
+) {
+    for shader in shaders {
+        if let Err(LayoutMismatches(shader_mismatches)) =
+            validate_constant_buffer_layout::<T>(shader, buffer_name)
+        {
+            for mismatch in shader_mismatches {
+                // The shaders of a pipeline usually report the same mismatches
+                if !mismatches.contains(&mismatch) {
+                    mismatches.push(mismatch);
+                }
+            }
+        }
+    }
+}
+
+// Checks the layout against the shaders of the pipeline that declare the constant buffer, before
+// the pipeline is created
+pub fn validate_pipeline_constant_buffer_layout<T: ConstantBufferLayout>(
+    pipeline_ci: &GraphicsPipelineStateCreateInfo,
+    buffer_name: &str,
+) -> Result<(), LayoutMismatches> {
+    let shaders: Vec<_> = pipeline_ci
+        .shaders()
+        .filter(|shader| {
+            shader.get_resources().iter().any(|resource_desc| {
+                resource_desc.resource_type == ShaderResourceType::ConstantBuffer
+                    && resource_desc.name == buffer_name
+            })
+        })
+        .collect();
+
+    if shaders.is_empty() {
+        return Err(LayoutMismatches(vec![LayoutMismatch::MissingReflection {
+            buffer: buffer_name.to_string(),
+        }]));
+    }
+
+    let mut mismatches = Vec::new();
+    validate_shaders::<T>(&shaders, buffer_name, &mut mismatches);
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(LayoutMismatches(mismatches))
+    }
+}
+
+fn is_dynamic(buffer: &Buffer) -> bool {
+    let desc = buffer.get_desc();
+    desc.Usage == bindings::USAGE_DYNAMIC as bindings::USAGE
+        && desc.CPUAccessFlags as bindings::_CPU_ACCESS_FLAGS & bindings::CPU_ACCESS_WRITE != 0
+}
+
+// A constant buffer whose layout was validated against the shaders that use it, and whose
+// content can only be written from a `T`
+pub struct ConstantBuffer<T: ConstantBufferLayout> {
+    buffer: Buffer,
+    phantom: PhantomData<T>,
+}
+
+impl<T: ConstantBufferLayout> ConstantBuffer<T> {
+    pub fn new(
+        buffer: Buffer,
+        buffer_name: &str,
+        shaders: &[&Shader],
+    ) -> Result<Self, LayoutMismatches> {
+        let mut mismatches = Vec::new();
+
+        if buffer.get_desc().Size < std::mem::size_of::<T>() as u64 {
+            mismatches.push(LayoutMismatch::BufferTooSmall {
+                buffer: buffer_name.to_string(),
+                size: std::mem::size_of::<T>(),
+                expected_size: buffer.get_desc().Size as u32,
+            });
+        }
+
+        if buffer.get_desc().Usage != bindings::USAGE_DEFAULT as bindings::USAGE
+            && !is_dynamic(&buffer)
+        {
+            mismatches.push(LayoutMismatch::NotWritable {
+                buffer: buffer_name.to_string(),
+            });
+        }
+
+        validate_shaders::<T>(shaders, buffer_name, &mut mismatches);
+
+        if mismatches.is_empty() {
+            Ok(ConstantBuffer {
+                buffer,
+                phantom: PhantomData,
+            })
+        } else {
+            Err(LayoutMismatches(mismatches))
+        }
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    // For buffers with the default usage
+    pub fn update(
+        &mut self,
+        device_context: &DeviceContext,
+        data: &T,
+        state_transition_mode: ResourceStateTransitionMode,
+    ) {
+        device_context.update_buffer(
+            &mut self.buffer,
+            0,
+            std::mem::size_of::<T>() as u64,
+            data,
+            state_transition_mode,
+        );
+    }
+
+    // For dynamic buffers, the previous content is discarded. Returns None if the buffer is not
+    // dynamic or cannot be mapped.
+    pub fn write(&self, device_context: &DeviceContext, data: &T) -> Option<()> {
+        if !is_dynamic(&self.buffer) {
+            return None;
+        }
+
+        let buffer_data =
+            device_context.map_buffer(&self.buffer, MapType::Write, MapFlags::Discard);
+        if buffer_data.is_null() {
+            return None;
+        }
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                std::ptr::from_ref(data) as *const u8,
+                buffer_data,
+                std::mem::size_of::<T>(),
+            );
+        }
+        device_context.unmap_buffer(&self.buffer, MapType::Write);
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(
+        class: ShaderCodeVariableClass,
+        num_rows: u8,
+        num_columns: u8,
+        array_size: u32,
+    ) -> ShaderCodeVariableDesc {
+        ShaderCodeVariableDesc {
+            name: String::new(),
+            type_name: String::new(),
+            class,
+            basic_type: ShaderCodeBasicType::Float,
+            num_rows,
+            num_columns,
+            offset: 0,
+            array_size,
+            members: Vec::new(),
+        }
+    }
+
+    fn member(offset: u32, num_columns: u8) -> ShaderCodeVariableDesc {
+        ShaderCodeVariableDesc {
+            offset,
+            ..variable(ShaderCodeVariableClass::Vector, 1, num_columns, 0)
+        }
+    }
+
+    // struct { float3 Direction; float Intensity; float2 Range; }
+    fn light(array_size: u32) -> ShaderCodeVariableDesc {
+        ShaderCodeVariableDesc {
+            members: vec![member(0, 3), member(12, 1), member(16, 2)],
+            ..variable(ShaderCodeVariableClass::Struct, 1, 1, array_size)
+        }
+    }
+
+    #[test]
+    fn variable_sizes() {
+        use ShaderCodeVariableClass::*;
+
+        for (description, variable, expected_size) in [
+            ("float", variable(Scalar, 1, 1, 0), 4),
+            ("float3", variable(Vector, 1, 3, 0), 12),
+            ("float4", variable(Vector, 1, 4, 0), 16),
+            ("row_major float3x3", variable(MatrixRows, 3, 3, 0), 44),
+            (
+                "column_major float3x3",
+                variable(MatrixColumns, 3, 3, 0),
+                44,
+            ),
+            ("row_major float4x3", variable(MatrixRows, 4, 3, 0), 60),
+            (
+                "column_major float4x3",
+                variable(MatrixColumns, 4, 3, 0),
+                48,
+            ),
+            ("row_major float3x4", variable(MatrixRows, 3, 4, 0), 48),
+            (
+                "column_major float3x4",
+                variable(MatrixColumns, 3, 4, 0),
+                60,
+            ),
+            ("float4x4", variable(MatrixColumns, 4, 4, 0), 64),
+            ("float[1]", variable(Scalar, 1, 1, 1), 4),
+            ("float[4]", variable(Scalar, 1, 1, 4), 52),
+            ("float2[2]", variable(Vector, 1, 2, 2), 24),
+            ("float4[3]", variable(Vector, 1, 4, 3), 48),
+            ("float4x4[2]", variable(MatrixColumns, 4, 4, 2), 128),
+            ("struct", light(0), 24),
+            ("struct[3]", light(3), 2 * 32 + 24),
+        ] {
+            assert_eq!(
+                variable_size(&variable),
+                Some(expected_size),
+                "{description}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_variables_have_no_size() {
+        let mut unknown = variable(ShaderCodeVariableClass::Unknown, 1, 1, 0);
+        assert_eq!(variable_size(&unknown), None);
+
+        unknown.class = ShaderCodeVariableClass::Scalar;
+        unknown.basic_type = ShaderCodeBasicType::Void;
+        assert_eq!(variable_size(&unknown), None);
+
+        let mut light = light(0);
+        light.members.push(unknown);
+        assert_eq!(variable_size(&light), None);
+    }
+
+    #[repr(C)]
+    struct Light {
+        direction: [f32; 3],
+        intensity: f32,
+        range: [f32; 2],
+    }
+
+    #[repr(C)]
+    struct Constants {
+        time: f32,
+        padding: [f32; 3],
+        light: Light,
+    }
+
+    crate::constant_buffer_layout!(Light { direction as "Direction", intensity as "Intensity", range as "Range" });
+    crate::constant_buffer_layout!(Constants { time as "Time", light as "Light": Light });
+
+    fn named(name: &str, offset: u32, variable: ShaderCodeVariableDesc) -> ShaderCodeVariableDesc {
+        ShaderCodeVariableDesc {
+            name: name.to_string(),
+            offset,
+            ..variable
+        }
+    }
+
+    fn light_variable(intensity_offset: u32) -> ShaderCodeVariableDesc {
+        ShaderCodeVariableDesc {
+            members: vec![
+                named("Direction", 0, member(0, 3)),
+                named("Intensity", intensity_offset, member(0, 1)),
+                named("Range", 16, member(0, 2)),
+            ],
+            ..named("Light", 16, light(0))
+        }
+    }
+
+    fn validate(variables: &[ShaderCodeVariableDesc]) -> Vec<LayoutMismatch> {
+        let mut mismatches = Vec::new();
+        validate_variables(&Constants::fields(), variables, "", &mut mismatches);
+        mismatches
+    }
+
+    #[test]
+    fn nested_members_are_validated() {
+        let time = named(
+            "Time",
+            0,
+            variable(ShaderCodeVariableClass::Scalar, 1, 1, 0),
+        );
+
+        assert_eq!(validate(&[time.clone(), light_variable(12)]), vec![]);
+        assert_eq!(
+            validate(&[time, light_variable(16)]),
+            vec![LayoutMismatch::WrongOffset {
+                variable: "Light.Intensity".to_string(),
+                offset: 12,
+                expected_offset: 16,
+            }]
+        );
+    }
+
+    #[test]
+    fn mismatches_are_displayed_one_per_line() {
+        let mismatches = LayoutMismatches(vec![
+            LayoutMismatch::MissingField {
+                variable: "Time".to_string(),
+            },
+            LayoutMismatch::FieldTooSmall {
+                variable: "Light".to_string(),
+                size: 12,
+                expected_size: 16,
+            },
+        ]);
+
+        assert_eq!(
+            mismatches.to_string(),
+            "no field for variable 'Time'\nfield 'Light' is 12 bytes but the variable is 16 bytes"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+pub fn save_png(path: &Path, width: u32, height: u32, rgba_pixels: &[u8]) -> std::io::Result<()> {
+    let file = File::create(path)?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(std::io::Error::other)?;
+
+    writer
+        .write_image_data(rgba_pixels)
+        .map_err(std::io::Error::other)
+}
+
+// Returns the width, the height and the pixels of the image converted to 8 bit RGBA
+pub fn load_png(path: &Path) -> std::io::Result<(u32, u32, Vec<u8>)> {
+    let file = File::open(path)?;
+
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info().map_err(std::io::Error::other)?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(std::io::Error::other)?;
+    buffer.truncate(info.buffer_size());
+
+    let rgba_pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], u8::MAX])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer
+            .iter()
+            .flat_map(|&value| [value, value, value, u8::MAX])
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(std::io::Error::other("Unexpected indexed PNG color type"))
+        }
+    };
+
+    Ok((info.width, info.height, rgba_pixels))
+}
+
+// Counts the pixels for which the difference of at least one channel exceeds the tolerance
+pub fn count_mismatched_pixels(rgba_pixels0: &[u8], rgba_pixels1: &[u8], tolerance: u32) -> usize {
+    rgba_pixels0
+        .chunks_exact(4)
+        .zip(rgba_pixels1.chunks_exact(4))
+        .filter(|(pixel0, pixel1)| {
+            pixel0
+                .iter()
+                .zip(pixel1.iter())
+                .any(|(channel0, channel1)| u32::from(channel0.abs_diff(*channel1)) > tolerance)
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs, path::PathBuf};
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "image_utils_test_{name}_{}.png",
+            std::process::id()
+        ))
+    }
+
+    fn save_with_color(path: &Path, width: u32, height: u32, color: png::ColorType, data: &[u8]) {
+        let mut encoder = png::Encoder::new(File::create(path).unwrap(), width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(data)
+            .unwrap();
+    }
+
+    #[test]
+    fn tolerance_boundary() {
+        let pixels = [10, 20, 30, 40, 200, 200, 200, 255];
+
+        assert_eq!(count_mismatched_pixels(&pixels, &pixels, 0), 0);
+
+        let other = [13, 20, 30, 40, 200, 200, 197, 255];
+        assert_eq!(count_mismatched_pixels(&pixels, &other, 3), 0);
+        assert_eq!(count_mismatched_pixels(&pixels, &other, 2), 2);
+
+        // A single channel is enough, including alpha
+        let other = [10, 20, 30, 44, 200, 200, 200, 255];
+        assert_eq!(count_mismatched_pixels(&pixels, &other, 3), 1);
+    }
+
+    #[test]
+    fn rgba_round_trip() {
+        let path = temporary_path("rgba");
+        let pixels: Vec<u8> = (0..3 * 2 * 4).map(|value| value * 10).collect();
+
+        save_png(&path, 3, 2, &pixels).unwrap();
+        let loaded = load_png(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), (3, 2, pixels));
+    }
+
+    #[test]
+    fn rgb_is_expanded_with_opaque_alpha() {
+        let path = temporary_path("rgb");
+        save_with_color(&path, 2, 1, png::ColorType::Rgb, &[1, 2, 3, 4, 5, 6]);
+        let loaded = load_png(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), (2, 1, vec![1, 2, 3, 255, 4, 5, 6, 255]));
+    }
+
+    #[test]
+    fn grayscale_is_expanded() {
+        let path = temporary_path("grayscale");
+        save_with_color(&path, 1, 2, png::ColorType::Grayscale, &[7, 8]);
+        let loaded = load_png(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), (1, 2, vec![7, 7, 7, 255, 8, 8, 8, 255]));
+
+        let path = temporary_path("grayscale_alpha");
+        save_with_color(&path, 1, 1, png::ColorType::GrayscaleAlpha, &[9, 100]);
+        let loaded = load_png(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), (1, 1, vec![9, 9, 9, 100]));
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let error = load_png(&temporary_path("missing")).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    }
+}
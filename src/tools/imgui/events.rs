@@ -1,23 +1,180 @@
 use imgui::Io;
 
-use crate::tools::native_app::events::{EventResult, MouseButton};
+use crate::tools::native_app::events::{EventResult, Key, KeyModifiers, MouseButton};
+
+// Index of the button in `Io::mouse_down`
+fn imgui_mouse_button(button: MouseButton) -> usize {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Right => 1,
+        MouseButton::Middle => 2,
+        MouseButton::Back => 3,
+        MouseButton::Forward => 4,
+    }
+}
+
+fn imgui_key(key: Key) -> Option<imgui::Key> {
+    let key = match key {
+        Key::A => imgui::Key::A,
+        Key::B => imgui::Key::B,
+        Key::C => imgui::Key::C,
+        Key::D => imgui::Key::D,
+        Key::E => imgui::Key::E,
+        Key::F => imgui::Key::F,
+        Key::G => imgui::Key::G,
+        Key::H => imgui::Key::H,
+        Key::I => imgui::Key::I,
+        Key::J => imgui::Key::J,
+        Key::K => imgui::Key::K,
+        Key::L => imgui::Key::L,
+        Key::M => imgui::Key::M,
+        Key::N => imgui::Key::N,
+        Key::O => imgui::Key::O,
+        Key::P => imgui::Key::P,
+        Key::Q => imgui::Key::Q,
+        Key::R => imgui::Key::R,
+        Key::S => imgui::Key::S,
+        Key::T => imgui::Key::T,
+        Key::U => imgui::Key::U,
+        Key::V => imgui::Key::V,
+        Key::W => imgui::Key::W,
+        Key::X => imgui::Key::X,
+        Key::Y => imgui::Key::Y,
+        Key::Z => imgui::Key::Z,
+
+        Key::Num0 => imgui::Key::Alpha0,
+        Key::Num1 => imgui::Key::Alpha1,
+        Key::Num2 => imgui::Key::Alpha2,
+        Key::Num3 => imgui::Key::Alpha3,
+        Key::Num4 => imgui::Key::Alpha4,
+        Key::Num5 => imgui::Key::Alpha5,
+        Key::Num6 => imgui::Key::Alpha6,
+        Key::Num7 => imgui::Key::Alpha7,
+        Key::Num8 => imgui::Key::Alpha8,
+        Key::Num9 => imgui::Key::Alpha9,
+
+        Key::F1 => imgui::Key::F1,
+        Key::F2 => imgui::Key::F2,
+        Key::F3 => imgui::Key::F3,
+        Key::F4 => imgui::Key::F4,
+        Key::F5 => imgui::Key::F5,
+        Key::F6 => imgui::Key::F6,
+        Key::F7 => imgui::Key::F7,
+        Key::F8 => imgui::Key::F8,
+        Key::F9 => imgui::Key::F9,
+        Key::F10 => imgui::Key::F10,
+        Key::F11 => imgui::Key::F11,
+        Key::F12 => imgui::Key::F12,
+
+        Key::Escape => imgui::Key::Escape,
+        Key::Enter => imgui::Key::Enter,
+        Key::Tab => imgui::Key::Tab,
+        Key::Backspace => imgui::Key::Backspace,
+        Key::Space => imgui::Key::Space,
+        Key::Insert => imgui::Key::Insert,
+        Key::Delete => imgui::Key::Delete,
+        Key::Home => imgui::Key::Home,
+        Key::End => imgui::Key::End,
+        Key::PageUp => imgui::Key::PageUp,
+        Key::PageDown => imgui::Key::PageDown,
+        Key::Left => imgui::Key::LeftArrow,
+        Key::Right => imgui::Key::RightArrow,
+        Key::Up => imgui::Key::UpArrow,
+        Key::Down => imgui::Key::DownArrow,
+
+        Key::Minus => imgui::Key::Minus,
+        Key::Equal => imgui::Key::Equal,
+        Key::LeftBracket => imgui::Key::LeftBracket,
+        Key::RightBracket => imgui::Key::RightBracket,
+        Key::Backslash => imgui::Key::Backslash,
+        Key::Semicolon => imgui::Key::Semicolon,
+        Key::Apostrophe => imgui::Key::Apostrophe,
+        Key::Comma => imgui::Key::Comma,
+        Key::Period => imgui::Key::Period,
+        Key::Slash => imgui::Key::Slash,
+        Key::GraveAccent => imgui::Key::GraveAccent,
+
+        Key::Keypad0 => imgui::Key::Keypad0,
+        Key::Keypad1 => imgui::Key::Keypad1,
+        Key::Keypad2 => imgui::Key::Keypad2,
+        Key::Keypad3 => imgui::Key::Keypad3,
+        Key::Keypad4 => imgui::Key::Keypad4,
+        Key::Keypad5 => imgui::Key::Keypad5,
+        Key::Keypad6 => imgui::Key::Keypad6,
+        Key::Keypad7 => imgui::Key::Keypad7,
+        Key::Keypad8 => imgui::Key::Keypad8,
+        Key::Keypad9 => imgui::Key::Keypad9,
+        Key::KeypadDecimal => imgui::Key::KeypadDecimal,
+        Key::KeypadDivide => imgui::Key::KeypadDivide,
+        Key::KeypadMultiply => imgui::Key::KeypadMultiply,
+        Key::KeypadSubtract => imgui::Key::KeypadSubtract,
+        Key::KeypadAdd => imgui::Key::KeypadAdd,
+        Key::KeypadEnter => imgui::Key::KeypadEnter,
+
+        Key::LeftShift => imgui::Key::LeftShift,
+        Key::RightShift => imgui::Key::RightShift,
+        Key::LeftControl => imgui::Key::LeftCtrl,
+        Key::RightControl => imgui::Key::RightCtrl,
+        Key::LeftAlt => imgui::Key::LeftAlt,
+        Key::RightAlt => imgui::Key::RightAlt,
+        Key::LeftSuper => imgui::Key::LeftSuper,
+        Key::RightSuper => imgui::Key::RightSuper,
+
+        Key::CapsLock => imgui::Key::CapsLock,
+        Key::NumLock => imgui::Key::NumLock,
+        Key::ScrollLock => imgui::Key::ScrollLock,
+        Key::PrintScreen => imgui::Key::PrintScreen,
+        Key::Pause => imgui::Key::Pause,
+        Key::Menu => imgui::Key::Menu,
+
+        Key::Unknown => return None,
+    };
+    Some(key)
+}
+
+// The modifiers of key events are the ones held before the event, which does not account for
+// the modifier key that is being pressed or released
+fn imgui_update_modifiers(io: &mut Io, key: Key, mut modifiers: KeyModifiers, pressed: bool) {
+    let key_modifier = match key {
+        Key::LeftShift | Key::RightShift => KeyModifiers::Shift,
+        Key::LeftControl | Key::RightControl => KeyModifiers::Control,
+        Key::LeftAlt | Key::RightAlt => KeyModifiers::Alt,
+        Key::LeftSuper | Key::RightSuper => KeyModifiers::Super,
+        _ => KeyModifiers::None,
+    };
+    modifiers.set(key_modifier, pressed);
+
+    io.add_key_event(
+        imgui::Key::ModShift,
+        modifiers.contains(KeyModifiers::Shift),
+    );
+    io.add_key_event(
+        imgui::Key::ModCtrl,
+        modifiers.contains(KeyModifiers::Control),
+    );
+    io.add_key_event(imgui::Key::ModAlt, modifiers.contains(KeyModifiers::Alt));
+    io.add_key_event(
+        imgui::Key::ModSuper,
+        modifiers.contains(KeyModifiers::Super),
+    );
+}
 
 pub fn imgui_handle_event(io: &mut Io, event: EventResult) -> EventResult {
     match event {
         EventResult::MouseMove { x, y } => {
-            io.mouse_pos = [x as f32, y as f32];
+            // Imgui positions are in points
+            io.mouse_pos = [
+                x as f32 / io.display_framebuffer_scale[0],
+                y as f32 / io.display_framebuffer_scale[1],
+            ];
             if io.want_capture_mouse {
                 EventResult::Continue
             } else {
                 event
             }
         }
-        EventResult::MouseDown { ref button } => {
-            match button {
-                MouseButton::Left => io.mouse_down[0] = true,
-                MouseButton::Right => io.mouse_down[2] = true,
-                MouseButton::Middle => io.mouse_down[1] = true,
-            }
+        EventResult::MouseDown { button, .. } => {
+            io.mouse_down[imgui_mouse_button(button)] = true;
             if io.want_capture_mouse {
                 EventResult::Continue
             } else {
@@ -25,12 +182,8 @@ pub fn imgui_handle_event(io: &mut Io, event: EventResult) -> EventResult {
             }
         }
 
-        EventResult::MouseUp { ref button } => {
-            match button {
-                MouseButton::Left => io.mouse_down[0] = false,
-                MouseButton::Right => io.mouse_down[2] = false,
-                MouseButton::Middle => io.mouse_down[1] = false,
-            }
+        EventResult::MouseUp { button } => {
+            io.mouse_down[imgui_mouse_button(button)] = false;
             if io.want_capture_mouse {
                 EventResult::Continue
             } else {
@@ -39,11 +192,56 @@ pub fn imgui_handle_event(io: &mut Io, event: EventResult) -> EventResult {
         }
 
         EventResult::Resize { width, height } => {
-            io.display_size = [width as f32, height as f32];
+            io.display_size = [
+                width as f32 / io.display_framebuffer_scale[0],
+                height as f32 / io.display_framebuffer_scale[1],
+            ];
             event
         }
 
-        // TODO Key events
+        EventResult::MouseWheel { delta_x, delta_y } => {
+            io.add_mouse_wheel_event([delta_x, delta_y]);
+            if io.want_capture_mouse {
+                EventResult::Continue
+            } else {
+                event
+            }
+        }
+
+        EventResult::KeyPress {
+            key,
+            modifiers,
+            ref text,
+            ..
+        } => {
+            imgui_update_modifiers(io, key, modifiers, true);
+            if let Some(key) = imgui_key(key) {
+                io.add_key_event(key, true);
+            }
+            if let Some(text) = text {
+                for character in text.chars() {
+                    io.add_input_character(character);
+                }
+            }
+            if io.want_capture_keyboard {
+                EventResult::Continue
+            } else {
+                event
+            }
+        }
+
+        EventResult::KeyRelease { key, modifiers } => {
+            imgui_update_modifiers(io, key, modifiers, false);
+            if let Some(key) = imgui_key(key) {
+                io.add_key_event(key, false);
+            }
+            if io.want_capture_keyboard {
+                EventResult::Continue
+            } else {
+                event
+            }
+        }
+
         _ => event,
     }
 }
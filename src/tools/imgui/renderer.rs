@@ -1,9 +1,9 @@
-use std::{ops::BitAnd, os::raw::c_void};
+use std::{collections::HashSet, ops::BitAnd, path::Path};
 
 use imgui::{
     internal::RawWrapper,
     sys::{ImDrawIdx, ImDrawVert},
-    Io, TextureId, Ui,
+    FontAtlas, FontConfig, FontId, FontSource, Io, TextureId, Textures, Ui,
 };
 
 use crate::{
@@ -19,6 +19,7 @@ use crate::{
             SetShaderResourceFlags, ShaderType, ShaderTypes, TextureAddressMode, Usage, ValueType,
         },
         input_layout::LayoutElement,
+        object::AsObject,
         pipeline_resource_signature::ImmutableSamplerDesc,
         pipeline_state::{
             BlendFactor, BlendOperation, BlendStateDesc, ColorMask, CullMode,
@@ -393,11 +394,84 @@ const PIXEL_SHADER_GAMMA_SPIRV: &[u32] = &[
     0x00000079, 0x000100fd, 0x00010038,
 ];
 
+// Size of the default imgui font, in points
+const DEFAULT_FONT_SIZE: f32 = 13.0;
+
+struct Font {
+    // The default imgui font when `None`
+    ttf_data: Option<Vec<u8>>,
+    // In points
+    size: f32,
+}
+
+fn add_fonts(font_atlas: &mut FontAtlas, fonts: &[Font], display_scale: f32) -> Vec<FontId> {
+    fonts
+        .iter()
+        .map(|font| {
+            let size_pixels = font.size * display_scale;
+            font_atlas.add_font(&[match &font.ttf_data {
+                Some(ttf_data) => FontSource::TtfData {
+                    data: ttf_data,
+                    size_pixels,
+                    config: None,
+                },
+                None => FontSource::DefaultFontData {
+                    config: Some(FontConfig {
+                        size_pixels,
+                        ..FontConfig::default()
+                    }),
+                },
+            }])
+        })
+        .collect()
+}
+
+fn create_font_texture_view(device: &RenderDevice, font_atlas: &mut FontAtlas) -> TextureView {
+    let font_atlas_texture = font_atlas.build_rgba32_texture();
+
+    let font_texture_desc = TextureDesc::new(
+        c"Imgui font texture",
+        TextureDimension::Texture2D,
+        font_atlas_texture.width,
+        font_atlas_texture.height,
+        bindings::TEX_FORMAT_RGBA8_UNORM,
+    )
+    .bind_flags(BindFlags::ShaderResourcec)
+    .usage(Usage::Immutable);
+
+    let font_texture = device
+        .create_texture(
+            &font_texture_desc,
+            &[&TextureSubResource::new_cpu(
+                font_atlas_texture.data,
+                4 * font_atlas_texture.width as u64,
+            )],
+            None,
+        )
+        .unwrap();
+
+    font_texture
+        .get_default_view(TextureViewType::ShaderResource)
+        .unwrap()
+}
+
 pub struct ImguiRenderer {
     context: imgui::Context,
+    render_device: RenderDevice,
     pipeline_state: PipelineState,
-    _font_texture_view: TextureView,
+    // The views referenced by the `TextureId`s of the draw commands, the font atlas included
+    textures: Textures<TextureView>,
     texture_var: ShaderResourceVariable,
+    // The unregistered ids that were drawn, reported once
+    missing_texture_ids: HashSet<usize>,
+
+    // Fonts are rasterized at `display_scale` pixels per point, the atlas is rebuilt when a font
+    // is added or the scale changes
+    fonts: Vec<Font>,
+    font_ids: Vec<FontId>,
+    font_texture_id: TextureId,
+    font_atlas_dirty: bool,
+    display_scale: f32,
     shader_resource_binding: ShaderResourceBinding,
 
     vertex_constant_buffer: Buffer,
@@ -427,6 +501,9 @@ pub struct ImguiRendererCreateInfo<'a> {
 
     initial_width: f32,
     initial_height: f32,
+
+    display_scale: f32,
+    font_size: f32,
 }
 
 impl<'a> ImguiRendererCreateInfo<'a> {
@@ -444,9 +521,23 @@ impl<'a> ImguiRendererCreateInfo<'a> {
             color_conversion: ColorConversionMode::Auto,
             initial_height: initial_height as f32,
             initial_width: initial_width as f32,
+            display_scale: 1.0,
+            font_size: DEFAULT_FONT_SIZE,
         }
     }
 
+    // Number of framebuffer pixels per imgui point. The initial size is in pixels.
+    pub fn display_scale(mut self, display_scale: f32) -> Self {
+        self.display_scale = display_scale;
+        self
+    }
+
+    // Size of the default font, in points
+    pub fn font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
     pub fn color_conversion(mut self, color_conversion: ColorConversionMode) -> Self {
         self.color_conversion = color_conversion;
         self
@@ -471,10 +562,7 @@ impl ImguiRenderer {
         let vertex_shader = {
             let shader_ci = {
                 let shader_source = match device_type {
-                    RenderDeviceType::VULKAN => ShaderSource::ByteCode(
-                        VERTEX_SHADER_SPIRV.as_ptr() as *const c_void,
-                        std::mem::size_of_val(VERTEX_SHADER_SPIRV),
-                    ),
+                    RenderDeviceType::VULKAN => ShaderSource::from_spirv(VERTEX_SHADER_SPIRV),
                     RenderDeviceType::D3D11 | RenderDeviceType::D3D12 => {
                         ShaderSource::SourceCode(VERTEX_SHADER_HLSL)
                     }
@@ -505,15 +593,9 @@ impl ImguiRenderer {
             let shader_source = match device_type {
                 RenderDeviceType::VULKAN => {
                     if manual_srgb {
-                        ShaderSource::ByteCode(
-                            PIXEL_SHADER_GAMMA_SPIRV.as_ptr() as *const c_void,
-                            std::mem::size_of_val(PIXEL_SHADER_GAMMA_SPIRV),
-                        )
+                        ShaderSource::from_spirv(PIXEL_SHADER_GAMMA_SPIRV)
                     } else {
-                        ShaderSource::ByteCode(
-                            PIXEL_SHADER_SPIRV.as_ptr() as *const c_void,
-                            std::mem::size_of_val(PIXEL_SHADER_SPIRV),
-                        )
+                        ShaderSource::from_spirv(PIXEL_SHADER_SPIRV)
                     }
                 }
                 RenderDeviceType::D3D11 | RenderDeviceType::D3D12 => {
@@ -605,35 +687,12 @@ impl ImguiRenderer {
             .unwrap()
             .set(&vertex_constant_buffer, SetShaderResourceFlags::None);
 
-        // Build texture atlas
-        let font_atlas = imgui_context.fonts();
-        let font_atlas_texture = font_atlas.build_rgba32_texture();
-
-        let font_texture_desc = TextureDesc::new(
-            c"Imgui font texture",
-            TextureDimension::Texture2D,
-            font_atlas_texture.width,
-            font_atlas_texture.height,
-            bindings::TEX_FORMAT_RGBA8_UNORM,
-        )
-        .bind_flags(BindFlags::ShaderResourcec)
-        .usage(Usage::Immutable);
-
-        let font_texture = create_info
-            .device
-            .create_texture(
-                &font_texture_desc,
-                &[&TextureSubResource::new_cpu(
-                    font_atlas_texture.data,
-                    4 * font_atlas_texture.width as u64,
-                )],
-                None,
-            )
-            .unwrap();
-
-        let font_texture_view = font_texture
-            .get_default_view(TextureViewType::ShaderResource)
-            .unwrap();
+        let fonts = vec![Font {
+            ttf_data: None,
+            size: create_info.font_size,
+        }];
+        let font_ids = add_fonts(imgui_context.fonts(), &fonts, create_info.display_scale);
+        let font_texture_view = create_font_texture_view(create_info.device, imgui_context.fonts());
 
         let shader_resource_binding = pipeline_state.create_shader_resource_binding(true).unwrap();
 
@@ -642,11 +701,20 @@ impl ImguiRenderer {
             .unwrap();
 
         // Store our identifier
-        imgui_context.fonts().tex_id =
-            TextureId::new(std::ptr::addr_of!(font_texture_view) as usize);
+        let mut textures = Textures::new();
+        let font_texture_id = textures.insert(font_texture_view);
+        imgui_context.fonts().tex_id = font_texture_id;
+
+        let io = imgui_context.io_mut();
+        io.display_size = [
+            create_info.initial_width / create_info.display_scale,
+            create_info.initial_height / create_info.display_scale,
+        ];
+        io.display_framebuffer_scale = [create_info.display_scale, create_info.display_scale];
+        io.font_global_scale = 1.0 / create_info.display_scale;
 
-        imgui_context.io_mut().display_size =
-            [create_info.initial_width, create_info.initial_height];
+        let render_device = RenderDevice::new(create_info.device.render_device);
+        render_device.as_object().add_ref();
 
         ImguiRenderer {
             base_vertex_supported: create_info
@@ -659,8 +727,15 @@ impl ImguiRenderer {
                 )
                 != 0,
             context: imgui_context,
+            render_device,
             pipeline_state,
-            _font_texture_view: font_texture_view,
+            textures,
+            missing_texture_ids: HashSet::new(),
+            fonts,
+            font_ids,
+            font_texture_id,
+            font_atlas_dirty: false,
+            display_scale: create_info.display_scale,
             shader_resource_binding,
             texture_var,
             vertex_buffer: None,
@@ -671,11 +746,90 @@ impl ImguiRenderer {
         }
     }
 
+    // Registers a shader resource view, e.g. of a render target, to be drawn by `Ui::image` and
+    // the other image widgets. The renderer keeps a reference to the view until the texture is
+    // unregistered.
+    pub fn register_texture(&mut self, texture_view: &TextureView) -> TextureId {
+        self.textures.insert(texture_view.new_reference())
+    }
+
+    // Makes `texture_id` refer to another view, e.g. after a render target has been resized
+    pub fn replace_texture(&mut self, texture_id: TextureId, texture_view: &TextureView) {
+        self.textures
+            .replace(texture_id, texture_view.new_reference());
+    }
+
+    pub fn unregister_texture(&mut self, texture_id: TextureId) {
+        self.textures.remove(texture_id);
+    }
+
+    // Returns the index of the font, for `get_font`. The default font is the font 0.
+    pub fn add_font_from_bytes(&mut self, ttf_data: &[u8], size: f32) -> usize {
+        self.fonts.push(Font {
+            ttf_data: Some(ttf_data.to_vec()),
+            size,
+        });
+        self.font_atlas_dirty = true;
+        self.fonts.len() - 1
+    }
+
+    pub fn add_font_from_file(&mut self, path: &Path, size: f32) -> Result<usize, std::io::Error> {
+        let ttf_data = std::fs::read(path)?;
+        Ok(self.add_font_from_bytes(&ttf_data, size))
+    }
+
+    // The identifiers change when the atlas is rebuilt: they should not be kept across frames
+    pub fn get_font(&self, index: usize) -> Option<FontId> {
+        self.font_ids.get(index).copied()
+    }
+
+    // Typically the ratio between the DPI of the display and 96. The display size is kept
+    // in pixels.
+    pub fn set_display_scale(&mut self, display_scale: f32) {
+        if display_scale == self.display_scale {
+            return;
+        }
+
+        let io = self.context.io_mut();
+        io.display_size = [
+            io.display_size[0] * self.display_scale / display_scale,
+            io.display_size[1] * self.display_scale / display_scale,
+        ];
+        io.display_framebuffer_scale = [display_scale, display_scale];
+        io.font_global_scale = 1.0 / display_scale;
+
+        self.display_scale = display_scale;
+        self.font_atlas_dirty = true;
+    }
+
+    pub fn get_display_scale(&self) -> f32 {
+        self.display_scale
+    }
+
+    // Rasterizes the fonts again and uploads the new atlas
+    pub fn rebuild_font_atlas(&mut self) {
+        let font_atlas = self.context.fonts();
+        font_atlas.clear();
+
+        self.font_ids = add_fonts(font_atlas, &self.fonts, self.display_scale);
+
+        let font_texture_view = create_font_texture_view(&self.render_device, font_atlas);
+        self.textures
+            .replace(self.font_texture_id, font_texture_view);
+        font_atlas.tex_id = self.font_texture_id;
+
+        self.font_atlas_dirty = false;
+    }
+
     pub fn io_mut(&mut self) -> &mut Io {
         self.context.io_mut()
     }
 
     pub fn new_frame(&mut self) -> &mut Ui {
+        if self.font_atlas_dirty {
+            self.rebuild_font_atlas();
+        }
+
         self.context.new_frame()
     }
 
@@ -815,6 +969,10 @@ impl ImguiRenderer {
             }
         }
 
+        // The display size is in points, the viewport and the scissor rectangles in pixels
+        let framebuffer_width = draw_data.display_size[0] * draw_data.framebuffer_scale[0];
+        let framebuffer_height = draw_data.display_size[1] * draw_data.framebuffer_scale[1];
+
         // Setup the render state
         {
             // Setup shader and vertex buffers
@@ -833,19 +991,14 @@ impl ImguiRenderer {
 
             device_context.set_blend_factors(&[0.0, 0.0, 0.0, 0.0]);
 
-            let viewport = Viewport::new(
-                0.0,
-                0.0,
-                draw_data.display_size[0],
-                draw_data.display_size[1],
-            )
-            .min_depth(0.0)
-            .max_depth(1.0);
+            let viewport = Viewport::new(0.0, 0.0, framebuffer_width, framebuffer_height)
+                .min_depth(0.0)
+                .max_depth(1.0);
 
             device_context.set_viewports(
                 &[&viewport],
-                draw_data.display_size[0] as u32,
-                draw_data.display_size[1] as u32,
+                framebuffer_width as u32,
+                framebuffer_height as u32,
             );
         }
 
@@ -854,7 +1007,7 @@ impl ImguiRenderer {
         let mut global_idx_offset: u32 = 0;
         let mut global_vtx_offset: u32 = 0;
 
-        let mut last_texture_view: *const TextureView = std::ptr::null();
+        let mut last_texture_id = None;
         for cmd_list in draw_data.draw_lists() {
             for cmd in cmd_list.commands() {
                 match cmd {
@@ -881,8 +1034,8 @@ impl ImguiRenderer {
                         let scissor = Rect::new(
                             clip_rect[0].max(0.0) as i32,
                             clip_rect[1].max(0.0) as i32,
-                            clip_rect[2].min(draw_data.display_size[0]) as i32,
-                            clip_rect[3].min(draw_data.display_size[1]) as i32,
+                            clip_rect[2].min(framebuffer_width) as i32,
+                            clip_rect[3].min(framebuffer_height) as i32,
                         );
 
                         if !scissor.is_valid() {
@@ -891,20 +1044,27 @@ impl ImguiRenderer {
 
                         device_context.set_scissor_rects(
                             &[&scissor],
-                            draw_data.display_size[0] as u32,
-                            draw_data.display_size[1] as u32,
+                            framebuffer_width as u32,
+                            framebuffer_height as u32,
                         );
 
                         // Bind texture
-                        let texture_view = cmd_params.texture_id.id() as *const TextureView;
+                        let Some(texture_view) = self.textures.get(cmd_params.texture_id) else {
+                            if self.missing_texture_ids.insert(cmd_params.texture_id.id()) {
+                                println!(
+                                    "Imgui texture {} is not registered",
+                                    cmd_params.texture_id.id()
+                                );
+                            }
+                            continue;
+                        };
 
-                        if texture_view != last_texture_view {
-                            last_texture_view = texture_view;
+                        if last_texture_id != Some(cmd_params.texture_id) {
+                            last_texture_id = Some(cmd_params.texture_id);
 
-                            //self.texture_var
-                            //    .set(unsafe { &*texture_view }, SetShaderResourceFlags::None);
+                            // The variable is dynamic: the binding can be changed between draws
                             self.texture_var
-                                .set(&self._font_texture_view, SetShaderResourceFlags::None);
+                                .set(texture_view, SetShaderResourceFlags::None);
 
                             device_context.commit_shader_resources(
                                 &self.shader_resource_binding,
@@ -1,3 +1,15 @@
 pub mod native_app;
 
 pub mod imgui;
+
+pub mod constant_buffer_layout;
+pub mod image_utils;
+#[cfg(feature = "rayon")]
+pub mod rayon_thread_pool;
+pub mod render_state_cache;
+pub mod render_surface;
+pub mod screen_capture;
+pub mod shader_cache;
+pub mod shader_hot_reload;
+pub mod texture_reader;
+pub mod timer;
@@ -1,10 +1,16 @@
+use std::{iter::Peekable, path::PathBuf, process::ExitCode};
+
 use crate::{
     bindings::NativeWindow,
-    core::{engine_factory::EngineCreateInfo, graphics_types::RenderDeviceType},
+    core::{
+        engine_factory::EngineCreateInfo,
+        graphics_types::{AdapterType, RenderDeviceType},
+    },
 };
 
 use super::events::EventHandler;
 
+#[derive(Clone, Copy)]
 pub enum GoldenImageMode {
     None,
     Capture,
@@ -12,14 +18,413 @@ pub enum GoldenImageMode {
     CompareUpdate,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WindowSystem {
+    // Wayland when a compositor is available, X11 otherwise
+    Auto,
+    X11,
+    Wayland,
+}
+
+pub struct AppSettings {
+    pub device_type: RenderDeviceType,
+
+    pub width: u16,
+    pub height: u16,
+    pub fullscreen: bool,
+
+    // Wayland windows are only supported with Vulkan
+    pub window_system: WindowSystem,
+
+    // Either an explicit adapter index or the preferred adapter type
+    pub adapter_index: Option<usize>,
+    pub adapter_type: AdapterType,
+
+    pub validation: Option<bool>,
+    pub vsync: bool,
+
+    // Renders into an offscreen render target for `num_frames` frames, without a window. The
+    // GL backend still needs an X display, e.g. `xvfb-run` on machines without one.
+    pub headless: bool,
+    pub num_frames: u32,
+
+    // Renders a single frame and captures it to, or compares it with, the golden image of the
+    // application. The exit code of `run` is a failure if the comparison fails.
+    pub golden_image_mode: GoldenImageMode,
+    pub golden_pixel_tolerance: u32,
+
+    // Every presented frame is written to this directory as a numbered PNG file
+    pub capture_path: Option<PathBuf>,
+
+    // Every frame advances the time by this many seconds instead of the measured frame time,
+    // which makes the rendered frames independent of the performance of the machine
+    pub fixed_time_step: Option<f64>,
+
+    // The events of the session are written to this file...
+    pub record_path: Option<PathBuf>,
+    // ...and replayed from this one, without a window and with the recorded size and frame times
+    pub replay_path: Option<PathBuf>,
+
+    pub non_separable_programs: bool,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            #[cfg(feature = "VULKAN_SUPPORTED")]
+            device_type: RenderDeviceType::VULKAN,
+            #[cfg(not(feature = "VULKAN_SUPPORTED"))]
+            device_type: RenderDeviceType::GL,
+
+            width: 1024,
+            height: 768,
+            fullscreen: false,
+
+            window_system: WindowSystem::Auto,
+
+            adapter_index: None,
+            adapter_type: AdapterType::Unknown,
+
+            validation: None,
+            vsync: false,
+
+            headless: false,
+            num_frames: 1,
+
+            golden_image_mode: GoldenImageMode::None,
+            golden_pixel_tolerance: 0,
+
+            capture_path: None,
+
+            fixed_time_step: None,
+
+            record_path: None,
+            replay_path: None,
+
+            non_separable_programs: false,
+        }
+    }
+}
+
+const USAGE: &str = "Options:
+  --mode <d3d11|d3d12|gl|gles|vk|mtl|wgpu>
+  --width <pixels>
+  --height <pixels>
+  --fullscreen
+  --window_system <auto|x11|wayland>
+  --adapter <id|discrete|integrated|software>
+  --validation <on|off>
+  --vsync <on|off>
+  --headless
+  --num_frames <count>
+  --golden_image_mode <none|capture|compare|compare_update>
+  --golden_image_tolerance <value>
+  --capture_path <directory>
+  --fixed_time_step <seconds>
+  --record <file>
+  --replay <file>
+  --non_separable_progs <on|off>";
+
+fn invalid_argument(message: String) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("{message}\n{USAGE}"),
+    )
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "on" | "true" | "yes" => Some(true),
+        "0" | "off" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, std::io::Error> {
+    value
+        .parse()
+        .map_err(|_| invalid_argument(format!("Invalid value '{value}' for {arg}")))
+}
+
+fn take_value<I>(
+    arg: &str,
+    value: &mut Option<String>,
+    args: &mut Peekable<I>,
+) -> Result<String, std::io::Error>
+where
+    I: Iterator<Item = String>,
+{
+    value
+        .take()
+        .or_else(|| args.next_if(|next| !next.starts_with("--")))
+        .ok_or_else(|| invalid_argument(format!("Missing value for {arg}")))
+}
+
+// A boolean option without a value is enabled
+fn take_bool<I>(
+    arg: &str,
+    value: &mut Option<String>,
+    args: &mut Peekable<I>,
+) -> Result<bool, std::io::Error>
+where
+    I: Iterator<Item = String>,
+{
+    match value.take() {
+        Some(value) => parse_bool(&value)
+            .ok_or_else(|| invalid_argument(format!("Invalid value '{value}' for {arg}"))),
+        None => Ok(args
+            .next_if(|next| parse_bool(next).is_some())
+            .and_then(|next| parse_bool(&next))
+            .unwrap_or(true)),
+    }
+}
+
+impl AppSettings {
+    // Arguments are given as `--name value` or `--name=value`
+    pub fn from_args<I>(args: I) -> Result<Self, std::io::Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut settings = AppSettings::default();
+
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            let (name, mut value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            let mut next_value = || take_value(&name, &mut value, &mut args);
+
+            match name.as_str() {
+                "--mode" => {
+                    let mode = next_value()?;
+                    settings.device_type = match mode.to_ascii_lowercase().as_str() {
+                        "d3d11" => RenderDeviceType::D3D11,
+                        "d3d12" => RenderDeviceType::D3D12,
+                        "gl" => RenderDeviceType::GL,
+                        "gles" => RenderDeviceType::GLES,
+                        "vk" => RenderDeviceType::VULKAN,
+                        "mtl" => RenderDeviceType::METAL,
+                        "wgpu" => RenderDeviceType::WEBGPU,
+                        _ => return Err(invalid_argument(format!("Unknown device type '{mode}'"))),
+                    };
+                }
+                "--width" => settings.width = parse_number(&name, &next_value()?)?,
+                "--height" => settings.height = parse_number(&name, &next_value()?)?,
+                "--fullscreen" => settings.fullscreen = take_bool(&name, &mut value, &mut args)?,
+                "--window_system" => {
+                    let window_system = next_value()?;
+                    settings.window_system = match window_system.to_ascii_lowercase().as_str() {
+                        "auto" => WindowSystem::Auto,
+                        "x11" => WindowSystem::X11,
+                        "wayland" => WindowSystem::Wayland,
+                        _ => {
+                            return Err(invalid_argument(format!(
+                                "Unknown window system '{window_system}'"
+                            )))
+                        }
+                    };
+                }
+                "--adapter" => {
+                    let adapter = next_value()?;
+                    match adapter.to_ascii_lowercase().as_str() {
+                        "discrete" => settings.adapter_type = AdapterType::Discrete,
+                        "integrated" => settings.adapter_type = AdapterType::Integrated,
+                        "sw" | "software" => settings.adapter_type = AdapterType::Software,
+                        _ => settings.adapter_index = Some(parse_number(&name, &adapter)?),
+                    }
+                }
+                "--validation" => {
+                    settings.validation = Some(take_bool(&name, &mut value, &mut args)?)
+                }
+                "--vsync" => settings.vsync = take_bool(&name, &mut value, &mut args)?,
+                "--headless" => settings.headless = true,
+                "--num_frames" => settings.num_frames = parse_number(&name, &next_value()?)?,
+                "--golden_image_mode" => {
+                    let mode = next_value()?;
+                    settings.golden_image_mode = match mode.to_ascii_lowercase().as_str() {
+                        "none" => GoldenImageMode::None,
+                        "capture" => GoldenImageMode::Capture,
+                        "compare" => GoldenImageMode::Compare,
+                        "compare_update" => GoldenImageMode::CompareUpdate,
+                        _ => {
+                            return Err(invalid_argument(format!(
+                                "Unknown golden image mode '{mode}'"
+                            )))
+                        }
+                    };
+                }
+                "--golden_image_tolerance" => {
+                    settings.golden_pixel_tolerance = parse_number(&name, &next_value()?)?
+                }
+                "--capture_path" => settings.capture_path = Some(PathBuf::from(next_value()?)),
+                "--fixed_time_step" => {
+                    settings.fixed_time_step = Some(parse_number(&name, &next_value()?)?)
+                }
+                "--record" => settings.record_path = Some(PathBuf::from(next_value()?)),
+                "--replay" => settings.replay_path = Some(PathBuf::from(next_value()?)),
+                "--non_separable_progs" => {
+                    settings.non_separable_programs = take_bool(&name, &mut value, &mut args)?
+                }
+                _ => return Err(invalid_argument(format!("Unknown argument '{name}'"))),
+            }
+        }
+
+        Ok(settings)
+    }
+
+    pub fn from_command_line() -> Result<Self, std::io::Error> {
+        Self::from_args(std::env::args().skip(1))
+    }
+}
+
+pub struct WindowDesc {
+    pub title: String,
+
+    pub width: u16,
+    pub height: u16,
+    pub min_width: u16,
+    pub min_height: u16,
+
+    pub resizable: bool,
+    // Requested from the window manager, the size of the window is then the size of the screen
+    pub fullscreen: bool,
+}
+
+impl WindowDesc {
+    pub fn new(title: &str, width: u16, height: u16) -> Self {
+        WindowDesc {
+            title: title.to_string(),
+            width,
+            height,
+            min_width: 320,
+            min_height: 240,
+            resizable: true,
+            fullscreen: false,
+        }
+    }
+}
+
 pub trait App {
+    // Describes the window the application is created with, when not running headless
+    fn get_window_desc(settings: &AppSettings) -> WindowDesc {
+        let mut window_desc = WindowDesc::new("Diligent Engine", settings.width, settings.height);
+        window_desc.fullscreen = settings.fullscreen;
+        window_desc
+    }
+
     fn new(
-        device_type: RenderDeviceType,
+        settings: &AppSettings,
         engine_create_info: EngineCreateInfo,
         window: Option<&NativeWindow>,
-        initial_width: u16,
-        initial_height: u16,
     ) -> Self;
 
-    fn run<EH: EventHandler>(self, event_handler: EH) -> Result<(), std::io::Error>;
+    // Every presented frame is written to `directory` as a numbered PNG file. Ignored by
+    // applications that do not support frame dumps.
+    fn set_frame_dump_directory(&mut self, _directory: PathBuf) {}
+
+    // Renders a single frame and captures it to, or compares it with, the golden image of the
+    // application. The exit code of `run` is a failure if the comparison fails. Ignored by
+    // applications without golden images.
+    fn set_golden_image_mode(&mut self, _mode: GoldenImageMode, _pixel_tolerance: u32) {}
+
+    fn run<EH: EventHandler>(self, event_handler: EH) -> Result<ExitCode, std::io::Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_args(args: &[&str]) -> Result<AppSettings, std::io::Error> {
+        AppSettings::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let settings = from_args(&[]).unwrap();
+        assert_eq!((settings.width, settings.height), (1024, 768));
+        assert!(!settings.fullscreen && !settings.headless && !settings.vsync);
+        assert_eq!(settings.num_frames, 1);
+        assert!(matches!(settings.golden_image_mode, GoldenImageMode::None));
+        assert!(settings.validation.is_none());
+    }
+
+    #[test]
+    fn values_as_separate_or_joined_arguments() {
+        let settings = from_args(&["--width", "640", "--height=480", "--width", "800"]).unwrap();
+        assert_eq!((settings.width, settings.height), (800, 480));
+
+        let settings = from_args(&["--mode=gl", "--adapter", "discrete"]).unwrap();
+        assert!(matches!(settings.device_type, RenderDeviceType::GL));
+        assert!(settings.adapter_type == AdapterType::Discrete);
+        assert_eq!(settings.adapter_index, None);
+
+        let settings = from_args(&["--mode", "VK", "--adapter=2"]).unwrap();
+        assert!(matches!(settings.device_type, RenderDeviceType::VULKAN));
+        assert_eq!(settings.adapter_index, Some(2));
+    }
+
+    #[test]
+    fn boolean_options() {
+        // Without a value, or followed by another option
+        let settings = from_args(&["--vsync", "--fullscreen"]).unwrap();
+        assert!(settings.vsync && settings.fullscreen);
+
+        let settings = from_args(&["--vsync", "off", "--validation=on"]).unwrap();
+        assert!(!settings.vsync);
+        assert_eq!(settings.validation, Some(true));
+
+        assert!(from_args(&["--vsync=maybe"]).is_err());
+    }
+
+    #[test]
+    fn modes_and_paths() {
+        let settings = from_args(&[
+            "--headless",
+            "--num_frames",
+            "3",
+            "--golden_image_mode",
+            "compare_update",
+            "--golden_image_tolerance=4",
+            "--capture_path",
+            "frames",
+            "--fixed_time_step=0.5",
+            "--replay",
+            "session.txt",
+            "--window_system",
+            "wayland",
+        ])
+        .unwrap();
+        assert!(settings.headless);
+        assert_eq!(settings.num_frames, 3);
+        assert!(matches!(
+            settings.golden_image_mode,
+            GoldenImageMode::CompareUpdate
+        ));
+        assert_eq!(settings.golden_pixel_tolerance, 4);
+        assert_eq!(settings.capture_path, Some(PathBuf::from("frames")));
+        assert_eq!(settings.fixed_time_step, Some(0.5));
+        assert_eq!(settings.replay_path, Some(PathBuf::from("session.txt")));
+        assert!(settings.window_system == WindowSystem::Wayland);
+    }
+
+    #[test]
+    fn invalid_arguments_print_the_usage() {
+        for args in [
+            &["--width"][..],
+            &["--width", "wide"],
+            &["--height", "--width"],
+            &["--mode", "dx9"],
+            &["--golden_image_mode=always"],
+            &["--unknown"],
+            &["-h"],
+            &["-w", "800"],
+        ] {
+            let error = from_args(args).err().unwrap();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+            assert!(error.to_string().ends_with(USAGE), "{args:?}");
+        }
+    }
 }
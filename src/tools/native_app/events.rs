@@ -1,23 +1,470 @@
+use bitflags::bitflags;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum MouseButton {
     Left,
     Right,
     Middle,
+    Back,
+    Forward,
+}
+
+// Keys are identified by their unshifted symbol in the active keyboard layout
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Space,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Right,
+    Up,
+    Down,
+
+    Minus,
+    Equal,
+    LeftBracket,
+    RightBracket,
+    Backslash,
+    Semicolon,
+    Apostrophe,
+    Comma,
+    Period,
+    Slash,
+    GraveAccent,
+
+    Keypad0,
+    Keypad1,
+    Keypad2,
+    Keypad3,
+    Keypad4,
+    Keypad5,
+    Keypad6,
+    Keypad7,
+    Keypad8,
+    Keypad9,
+    KeypadDecimal,
+    KeypadDivide,
+    KeypadMultiply,
+    KeypadSubtract,
+    KeypadAdd,
+    KeypadEnter,
+
+    LeftShift,
+    RightShift,
+    LeftControl,
+    RightControl,
+    LeftAlt,
+    RightAlt,
+    LeftSuper,
+    RightSuper,
+
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    PrintScreen,
+    Pause,
+    Menu,
+
+    Unknown,
 }
+
+impl Key {
+    // Inverse of the `Debug` name of the key
+    pub fn from_name(name: &str) -> Option<Key> {
+        Some(match name {
+            "A" => Key::A,
+            "B" => Key::B,
+            "C" => Key::C,
+            "D" => Key::D,
+            "E" => Key::E,
+            "F" => Key::F,
+            "G" => Key::G,
+            "H" => Key::H,
+            "I" => Key::I,
+            "J" => Key::J,
+            "K" => Key::K,
+            "L" => Key::L,
+            "M" => Key::M,
+            "N" => Key::N,
+            "O" => Key::O,
+            "P" => Key::P,
+            "Q" => Key::Q,
+            "R" => Key::R,
+            "S" => Key::S,
+            "T" => Key::T,
+            "U" => Key::U,
+            "V" => Key::V,
+            "W" => Key::W,
+            "X" => Key::X,
+            "Y" => Key::Y,
+            "Z" => Key::Z,
+            "Num0" => Key::Num0,
+            "Num1" => Key::Num1,
+            "Num2" => Key::Num2,
+            "Num3" => Key::Num3,
+            "Num4" => Key::Num4,
+            "Num5" => Key::Num5,
+            "Num6" => Key::Num6,
+            "Num7" => Key::Num7,
+            "Num8" => Key::Num8,
+            "Num9" => Key::Num9,
+            "F1" => Key::F1,
+            "F2" => Key::F2,
+            "F3" => Key::F3,
+            "F4" => Key::F4,
+            "F5" => Key::F5,
+            "F6" => Key::F6,
+            "F7" => Key::F7,
+            "F8" => Key::F8,
+            "F9" => Key::F9,
+            "F10" => Key::F10,
+            "F11" => Key::F11,
+            "F12" => Key::F12,
+            "Escape" => Key::Escape,
+            "Enter" => Key::Enter,
+            "Tab" => Key::Tab,
+            "Backspace" => Key::Backspace,
+            "Space" => Key::Space,
+            "Insert" => Key::Insert,
+            "Delete" => Key::Delete,
+            "Home" => Key::Home,
+            "End" => Key::End,
+            "PageUp" => Key::PageUp,
+            "PageDown" => Key::PageDown,
+            "Left" => Key::Left,
+            "Right" => Key::Right,
+            "Up" => Key::Up,
+            "Down" => Key::Down,
+            "Minus" => Key::Minus,
+            "Equal" => Key::Equal,
+            "LeftBracket" => Key::LeftBracket,
+            "RightBracket" => Key::RightBracket,
+            "Backslash" => Key::Backslash,
+            "Semicolon" => Key::Semicolon,
+            "Apostrophe" => Key::Apostrophe,
+            "Comma" => Key::Comma,
+            "Period" => Key::Period,
+            "Slash" => Key::Slash,
+            "GraveAccent" => Key::GraveAccent,
+            "Keypad0" => Key::Keypad0,
+            "Keypad1" => Key::Keypad1,
+            "Keypad2" => Key::Keypad2,
+            "Keypad3" => Key::Keypad3,
+            "Keypad4" => Key::Keypad4,
+            "Keypad5" => Key::Keypad5,
+            "Keypad6" => Key::Keypad6,
+            "Keypad7" => Key::Keypad7,
+            "Keypad8" => Key::Keypad8,
+            "Keypad9" => Key::Keypad9,
+            "KeypadDecimal" => Key::KeypadDecimal,
+            "KeypadDivide" => Key::KeypadDivide,
+            "KeypadMultiply" => Key::KeypadMultiply,
+            "KeypadSubtract" => Key::KeypadSubtract,
+            "KeypadAdd" => Key::KeypadAdd,
+            "KeypadEnter" => Key::KeypadEnter,
+            "LeftShift" => Key::LeftShift,
+            "RightShift" => Key::RightShift,
+            "LeftControl" => Key::LeftControl,
+            "RightControl" => Key::RightControl,
+            "LeftAlt" => Key::LeftAlt,
+            "RightAlt" => Key::RightAlt,
+            "LeftSuper" => Key::LeftSuper,
+            "RightSuper" => Key::RightSuper,
+            "CapsLock" => Key::CapsLock,
+            "NumLock" => Key::NumLock,
+            "ScrollLock" => Key::ScrollLock,
+            "PrintScreen" => Key::PrintScreen,
+            "Pause" => Key::Pause,
+            "Menu" => Key::Menu,
+            "Unknown" => Key::Unknown,
+            _ => return None,
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct KeyModifiers: u8 {
+        const None    = 0;
+        const Shift   = 1 << 0;
+        const Control = 1 << 1;
+        const Alt     = 1 << 2;
+        const Super   = 1 << 3;
+    }
+}
+
+#[derive(Clone)]
 pub enum EventResult {
-    MouseMove { x: i16, y: i16 },
-    MouseDown { button: MouseButton },
-    MouseUp { button: MouseButton },
-    MouseWheel { up: bool },
-    KeyPress { keycode: u8 },
-    KeyRelease { keycode: u8 },
-    Resize { width: u16, height: u16 },
+    MouseMove {
+        x: i16,
+        y: i16,
+    },
+    // Reported instead of `MouseMove` in relative mouse mode
+    MouseMoveRelative {
+        dx: i16,
+        dy: i16,
+    },
+    // `double_click` is set for the second press of a double click
+    MouseDown {
+        button: MouseButton,
+        double_click: bool,
+    },
+    MouseUp {
+        button: MouseButton,
+    },
+    // In wheel notches, positive values scroll up and right. Touchpads and high resolution
+    // wheels report fractions of notches.
+    MouseWheel {
+        delta_x: f32,
+        delta_y: f32,
+    },
+    // `repeat` is set for the presses generated by holding the key down. `text` is the UTF-8
+    // text the key press produces with the current modifiers and layout, if any.
+    KeyPress {
+        key: Key,
+        modifiers: KeyModifiers,
+        repeat: bool,
+        text: Option<String>,
+    },
+    KeyRelease {
+        key: Key,
+        modifiers: KeyModifiers,
+    },
+    Resize {
+        width: u16,
+        height: u16,
+    },
     Continue,
     Quit,
 }
 
+// Maximum time between the presses of a double click, in milliseconds
+const DOUBLE_CLICK_TIME: u32 = 500;
+// Maximum distance between the presses of a double click, in pixels
+const DOUBLE_CLICK_DISTANCE: u16 = 4;
+
+struct Click {
+    button: MouseButton,
+    time: u32,
+    x: i16,
+    y: i16,
+}
+
+// Detects double clicks from the timestamps, in milliseconds, of the button presses reported
+// by the window system
+pub struct DoubleClickDetector {
+    last_click: Option<Click>,
+}
+
+impl Default for DoubleClickDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DoubleClickDetector {
+    pub fn new() -> Self {
+        DoubleClickDetector { last_click: None }
+    }
+
+    // Returns true if the press completes a double click. A triple click is a double click
+    // followed by a single click.
+    pub fn press(&mut self, button: MouseButton, time: u32, x: i16, y: i16) -> bool {
+        let double_click = self.last_click.as_ref().is_some_and(|click| {
+            click.button == button
+                && time.wrapping_sub(click.time) <= DOUBLE_CLICK_TIME
+                && x.abs_diff(click.x) <= DOUBLE_CLICK_DISTANCE
+                && y.abs_diff(click.y) <= DOUBLE_CLICK_DISTANCE
+        });
+
+        self.last_click = if double_click {
+            None
+        } else {
+            Some(Click { button, time, x, y })
+        };
+
+        double_click
+    }
+}
+
+// The cursor the application requests from the window
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CursorState {
+    pub visible: bool,
+    pub grab: bool,
+    pub relative_mouse_mode: bool,
+}
+
+impl Default for CursorState {
+    fn default() -> Self {
+        CursorState {
+            visible: true,
+            grab: false,
+            relative_mouse_mode: false,
+        }
+    }
+}
+
 pub trait EventHandler {
     type EventType;
 
     fn poll_event(&self) -> Option<Self::EventType>;
     fn handle_event(&mut self, event: &Self::EventType) -> EventResult;
+
+    // The window controls do nothing for event handlers without a window
+    fn set_title(&self, _title: &str) {}
+    fn set_cursor_visible(&mut self, _visible: bool) {}
+    // Confines the cursor to the window
+    fn set_cursor_grab(&mut self, _grab: bool) {}
+    // Hides and grabs the cursor, which stays at the center of the window. Mouse motion is
+    // reported as `EventResult::MouseMoveRelative` until the mode is disabled.
+    fn set_relative_mouse_mode(&mut self, _enabled: bool) {}
+
+    // The time of the current frame, in seconds, when it is not measured by the application,
+    // as when replaying a recorded session
+    fn get_frame_time(&self) -> Option<f64> {
+        None
+    }
+
+    // Only changes what differs from the `current` state
+    fn set_cursor_state(&mut self, current: &CursorState, requested: &CursorState) {
+        if current.visible != requested.visible {
+            self.set_cursor_visible(requested.visible);
+        }
+        if current.grab != requested.grab {
+            self.set_cursor_grab(requested.grab);
+        }
+        if current.relative_mouse_mode != requested.relative_mouse_mode {
+            self.set_relative_mouse_mode(requested.relative_mouse_mode);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_click_within_thresholds() {
+        let mut detector = DoubleClickDetector::new();
+
+        assert!(!detector.press(MouseButton::Left, 1000, 10, 10));
+        assert!(detector.press(
+            MouseButton::Left,
+            1000 + DOUBLE_CLICK_TIME,
+            10 + DOUBLE_CLICK_DISTANCE as i16,
+            10 - DOUBLE_CLICK_DISTANCE as i16
+        ));
+    }
+
+    #[test]
+    fn slow_click_is_not_a_double_click() {
+        let mut detector = DoubleClickDetector::new();
+
+        assert!(!detector.press(MouseButton::Left, 1000, 10, 10));
+        assert!(!detector.press(MouseButton::Left, 1001 + DOUBLE_CLICK_TIME, 10, 10));
+        // The slow click starts a new double click
+        assert!(detector.press(MouseButton::Left, 1002 + DOUBLE_CLICK_TIME, 10, 10));
+    }
+
+    #[test]
+    fn distant_click_is_not_a_double_click() {
+        for (x, y) in [
+            (11 + DOUBLE_CLICK_DISTANCE as i16, 10),
+            (9 - DOUBLE_CLICK_DISTANCE as i16, 10),
+            (10, 11 + DOUBLE_CLICK_DISTANCE as i16),
+            (10, 9 - DOUBLE_CLICK_DISTANCE as i16),
+        ] {
+            let mut detector = DoubleClickDetector::new();
+
+            assert!(!detector.press(MouseButton::Left, 1000, 10, 10));
+            assert!(!detector.press(MouseButton::Left, 1100, x, y));
+        }
+    }
+
+    #[test]
+    fn click_of_another_button_is_not_a_double_click() {
+        let mut detector = DoubleClickDetector::new();
+
+        assert!(!detector.press(MouseButton::Left, 1000, 10, 10));
+        assert!(!detector.press(MouseButton::Right, 1100, 10, 10));
+        assert!(detector.press(MouseButton::Right, 1200, 10, 10));
+    }
+
+    #[test]
+    fn double_click_across_time_wrap_around() {
+        let mut detector = DoubleClickDetector::new();
+
+        assert!(!detector.press(MouseButton::Left, u32::MAX - 100, 10, 10));
+        assert!(detector.press(MouseButton::Left, 100, 10, 10));
+    }
+
+    #[test]
+    fn triple_click_is_a_double_click_then_a_single_click() {
+        let mut detector = DoubleClickDetector::new();
+
+        assert!(!detector.press(MouseButton::Left, 1000, 10, 10));
+        assert!(detector.press(MouseButton::Left, 1100, 10, 10));
+        assert!(!detector.press(MouseButton::Left, 1200, 10, 10));
+        assert!(detector.press(MouseButton::Left, 1300, 10, 10));
+    }
 }
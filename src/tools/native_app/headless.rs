@@ -0,0 +1,77 @@
+use std::{cell::Cell, process::ExitCode};
+
+use crate::core::engine_factory::EngineCreateInfo;
+
+#[cfg(all(target_os = "linux", feature = "OPENGL_SUPPORTED"))]
+use crate::core::graphics_types::RenderDeviceType;
+
+use super::{
+    app::{App, AppSettings},
+    events::{EventHandler, EventResult},
+    recording::{self, ReplayEventHandler},
+};
+
+pub enum HeadlessEvent {
+    Quit,
+}
+
+// There is no window to get events from: the application renders a fixed number of
+// frames and quits.
+pub struct HeadlessEventHandler {
+    num_frames: u32,
+    frame: Cell<u32>,
+}
+
+impl HeadlessEventHandler {
+    pub fn new(num_frames: u32) -> Self {
+        HeadlessEventHandler {
+            num_frames,
+            frame: Cell::new(0),
+        }
+    }
+}
+
+impl EventHandler for HeadlessEventHandler {
+    type EventType = HeadlessEvent;
+
+    // The event queue is drained once per frame
+    fn poll_event(&self) -> Option<HeadlessEvent> {
+        let frame = self.frame.get();
+        if frame >= self.num_frames {
+            Some(HeadlessEvent::Quit)
+        } else {
+            self.frame.set(frame + 1);
+            None
+        }
+    }
+
+    fn handle_event(&mut self, event: &HeadlessEvent) -> EventResult {
+        match event {
+            HeadlessEvent::Quit => EventResult::Quit,
+        }
+    }
+}
+
+pub fn main<Application>(settings: &AppSettings) -> Result<ExitCode, std::io::Error>
+where
+    Application: App,
+{
+    // The engine attaches to the GL context that is current on the calling thread
+    #[cfg(all(target_os = "linux", feature = "OPENGL_SUPPORTED"))]
+    let _gl_context = match settings.device_type {
+        RenderDeviceType::GL => Some(super::linux::create_headless_gl_context()?),
+        _ => None,
+    };
+
+    let app = Application::new(settings, EngineCreateInfo::default(), None);
+
+    // The application is released before the GL context
+    match &settings.replay_path {
+        Some(path) => recording::run(app, settings, ReplayEventHandler::new(path)?),
+        None => recording::run(
+            app,
+            settings,
+            HeadlessEventHandler::new(settings.num_frames),
+        ),
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashSet;
+
+#[cfg(feature = "VULKAN_SUPPORTED")]
+use xkbcommon::xkb;
+use xkbcommon::xkb::keysyms;
+
+#[cfg(feature = "VULKAN_SUPPORTED")]
+use crate::tools::native_app::events::EventResult;
+use crate::tools::native_app::events::{Key, KeyModifiers};
+
+// Modifier bits of the core X protocol key and button events
+const SHIFT_MASK: u32 = 1 << 0;
+const CONTROL_MASK: u32 = 1 << 2;
+const MOD1_MASK: u32 = 1 << 3;
+const MOD4_MASK: u32 = 1 << 6;
+
+pub(super) fn modifiers_from_state(state: u32) -> KeyModifiers {
+    let mut modifiers = KeyModifiers::None;
+    if state & SHIFT_MASK != 0 {
+        modifiers |= KeyModifiers::Shift;
+    }
+    if state & CONTROL_MASK != 0 {
+        modifiers |= KeyModifiers::Control;
+    }
+    if state & MOD1_MASK != 0 {
+        modifiers |= KeyModifiers::Alt;
+    }
+    if state & MOD4_MASK != 0 {
+        modifiers |= KeyModifiers::Super;
+    }
+    modifiers
+}
+
+// `keysym` is expected to be the symbol of the first shift level of the key
+pub(super) fn key_from_keysym(keysym: u32) -> Key {
+    match keysym {
+        keysyms::KEY_a => Key::A,
+        keysyms::KEY_b => Key::B,
+        keysyms::KEY_c => Key::C,
+        keysyms::KEY_d => Key::D,
+        keysyms::KEY_e => Key::E,
+        keysyms::KEY_f => Key::F,
+        keysyms::KEY_g => Key::G,
+        keysyms::KEY_h => Key::H,
+        keysyms::KEY_i => Key::I,
+        keysyms::KEY_j => Key::J,
+        keysyms::KEY_k => Key::K,
+        keysyms::KEY_l => Key::L,
+        keysyms::KEY_m => Key::M,
+        keysyms::KEY_n => Key::N,
+        keysyms::KEY_o => Key::O,
+        keysyms::KEY_p => Key::P,
+        keysyms::KEY_q => Key::Q,
+        keysyms::KEY_r => Key::R,
+        keysyms::KEY_s => Key::S,
+        keysyms::KEY_t => Key::T,
+        keysyms::KEY_u => Key::U,
+        keysyms::KEY_v => Key::V,
+        keysyms::KEY_w => Key::W,
+        keysyms::KEY_x => Key::X,
+        keysyms::KEY_y => Key::Y,
+        keysyms::KEY_z => Key::Z,
+
+        keysyms::KEY_0 => Key::Num0,
+        keysyms::KEY_1 => Key::Num1,
+        keysyms::KEY_2 => Key::Num2,
+        keysyms::KEY_3 => Key::Num3,
+        keysyms::KEY_4 => Key::Num4,
+        keysyms::KEY_5 => Key::Num5,
+        keysyms::KEY_6 => Key::Num6,
+        keysyms::KEY_7 => Key::Num7,
+        keysyms::KEY_8 => Key::Num8,
+        keysyms::KEY_9 => Key::Num9,
+
+        keysyms::KEY_F1 => Key::F1,
+        keysyms::KEY_F2 => Key::F2,
+        keysyms::KEY_F3 => Key::F3,
+        keysyms::KEY_F4 => Key::F4,
+        keysyms::KEY_F5 => Key::F5,
+        keysyms::KEY_F6 => Key::F6,
+        keysyms::KEY_F7 => Key::F7,
+        keysyms::KEY_F8 => Key::F8,
+        keysyms::KEY_F9 => Key::F9,
+        keysyms::KEY_F10 => Key::F10,
+        keysyms::KEY_F11 => Key::F11,
+        keysyms::KEY_F12 => Key::F12,
+
+        keysyms::KEY_Escape => Key::Escape,
+        keysyms::KEY_Return => Key::Enter,
+        keysyms::KEY_Tab | keysyms::KEY_ISO_Left_Tab => Key::Tab,
+        keysyms::KEY_BackSpace => Key::Backspace,
+        keysyms::KEY_space => Key::Space,
+        keysyms::KEY_Insert => Key::Insert,
+        keysyms::KEY_Delete => Key::Delete,
+        keysyms::KEY_Home => Key::Home,
+        keysyms::KEY_End => Key::End,
+        keysyms::KEY_Page_Up => Key::PageUp,
+        keysyms::KEY_Page_Down => Key::PageDown,
+        keysyms::KEY_Left => Key::Left,
+        keysyms::KEY_Right => Key::Right,
+        keysyms::KEY_Up => Key::Up,
+        keysyms::KEY_Down => Key::Down,
+
+        keysyms::KEY_minus => Key::Minus,
+        keysyms::KEY_equal => Key::Equal,
+        keysyms::KEY_bracketleft => Key::LeftBracket,
+        keysyms::KEY_bracketright => Key::RightBracket,
+        keysyms::KEY_backslash => Key::Backslash,
+        keysyms::KEY_semicolon => Key::Semicolon,
+        keysyms::KEY_apostrophe => Key::Apostrophe,
+        keysyms::KEY_comma => Key::Comma,
+        keysyms::KEY_period => Key::Period,
+        keysyms::KEY_slash => Key::Slash,
+        keysyms::KEY_grave => Key::GraveAccent,
+
+        // Without num lock, the first shift level of the keypad produces the navigation keys
+        keysyms::KEY_KP_0 | keysyms::KEY_KP_Insert => Key::Keypad0,
+        keysyms::KEY_KP_1 | keysyms::KEY_KP_End => Key::Keypad1,
+        keysyms::KEY_KP_2 | keysyms::KEY_KP_Down => Key::Keypad2,
+        keysyms::KEY_KP_3 | keysyms::KEY_KP_Next => Key::Keypad3,
+        keysyms::KEY_KP_4 | keysyms::KEY_KP_Left => Key::Keypad4,
+        keysyms::KEY_KP_5 | keysyms::KEY_KP_Begin => Key::Keypad5,
+        keysyms::KEY_KP_6 | keysyms::KEY_KP_Right => Key::Keypad6,
+        keysyms::KEY_KP_7 | keysyms::KEY_KP_Home => Key::Keypad7,
+        keysyms::KEY_KP_8 | keysyms::KEY_KP_Up => Key::Keypad8,
+        keysyms::KEY_KP_9 | keysyms::KEY_KP_Prior => Key::Keypad9,
+        keysyms::KEY_KP_Decimal | keysyms::KEY_KP_Separator | keysyms::KEY_KP_Delete => {
+            Key::KeypadDecimal
+        }
+        keysyms::KEY_KP_Divide => Key::KeypadDivide,
+        keysyms::KEY_KP_Multiply => Key::KeypadMultiply,
+        keysyms::KEY_KP_Subtract => Key::KeypadSubtract,
+        keysyms::KEY_KP_Add => Key::KeypadAdd,
+        keysyms::KEY_KP_Enter => Key::KeypadEnter,
+
+        keysyms::KEY_Shift_L => Key::LeftShift,
+        keysyms::KEY_Shift_R => Key::RightShift,
+        keysyms::KEY_Control_L => Key::LeftControl,
+        keysyms::KEY_Control_R => Key::RightControl,
+        keysyms::KEY_Alt_L | keysyms::KEY_Meta_L => Key::LeftAlt,
+        keysyms::KEY_Alt_R | keysyms::KEY_Meta_R | keysyms::KEY_ISO_Level3_Shift => Key::RightAlt,
+        keysyms::KEY_Super_L => Key::LeftSuper,
+        keysyms::KEY_Super_R => Key::RightSuper,
+
+        keysyms::KEY_Caps_Lock => Key::CapsLock,
+        keysyms::KEY_Num_Lock => Key::NumLock,
+        keysyms::KEY_Scroll_Lock => Key::ScrollLock,
+        keysyms::KEY_Print => Key::PrintScreen,
+        keysyms::KEY_Pause => Key::Pause,
+        keysyms::KEY_Menu => Key::Menu,
+
+        _ => Key::Unknown,
+    }
+}
+
+// Control characters, produced by keys like Enter or Backspace, are not text input, and
+// neither are shortcuts like Control+C or Alt+F. AltGr is not Alt: it is a shift level.
+pub(super) fn text_input(text: String, modifiers: KeyModifiers) -> Option<String> {
+    if text.is_empty()
+        || text.chars().any(char::is_control)
+        || modifiers.intersects(KeyModifiers::Control | KeyModifiers::Alt)
+    {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+// Tracks the pressed keys to detect repeats: the server is expected to report auto-repeat
+// without the intermediate key releases (XKB detectable auto-repeat).
+pub(super) struct PressedKeys {
+    keycodes: HashSet<u32>,
+}
+
+impl PressedKeys {
+    pub(super) fn new() -> Self {
+        PressedKeys {
+            keycodes: HashSet::new(),
+        }
+    }
+
+    // Returns true if the key was already pressed
+    pub(super) fn press(&mut self, keycode: u32) -> bool {
+        !self.keycodes.insert(keycode)
+    }
+
+    pub(super) fn release(&mut self, keycode: u32) {
+        self.keycodes.remove(&keycode);
+    }
+}
+
+// The key is identified by the symbol of the first shift level of the keycode in the active
+// layout
+#[cfg(feature = "VULKAN_SUPPORTED")]
+pub(super) fn key_from_keymap(
+    keymap: &xkb::Keymap,
+    state: &xkb::State,
+    keycode: xkb::Keycode,
+) -> Key {
+    keymap
+        .key_get_syms_by_level(keycode, state.key_get_layout(keycode), 0)
+        .first()
+        .map_or(Key::Unknown, |keysym| key_from_keysym(keysym.raw()))
+}
+
+#[cfg(feature = "WAYLAND_SUPPORTED")]
+pub(super) fn modifiers_from_xkb_state(state: &xkb::State) -> KeyModifiers {
+    let mut modifiers = KeyModifiers::None;
+    for (name, modifier) in [
+        (xkb::MOD_NAME_SHIFT, KeyModifiers::Shift),
+        (xkb::MOD_NAME_CTRL, KeyModifiers::Control),
+        (xkb::MOD_NAME_ALT, KeyModifiers::Alt),
+        (xkb::MOD_NAME_LOGO, KeyModifiers::Super),
+    ] {
+        if state.mod_name_is_active(name, xkb::STATE_MODS_EFFECTIVE) {
+            modifiers |= modifier;
+        }
+    }
+    modifiers
+}
+
+// Translates core X key events with the keymap of the keyboard retrieved through XKB
+#[cfg(feature = "VULKAN_SUPPORTED")]
+pub(super) struct XkbKeyboard {
+    keymap: xkb::Keymap,
+    state: xkb::State,
+    pressed_keys: PressedKeys,
+}
+
+#[cfg(feature = "VULKAN_SUPPORTED")]
+impl XkbKeyboard {
+    pub(super) fn new(connection: &xcb::Connection) -> Option<Self> {
+        let mut major_xkb_version = 0;
+        let mut minor_xkb_version = 0;
+        let mut base_event = 0;
+        let mut base_error = 0;
+
+        if !xkb::x11::setup_xkb_extension(
+            connection,
+            xkb::x11::MIN_MAJOR_XKB_VERSION,
+            xkb::x11::MIN_MINOR_XKB_VERSION,
+            xkb::x11::SetupXkbExtensionFlags::NoFlags,
+            &mut major_xkb_version,
+            &mut minor_xkb_version,
+            &mut base_event,
+            &mut base_error,
+        ) {
+            return None;
+        }
+
+        let device_id = xkb::x11::get_core_keyboard_device_id(connection);
+        if device_id < 0 {
+            return None;
+        }
+
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+
+        let keymap = xkb::x11::keymap_new_from_device(
+            &context,
+            connection,
+            device_id,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        );
+
+        let state = xkb::x11::state_new_from_device(&keymap, connection, device_id);
+
+        Some(XkbKeyboard {
+            keymap,
+            state,
+            pressed_keys: PressedKeys::new(),
+        })
+    }
+
+    // `core_state` is the modifier and button mask of the event, before the key is pressed
+    pub(super) fn handle_key(
+        &mut self,
+        keycode: u32,
+        core_state: u32,
+        pressed: bool,
+    ) -> EventResult {
+        let group = (core_state >> 13) & 0x3;
+        self.state.update_mask(core_state & 0xff, 0, 0, 0, 0, group);
+
+        let xkb_keycode = xkb::Keycode::new(keycode);
+
+        let key = key_from_keymap(&self.keymap, &self.state, xkb_keycode);
+
+        let modifiers = modifiers_from_state(core_state);
+
+        if pressed {
+            EventResult::KeyPress {
+                key,
+                modifiers,
+                repeat: self.pressed_keys.press(keycode),
+                text: text_input(self.state.key_get_utf8(xkb_keycode), modifiers),
+            }
+        } else {
+            self.pressed_keys.release(keycode);
+            EventResult::KeyRelease { key, modifiers }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        for (keysym, key) in [
+            (keysyms::KEY_a, Key::A),
+            (keysyms::KEY_z, Key::Z),
+            (keysyms::KEY_0, Key::Num0),
+            (keysyms::KEY_F1, Key::F1),
+            (keysyms::KEY_Escape, Key::Escape),
+            (keysyms::KEY_Return, Key::Enter),
+            (keysyms::KEY_space, Key::Space),
+            (keysyms::KEY_Left, Key::Left),
+            (keysyms::KEY_minus, Key::Minus),
+            (keysyms::KEY_KP_0, Key::Keypad0),
+            (keysyms::KEY_KP_Insert, Key::Keypad0),
+            (keysyms::KEY_KP_Enter, Key::KeypadEnter),
+            (keysyms::KEY_Shift_L, Key::LeftShift),
+            (keysyms::KEY_ISO_Level3_Shift, Key::RightAlt),
+            (keysyms::KEY_Menu, Key::Menu),
+            // Only the symbols of the first shift level are expected
+            (keysyms::KEY_A, Key::Unknown),
+            (keysyms::KEY_eacute, Key::Unknown),
+        ] {
+            assert_eq!(key_from_keysym(keysym), key, "keysym {keysym:#x}");
+        }
+    }
+
+    #[test]
+    fn modifiers() {
+        assert!(modifiers_from_state(0) == KeyModifiers::None);
+        assert!(modifiers_from_state(SHIFT_MASK) == KeyModifiers::Shift);
+        assert!(modifiers_from_state(CONTROL_MASK) == KeyModifiers::Control);
+        assert!(modifiers_from_state(MOD1_MASK) == KeyModifiers::Alt);
+        assert!(modifiers_from_state(MOD4_MASK) == KeyModifiers::Super);
+        assert!(
+            modifiers_from_state(SHIFT_MASK | CONTROL_MASK | MOD1_MASK | MOD4_MASK)
+                == KeyModifiers::Shift
+                    | KeyModifiers::Control
+                    | KeyModifiers::Alt
+                    | KeyModifiers::Super
+        );
+
+        // Lock (1), Mod2 (Num Lock), Mod3, Mod5 (AltGr) and the buttons are not modifiers
+        assert!(
+            modifiers_from_state(1 << 1 | 1 << 4 | 1 << 5 | 1 << 7 | 1 << 8) == KeyModifiers::None
+        );
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(
+            text_input("a".to_string(), KeyModifiers::None),
+            Some("a".to_string())
+        );
+        assert_eq!(
+            text_input("A".to_string(), KeyModifiers::Shift),
+            Some("A".to_string())
+        );
+        assert_eq!(
+            text_input("€".to_string(), KeyModifiers::None),
+            Some("€".to_string())
+        );
+        assert_eq!(
+            text_input("a".to_string(), KeyModifiers::Super),
+            Some("a".to_string())
+        );
+
+        assert_eq!(text_input(String::new(), KeyModifiers::None), None);
+        assert_eq!(text_input("\r".to_string(), KeyModifiers::None), None);
+        assert_eq!(text_input("\u{8}".to_string(), KeyModifiers::None), None);
+        assert_eq!(text_input("\u{1b}".to_string(), KeyModifiers::None), None);
+
+        assert_eq!(text_input("c".to_string(), KeyModifiers::Control), None);
+        assert_eq!(text_input("f".to_string(), KeyModifiers::Alt), None);
+        assert_eq!(
+            text_input("C".to_string(), KeyModifiers::Shift | KeyModifiers::Control),
+            None
+        );
+    }
+
+    #[test]
+    fn repeats() {
+        let mut pressed_keys = PressedKeys::new();
+
+        assert!(!pressed_keys.press(38));
+        assert!(pressed_keys.press(38));
+        assert!(!pressed_keys.press(39));
+
+        pressed_keys.release(38);
+        assert!(!pressed_keys.press(38));
+        assert!(pressed_keys.press(39));
+
+        // Releasing a key that is not pressed is ignored
+        pressed_keys.release(40);
+        assert!(!pressed_keys.press(40));
+    }
+}
@@ -0,0 +1,581 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    os::raw::c_void,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use wayland_client::{
+    delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_compositor, wl_keyboard, wl_pointer, wl_registry, wl_seat, wl_surface},
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+use xkbcommon::xkb;
+
+use super::keyboard::{key_from_keymap, modifiers_from_xkb_state, text_input};
+
+use crate::{
+    bindings,
+    core::engine_factory::EngineCreateInfo,
+    tools::native_app::{
+        app::{App, AppSettings, WindowDesc},
+        events::{DoubleClickDetector, EventHandler, EventResult, MouseButton},
+        recording,
+    },
+};
+
+// Linux input event codes of the mouse buttons
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+const BTN_FORWARD: u32 = 0x115;
+const BTN_BACK: u32 = 0x116;
+
+// Scroll distance of a wheel notch, in surface coordinates, as reported by most compositors
+const SCROLL_DISTANCE_PER_NOTCH: f64 = 10.0;
+
+struct KeyRepeat {
+    keycode: xkb::Keycode,
+    next_repeat_time: Instant,
+}
+
+// Wayland does not repeat keys: the client repeats the last pressed key with the rate and
+// delay requested by the compositor
+struct WaylandKeyboard {
+    context: xkb::Context,
+    keymap_and_state: Option<(xkb::Keymap, xkb::State)>,
+
+    // Repeats per second, 0 disables key repeat
+    repeat_rate: u32,
+    repeat_delay: Duration,
+    key_repeat: Option<KeyRepeat>,
+}
+
+impl WaylandKeyboard {
+    fn new() -> Self {
+        WaylandKeyboard {
+            context: xkb::Context::new(xkb::CONTEXT_NO_FLAGS),
+            keymap_and_state: None,
+            repeat_rate: 25,
+            repeat_delay: Duration::from_millis(600),
+            key_repeat: None,
+        }
+    }
+
+    fn key_event(&self, keycode: xkb::Keycode, pressed: bool, repeat: bool) -> EventResult {
+        let Some((keymap, state)) = &self.keymap_and_state else {
+            return EventResult::Continue;
+        };
+
+        let key = key_from_keymap(keymap, state, keycode);
+        let modifiers = modifiers_from_xkb_state(state);
+
+        if pressed {
+            EventResult::KeyPress {
+                key,
+                modifiers,
+                repeat,
+                text: text_input(state.key_get_utf8(keycode), modifiers),
+            }
+        } else {
+            EventResult::KeyRelease { key, modifiers }
+        }
+    }
+}
+
+struct WaylandState {
+    wm_base: xdg_wm_base::XdgWmBase,
+
+    pointer: Option<wl_pointer::WlPointer>,
+    keyboard_device: Option<wl_keyboard::WlKeyboard>,
+    keyboard: WaylandKeyboard,
+
+    // In surface coordinates, button events do not report the position of the pointer
+    pointer_position: (i16, i16),
+    double_click_detector: DoubleClickDetector,
+
+    configured: bool,
+    width: u16,
+    height: u16,
+    // The size of the last toplevel configure, applied with the following surface configure
+    pending_size: Option<(u16, u16)>,
+
+    events: VecDeque<EventResult>,
+}
+
+impl WaylandState {
+    fn repeat_key(&mut self) {
+        let Some(key_repeat) = &mut self.keyboard.key_repeat else {
+            return;
+        };
+
+        let now = Instant::now();
+        if now < key_repeat.next_repeat_time {
+            return;
+        }
+
+        key_repeat.next_repeat_time = now + Duration::from_secs(1) / self.keyboard.repeat_rate;
+
+        let keycode = key_repeat.keycode;
+        self.events
+            .push_back(self.keyboard.key_event(keycode, true, true));
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _connection: &Connection,
+        _queue_handle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(WaylandState: wl_compositor::WlCompositor);
+delegate_noop!(WaylandState: ignore wl_surface::WlSurface);
+
+impl Dispatch<xdg_wm_base::XdgWmBase, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        wm_base: &xdg_wm_base::XdgWmBase,
+        event: xdg_wm_base::Event,
+        _data: &(),
+        _connection: &Connection,
+        _queue_handle: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<xdg_surface::XdgSurface, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        xdg_surface: &xdg_surface::XdgSurface,
+        event: xdg_surface::Event,
+        _data: &(),
+        _connection: &Connection,
+        _queue_handle: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            xdg_surface.ack_configure(serial);
+            state.configured = true;
+
+            if let Some((width, height)) = state.pending_size.take() {
+                if (width, height) != (state.width, state.height) {
+                    state.width = width;
+                    state.height = height;
+                    state
+                        .events
+                        .push_back(EventResult::Resize { width, height });
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<xdg_toplevel::XdgToplevel, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _toplevel: &xdg_toplevel::XdgToplevel,
+        event: xdg_toplevel::Event,
+        _data: &(),
+        _connection: &Connection,
+        _queue_handle: &QueueHandle<Self>,
+    ) {
+        match event {
+            // A size of 0 lets the client choose the size of the window
+            xdg_toplevel::Event::Configure { width, height, .. } if width > 0 && height > 0 => {
+                state.pending_size = Some((width as u16, height as u16));
+            }
+            xdg_toplevel::Event::Close => state.events.push_back(EventResult::Quit),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        seat: &wl_seat::WlSeat,
+        event: wl_seat::Event,
+        _data: &(),
+        _connection: &Connection,
+        queue_handle: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        {
+            if capabilities.contains(wl_seat::Capability::Pointer) && state.pointer.is_none() {
+                state.pointer = Some(seat.get_pointer(queue_handle, ()));
+            }
+            if capabilities.contains(wl_seat::Capability::Keyboard)
+                && state.keyboard_device.is_none()
+            {
+                state.keyboard_device = Some(seat.get_keyboard(queue_handle, ()));
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_pointer::WlPointer, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _pointer: &wl_pointer::WlPointer,
+        event: wl_pointer::Event,
+        _data: &(),
+        _connection: &Connection,
+        _queue_handle: &QueueHandle<Self>,
+    ) {
+        let event = match event {
+            wl_pointer::Event::Enter {
+                surface_x,
+                surface_y,
+                ..
+            }
+            | wl_pointer::Event::Motion {
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                state.pointer_position = (surface_x as i16, surface_y as i16);
+                EventResult::MouseMove {
+                    x: surface_x as i16,
+                    y: surface_y as i16,
+                }
+            }
+
+            wl_pointer::Event::Button {
+                time,
+                button,
+                state: WEnum::Value(button_state),
+                ..
+            } => {
+                let button = match button {
+                    BTN_LEFT => MouseButton::Left,
+                    BTN_RIGHT => MouseButton::Right,
+                    BTN_MIDDLE => MouseButton::Middle,
+                    BTN_SIDE | BTN_BACK => MouseButton::Back,
+                    BTN_EXTRA | BTN_FORWARD => MouseButton::Forward,
+                    _ => return,
+                };
+
+                if button_state == wl_pointer::ButtonState::Pressed {
+                    let (x, y) = state.pointer_position;
+                    EventResult::MouseDown {
+                        button,
+                        double_click: state.double_click_detector.press(button, time, x, y),
+                    }
+                } else {
+                    EventResult::MouseUp { button }
+                }
+            }
+
+            // Positive values scroll down and right
+            wl_pointer::Event::Axis {
+                axis: WEnum::Value(axis),
+                value,
+                ..
+            } if value != 0.0 => {
+                let delta = (value / SCROLL_DISTANCE_PER_NOTCH) as f32;
+                match axis {
+                    wl_pointer::Axis::VerticalScroll => EventResult::MouseWheel {
+                        delta_x: 0.0,
+                        delta_y: -delta,
+                    },
+                    wl_pointer::Axis::HorizontalScroll => EventResult::MouseWheel {
+                        delta_x: delta,
+                        delta_y: 0.0,
+                    },
+                    _ => return,
+                }
+            }
+
+            _ => return,
+        };
+
+        state.events.push_back(event);
+    }
+}
+
+impl Dispatch<wl_keyboard::WlKeyboard, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _keyboard: &wl_keyboard::WlKeyboard,
+        event: wl_keyboard::Event,
+        _data: &(),
+        _connection: &Connection,
+        _queue_handle: &QueueHandle<Self>,
+    ) {
+        let keyboard = &mut state.keyboard;
+
+        match event {
+            wl_keyboard::Event::Keymap {
+                format: WEnum::Value(wl_keyboard::KeymapFormat::XkbV1),
+                fd,
+                size,
+            } => {
+                let keymap = unsafe {
+                    xkb::Keymap::new_from_fd(
+                        &keyboard.context,
+                        fd,
+                        size as usize,
+                        xkb::KEYMAP_FORMAT_TEXT_V1,
+                        xkb::KEYMAP_COMPILE_NO_FLAGS,
+                    )
+                };
+
+                keyboard.keymap_and_state = match keymap {
+                    Ok(Some(keymap)) => {
+                        let state = xkb::State::new(&keymap);
+                        Some((keymap, state))
+                    }
+                    _ => {
+                        println!("Failed to load the Wayland keymap, key events are ignored");
+                        None
+                    }
+                };
+            }
+
+            wl_keyboard::Event::Key {
+                key,
+                state: WEnum::Value(key_state),
+                ..
+            } => {
+                // Wayland keycodes are evdev codes, XKB keycodes are offset by 8
+                let keycode = xkb::Keycode::new(key + 8);
+                let pressed = key_state == wl_keyboard::KeyState::Pressed;
+
+                let repeats = keyboard
+                    .keymap_and_state
+                    .as_ref()
+                    .is_some_and(|(keymap, _)| keymap.key_repeats(keycode));
+
+                if pressed && repeats && keyboard.repeat_rate > 0 {
+                    keyboard.key_repeat = Some(KeyRepeat {
+                        keycode,
+                        next_repeat_time: Instant::now() + keyboard.repeat_delay,
+                    });
+                } else if keyboard
+                    .key_repeat
+                    .as_ref()
+                    .is_some_and(|key_repeat| key_repeat.keycode == keycode)
+                {
+                    keyboard.key_repeat = None;
+                }
+
+                let event = keyboard.key_event(keycode, pressed, false);
+                state.events.push_back(event);
+            }
+
+            wl_keyboard::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => {
+                if let Some((_, state)) = &mut keyboard.keymap_and_state {
+                    state.update_mask(mods_depressed, mods_latched, mods_locked, 0, 0, group);
+                }
+            }
+
+            wl_keyboard::Event::RepeatInfo { rate, delay } => {
+                keyboard.repeat_rate = rate.max(0) as u32;
+                keyboard.repeat_delay = Duration::from_millis(delay.max(0) as u64);
+                if keyboard.repeat_rate == 0 {
+                    keyboard.key_repeat = None;
+                }
+            }
+
+            wl_keyboard::Event::Leave { .. } => keyboard.key_repeat = None,
+
+            _ => {}
+        }
+    }
+}
+
+// A wl_surface with the xdg-shell toplevel role, destroyed when the last reference to it is
+// released: the swap chain created for the surface must be released before.
+struct WaylandWindow {
+    connection: Connection,
+
+    surface: wl_surface::WlSurface,
+    xdg_surface: xdg_surface::XdgSurface,
+    toplevel: xdg_toplevel::XdgToplevel,
+}
+
+impl WaylandWindow {
+    fn get_native_window(&self) -> bindings::NativeWindow {
+        bindings::NativeWindow {
+            WindowId: 0,
+            pDisplay: self.connection.backend().display_ptr() as *mut c_void,
+            pXCBConnection: std::ptr::null_mut(),
+            pWaylandSurface: self.surface.id().as_ptr() as *mut c_void,
+        }
+    }
+}
+
+impl Drop for WaylandWindow {
+    fn drop(&mut self) {
+        self.toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.surface.destroy();
+        let _ = self.connection.flush();
+    }
+}
+
+// The cursor controls of `EventHandler` are not supported, they require the
+// pointer-constraints and relative-pointer protocols.
+// There is no automated test of this path: it can be run by hand in a headless compositor,
+// e.g. with `weston --backend=headless` and `WAYLAND_DISPLAY` set to its socket.
+pub(super) struct WaylandEventHandler {
+    window: Rc<WaylandWindow>,
+    event_queue: RefCell<EventQueue<WaylandState>>,
+    state: RefCell<WaylandState>,
+}
+
+impl WaylandEventHandler {
+    // Fails if no Wayland compositor is available
+    pub(super) fn new(window_desc: &WindowDesc) -> Result<Self, std::io::Error> {
+        let connection = Connection::connect_to_env().map_err(std::io::Error::other)?;
+
+        let (globals, mut event_queue) =
+            registry_queue_init::<WaylandState>(&connection).map_err(std::io::Error::other)?;
+
+        let queue_handle = event_queue.handle();
+
+        let compositor: wl_compositor::WlCompositor = globals
+            .bind(&queue_handle, 1..=6, ())
+            .map_err(std::io::Error::other)?;
+
+        let wm_base: xdg_wm_base::XdgWmBase = globals
+            .bind(&queue_handle, 1..=6, ())
+            .map_err(std::io::Error::other)?;
+
+        // Input is optional, there might not be any seat in a headless compositor
+        let _seat: Option<wl_seat::WlSeat> = globals.bind(&queue_handle, 1..=7, ()).ok();
+
+        let surface = compositor.create_surface(&queue_handle, ());
+        let xdg_surface = wm_base.get_xdg_surface(&surface, &queue_handle, ());
+        let toplevel = xdg_surface.get_toplevel(&queue_handle, ());
+
+        toplevel.set_title(window_desc.title.clone());
+
+        if window_desc.resizable {
+            toplevel.set_min_size(window_desc.min_width as i32, window_desc.min_height as i32);
+        } else {
+            toplevel.set_min_size(window_desc.width as i32, window_desc.height as i32);
+            toplevel.set_max_size(window_desc.width as i32, window_desc.height as i32);
+        }
+
+        if window_desc.fullscreen {
+            toplevel.set_fullscreen(None);
+        }
+
+        // The surface must not be drawn to before the first configure event
+        surface.commit();
+
+        let mut state = WaylandState {
+            wm_base,
+            pointer: None,
+            keyboard_device: None,
+            keyboard: WaylandKeyboard::new(),
+            pointer_position: (0, 0),
+            double_click_detector: DoubleClickDetector::new(),
+            configured: false,
+            width: window_desc.width,
+            height: window_desc.height,
+            pending_size: None,
+            events: VecDeque::new(),
+        };
+
+        while !state.configured {
+            event_queue
+                .blocking_dispatch(&mut state)
+                .map_err(std::io::Error::other)?;
+        }
+
+        Ok(WaylandEventHandler {
+            window: Rc::new(WaylandWindow {
+                connection,
+                surface,
+                xdg_surface,
+                toplevel,
+            }),
+            event_queue: RefCell::new(event_queue),
+            state: RefCell::new(state),
+        })
+    }
+}
+
+impl EventHandler for WaylandEventHandler {
+    // The events are translated while they are dispatched
+    type EventType = EventResult;
+
+    fn poll_event(&self) -> Option<EventResult> {
+        let mut state = self.state.borrow_mut();
+
+        if state.events.is_empty() {
+            let mut event_queue = self.event_queue.borrow_mut();
+
+            // A broken connection is reported by the dispatch
+            let _ = event_queue.flush();
+
+            // Reading fails with `WouldBlock` when there are no new events
+            if let Some(guard) = event_queue.prepare_read() {
+                let _ = guard.read();
+            }
+
+            if let Err(error) = event_queue.dispatch_pending(&mut state) {
+                println!("Wayland connection error: {error}");
+                return Some(EventResult::Quit);
+            }
+
+            state.repeat_key();
+        }
+
+        state.events.pop_front()
+    }
+
+    fn handle_event(&mut self, event: &EventResult) -> EventResult {
+        event.clone()
+    }
+
+    fn set_title(&self, title: &str) {
+        self.window.toplevel.set_title(title.to_string());
+        let _ = self.window.connection.flush();
+    }
+}
+
+pub(super) fn main<Application>(
+    settings: &AppSettings,
+    event_handler: WaylandEventHandler,
+) -> Result<std::process::ExitCode, std::io::Error>
+where
+    Application: App,
+{
+    // The event handler may be released before the application: the window is kept alive
+    // until the application, and therefore its swap chain, has been released by `run`
+    let window = event_handler.window.clone();
+
+    let native_window = window.get_native_window();
+
+    let app = Application::new(settings, EngineCreateInfo::default(), Some(&native_window));
+
+    let result = recording::run(app, settings, event_handler);
+
+    drop(window);
+
+    result
+}
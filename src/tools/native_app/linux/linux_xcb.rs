@@ -2,21 +2,36 @@ use std::os::raw::c_void;
 
 use xcb::{x, Xid};
 
+use super::{keyboard::XkbKeyboard, mouse};
+
 use crate::{
     bindings,
-    core::{engine_factory::EngineCreateInfo, graphics_types::RenderDeviceType},
+    core::engine_factory::EngineCreateInfo,
     tools::native_app::{
-        app::App,
-        events::{EventHandler, EventResult, MouseButton},
+        app::{App, AppSettings, WindowDesc},
+        events::{DoubleClickDetector, EventHandler, EventResult},
+        recording,
     },
 };
 
+// Flags of the WM_SIZE_HINTS property
+const SIZE_HINT_P_MIN_SIZE: u32 = 1 << 4;
+const SIZE_HINT_P_MAX_SIZE: u32 = 1 << 5;
+
+fn intern_atom(connection: &xcb::Connection, name: &[u8]) -> xcb::Result<x::Atom> {
+    let cookie = connection.send_request(&x::InternAtom {
+        only_if_exists: false,
+        name,
+    });
+    Ok(connection.wait_for_reply(cookie)?.atom())
+}
+
 fn init_connection_and_window(
-    width: u16,
-    height: u16,
+    window_desc: &WindowDesc,
 ) -> xcb::Result<(xcb::Connection, x::Window, x::Atom)> {
     let (connection, screen_number) =
-        xcb::Connection::connect(None).expect("Unable to make an XCB connection");
+        xcb::Connection::connect_with_extensions(None, &[xcb::Extension::Xkb], &[])
+            .expect("Unable to make an XCB connection");
 
     let setup = connection.get_setup();
 
@@ -30,8 +45,8 @@ fn init_connection_and_window(
         parent: screen.root(),
         x: 0,
         y: 0,
-        width,
-        height,
+        width: window_desc.width,
+        height: window_desc.height,
         border_width: 0,
         class: x::WindowClass::InputOutput,
         visual: screen.root_visual(),
@@ -76,18 +91,74 @@ fn init_connection_and_window(
         window,
         property: x::ATOM_WM_NAME,
         r#type: x::ATOM_STRING,
-        data: b"Test",
+        data: window_desc.title.as_bytes(),
     });
 
-    // TODO : set the XCB_ATOM_WM_NORMAL_HINTS for minimal window size
+    {
+        // A window that is not resizable has its maximum size equal to its minimum size
+        let (min_width, min_height, max_width, max_height) = if window_desc.resizable {
+            (window_desc.min_width, window_desc.min_height, 0, 0)
+        } else {
+            (
+                window_desc.width,
+                window_desc.height,
+                window_desc.width,
+                window_desc.height,
+            )
+        };
+
+        let flags = if window_desc.resizable {
+            SIZE_HINT_P_MIN_SIZE
+        } else {
+            SIZE_HINT_P_MIN_SIZE | SIZE_HINT_P_MAX_SIZE
+        };
+
+        // flags, x, y, width, height, min size, max size, size increments, min aspect,
+        // max aspect, base size and window gravity
+        #[rustfmt::skip]
+        let size_hints: [u32; 18] = [
+            flags,
+            0, 0, 0, 0,
+            min_width as u32, min_height as u32,
+            max_width as u32, max_height as u32,
+            0, 0,
+            0, 0, 0, 0,
+            0, 0,
+            0,
+        ];
+
+        connection.send_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: x::ATOM_WM_NORMAL_HINTS,
+            r#type: x::ATOM_WM_SIZE_HINTS,
+            data: &size_hints,
+        });
+    }
+
+    // The window manager reads the initial state of the window when it is mapped
+    if window_desc.fullscreen {
+        let atom_wm_state = intern_atom(&connection, b"_NET_WM_STATE")?;
+        let atom_wm_state_fullscreen = intern_atom(&connection, b"_NET_WM_STATE_FULLSCREEN")?;
+
+        connection.send_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: atom_wm_state,
+            r#type: x::ATOM_ATOM,
+            data: &[atom_wm_state_fullscreen],
+        });
+    }
 
     connection.send_request(&x::MapWindow { window });
 
     // Force the x/y coordinates to 100,100 results are identical in consecutive runs
-    connection.send_request(&x::ConfigureWindow {
-        window: window,
-        value_list: &[x::ConfigWindow::X(100), x::ConfigWindow::Y(100)],
-    });
+    if !window_desc.fullscreen {
+        connection.send_request(&x::ConfigureWindow {
+            window,
+            value_list: &[x::ConfigWindow::X(100), x::ConfigWindow::Y(100)],
+        });
+    }
 
     connection.flush()?;
 
@@ -103,17 +174,176 @@ fn init_connection_and_window(
     Ok((connection, window, atom_wm_delete_window))
 }
 
+fn create_invisible_cursor(connection: &xcb::Connection, window: x::Window) -> x::Cursor {
+    let pixmap = connection.generate_id();
+    connection.send_request(&x::CreatePixmap {
+        depth: 1,
+        pid: pixmap,
+        drawable: x::Drawable::Window(window),
+        width: 1,
+        height: 1,
+    });
+
+    let cursor = connection.generate_id();
+    connection.send_request(&x::CreateCursor {
+        cid: cursor,
+        source: pixmap,
+        mask: pixmap,
+        fore_red: 0,
+        fore_green: 0,
+        fore_blue: 0,
+        back_red: 0,
+        back_green: 0,
+        back_blue: 0,
+        x: 0,
+        y: 0,
+    });
+
+    connection.send_request(&x::FreePixmap { pixmap });
+
+    cursor
+}
+
 struct XcbEventHandler {
     connection: xcb::Connection,
+    window: x::Window,
     atom_delete_window: xcb::x::Atom,
+    keyboard: Option<XkbKeyboard>,
+    double_click_detector: DoubleClickDetector,
+
+    width: u16,
+    height: u16,
+
+    invisible_cursor: x::Cursor,
+    cursor_visible: bool,
+    cursor_grab: bool,
+    relative_mouse_mode: bool,
+    pointer_grabbed: bool,
 }
 
 impl XcbEventHandler {
-    fn new(connection: xcb::Connection, atom_delete_window: xcb::x::Atom) -> Self {
+    fn new(
+        connection: xcb::Connection,
+        window: x::Window,
+        atom_delete_window: xcb::x::Atom,
+        window_desc: &WindowDesc,
+    ) -> Self {
+        let keyboard = XkbKeyboard::new(&connection);
+        if keyboard.is_some() {
+            // Holding a key down sends repeated presses without the intermediate releases
+            connection.send_request(&xcb::xkb::PerClientFlags {
+                device_spec: xcb::xkb::Id::UseCoreKbd as xcb::xkb::DeviceSpec,
+                change: xcb::xkb::PerClientFlag::DETECTABLE_AUTO_REPEAT,
+                value: xcb::xkb::PerClientFlag::DETECTABLE_AUTO_REPEAT,
+                ctrls_to_change: xcb::xkb::BoolCtrl::empty(),
+                auto_ctrls: xcb::xkb::BoolCtrl::empty(),
+                auto_ctrls_values: xcb::xkb::BoolCtrl::empty(),
+            });
+        } else {
+            println!("Failed to initialize the XKB keyboard, key events are ignored");
+        }
+
+        let invisible_cursor = create_invisible_cursor(&connection, window);
+
         XcbEventHandler {
             connection,
+            window,
             atom_delete_window,
+            keyboard,
+            double_click_detector: DoubleClickDetector::new(),
+            width: window_desc.width,
+            height: window_desc.height,
+            invisible_cursor,
+            cursor_visible: true,
+            cursor_grab: false,
+            relative_mouse_mode: false,
+            pointer_grabbed: false,
+        }
+    }
+
+    fn get_window_center(&self) -> (i16, i16) {
+        ((self.width / 2) as i16, (self.height / 2) as i16)
+    }
+
+    fn warp_pointer_to_center(&self) {
+        let (x, y) = self.get_window_center();
+        self.connection.send_request(&x::WarpPointer {
+            src_window: x::WINDOW_NONE,
+            dst_window: self.window,
+            src_x: 0,
+            src_y: 0,
+            src_width: 0,
+            src_height: 0,
+            dst_x: x,
+            dst_y: y,
+        });
+    }
+
+    fn grab_pointer(&self) -> bool {
+        let cookie = self.connection.send_request(&x::GrabPointer {
+            owner_events: true,
+            grab_window: self.window,
+            event_mask: x::EventMask::POINTER_MOTION
+                | x::EventMask::BUTTON_PRESS
+                | x::EventMask::BUTTON_RELEASE,
+            pointer_mode: x::GrabMode::Async,
+            keyboard_mode: x::GrabMode::Async,
+            confine_to: self.window,
+            cursor: x::CURSOR_NONE,
+            time: x::CURRENT_TIME,
+        });
+
+        matches!(
+            self.connection.wait_for_reply(cookie),
+            Ok(reply) if reply.status() == x::GrabStatus::Success
+        )
+    }
+
+    // The grab fails while the window is not viewable or another client holds the pointer,
+    // so it is retried when the pointer moves over the window
+    fn retry_pointer_grab(&mut self) {
+        if (self.cursor_grab || self.relative_mouse_mode) && !self.pointer_grabbed {
+            self.pointer_grabbed = self.grab_pointer();
+        }
+    }
+
+    // Relative mouse mode overrides the cursor visibility and grab requested by the application
+    fn update_cursor(&mut self) {
+        let cursor = if self.cursor_visible && !self.relative_mouse_mode {
+            x::CURSOR_NONE
+        } else {
+            self.invisible_cursor
+        };
+
+        self.connection.send_request(&x::ChangeWindowAttributes {
+            window: self.window,
+            value_list: &[x::Cw::Cursor(cursor)],
+        });
+
+        if self.cursor_grab || self.relative_mouse_mode {
+            self.pointer_grabbed = self.grab_pointer();
+        } else {
+            self.connection.send_request(&x::UngrabPointer {
+                time: x::CURRENT_TIME,
+            });
+            self.pointer_grabbed = false;
+        }
+
+        if self.relative_mouse_mode {
+            self.warp_pointer_to_center();
         }
+
+        self.connection.flush().unwrap();
+    }
+}
+
+impl Drop for XcbEventHandler {
+    fn drop(&mut self) {
+        self.connection.send_request(&x::FreeCursor {
+            cursor: self.invisible_cursor,
+        });
+        // Errors are irrelevant as the connection is closed right after
+        let _ = self.connection.flush();
     }
 }
 
@@ -135,86 +365,118 @@ impl EventHandler for XcbEventHandler {
                 return EventResult::Continue;
             }
 
-            xcb::Event::X(x::Event::KeyRelease(_key_event)) => {
-                // TODO
-                EventResult::Continue
-            }
+            xcb::Event::X(x::Event::KeyPress(key_event)) => match &mut self.keyboard {
+                Some(keyboard) => {
+                    keyboard.handle_key(key_event.detail() as u32, key_event.state().bits(), true)
+                }
+                None => EventResult::Continue,
+            },
+
+            xcb::Event::X(x::Event::KeyRelease(key_event)) => match &mut self.keyboard {
+                Some(keyboard) => {
+                    keyboard.handle_key(key_event.detail() as u32, key_event.state().bits(), false)
+                }
+                None => EventResult::Continue,
+            },
 
             xcb::Event::X(x::Event::DestroyNotify(_destroy_event)) => EventResult::Quit,
 
-            xcb::Event::X(x::Event::ConfigureNotify(configure_event)) => EventResult::Resize {
-                width: configure_event.width(),
-                height: configure_event.height(),
-            },
+            xcb::Event::X(x::Event::ConfigureNotify(configure_event)) => {
+                self.width = configure_event.width();
+                self.height = configure_event.height();
+                EventResult::Resize {
+                    width: configure_event.width(),
+                    height: configure_event.height(),
+                }
+            }
 
-            xcb::Event::X(xcb::x::Event::MotionNotify(motion_event)) => EventResult::MouseMove {
-                x: motion_event.event_x(),
-                y: motion_event.event_y(),
-            },
+            xcb::Event::X(xcb::x::Event::MotionNotify(motion_event)) => {
+                self.retry_pointer_grab();
 
-            xcb::Event::X(xcb::x::Event::ButtonPress(press_event)) => match press_event.detail() {
-                1 => EventResult::MouseDown {
-                    button: MouseButton::Left,
-                },
-                2 => EventResult::MouseDown {
-                    button: MouseButton::Right,
-                },
-                3 => EventResult::MouseDown {
-                    button: MouseButton::Middle,
-                },
-                4 => EventResult::MouseWheel { up: true },
-                5 => EventResult::MouseWheel { up: false },
-                _ => EventResult::Continue,
-            },
+                if self.relative_mouse_mode {
+                    let (center_x, center_y) = self.get_window_center();
+                    let (x, y) = (motion_event.event_x(), motion_event.event_y());
+
+                    // Warping the pointer back to the center generates a motion event as well
+                    if (x, y) == (center_x, center_y) {
+                        return EventResult::Continue;
+                    }
 
-            xcb::Event::X(xcb::x::Event::ButtonRelease(release_event)) => {
-                match release_event.detail() {
-                    1 => EventResult::MouseUp {
-                        button: MouseButton::Left,
-                    },
-                    2 => EventResult::MouseUp {
-                        button: MouseButton::Right,
-                    },
-                    3 => EventResult::MouseUp {
-                        button: MouseButton::Middle,
-                    },
-                    _ => EventResult::Continue,
+                    self.warp_pointer_to_center();
+                    self.connection.flush().unwrap();
+
+                    EventResult::MouseMoveRelative {
+                        dx: x - center_x,
+                        dy: y - center_y,
+                    }
+                } else {
+                    EventResult::MouseMove {
+                        x: motion_event.event_x(),
+                        y: motion_event.event_y(),
+                    }
                 }
             }
 
+            xcb::Event::X(xcb::x::Event::ButtonPress(_) | xcb::x::Event::ButtonRelease(_)) => {
+                mouse::xcb_button_event(&mut self.double_click_detector, event)
+            }
+
             _ => EventResult::Continue,
         }
     }
+
+    fn set_title(&self, title: &str) {
+        self.connection.send_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: self.window,
+            property: x::ATOM_WM_NAME,
+            r#type: x::ATOM_STRING,
+            data: title.as_bytes(),
+        });
+        self.connection.flush().unwrap();
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+        self.update_cursor();
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) {
+        self.cursor_grab = grab;
+        self.update_cursor();
+    }
+
+    fn set_relative_mouse_mode(&mut self, enabled: bool) {
+        self.relative_mouse_mode = enabled;
+        self.update_cursor();
+    }
 }
 
-pub(super) fn main<Application>() -> Result<(), std::io::Error>
+pub(super) fn main<Application>(
+    settings: &AppSettings,
+) -> Result<std::process::ExitCode, std::io::Error>
 where
     Application: App,
 {
-    let width = 1024;
-    let height = 768;
+    let window_desc = Application::get_window_desc(settings);
 
     let (connection, window, atom_delete_window) =
-        init_connection_and_window(width, height).unwrap();
+        init_connection_and_window(&window_desc).unwrap();
 
     let native_window = bindings::NativeWindow {
         WindowId: window.resource_id(),
         pXCBConnection: connection.get_raw_conn() as *mut c_void,
         pDisplay: std::ptr::null_mut(),
+        pWaylandSurface: std::ptr::null_mut(),
     };
 
-    // TODO : Get other device types from console arguments
-    let device_type = RenderDeviceType::VULKAN;
-
-    let app = Application::new(
-        device_type,
-        EngineCreateInfo::default(),
-        Some(&native_window),
-        width,
-        height,
-    );
+    let app = Application::new(settings, EngineCreateInfo::default(), Some(&native_window));
 
     connection.flush().unwrap();
 
-    app.run(XcbEventHandler::new(connection, atom_delete_window))
+    recording::run(
+        app,
+        settings,
+        XcbEventHandler::new(connection, window, atom_delete_window, &window_desc),
+    )
 }
@@ -0,0 +1,681 @@
+use std::{
+    ffi::CString,
+    os::raw::{c_char, c_int, c_void},
+};
+
+use x11::{glx, xlib};
+use xkbcommon::xkb;
+
+use super::{
+    keyboard::{key_from_keysym, modifiers_from_state, text_input, PressedKeys},
+    mouse,
+};
+
+use crate::{
+    bindings,
+    core::engine_factory::EngineCreateInfo,
+    tools::native_app::{
+        app::{App, AppSettings, WindowDesc},
+        events::{DoubleClickDetector, EventHandler, EventResult},
+        recording,
+    },
+};
+
+type GlXCreateContextAttribsARB = unsafe extern "C" fn(
+    *mut xlib::Display,
+    glx::GLXFBConfig,
+    glx::GLXContext,
+    xlib::Bool,
+    *const c_int,
+) -> glx::GLXContext;
+
+unsafe fn create_context(
+    display: *mut xlib::Display,
+    fb_config: glx::GLXFBConfig,
+) -> Result<glx::GLXContext, std::io::Error> {
+    let create_context_attribs: Option<GlXCreateContextAttribsARB> =
+        glx::glXGetProcAddressARB(c"glXCreateContextAttribsARB".as_ptr() as *const u8)
+            .map(|function| std::mem::transmute(function));
+
+    let Some(create_context_attribs) = create_context_attribs else {
+        return Err(std::io::Error::other(
+            "glXCreateContextAttribsARB entry point not found",
+        ));
+    };
+
+    let flags = if cfg!(debug_assertions) {
+        glx::arb::GLX_CONTEXT_FORWARD_COMPATIBLE_BIT_ARB | glx::arb::GLX_CONTEXT_DEBUG_BIT_ARB
+    } else {
+        glx::arb::GLX_CONTEXT_FORWARD_COMPATIBLE_BIT_ARB
+    };
+
+    #[rustfmt::skip]
+    let context_attribs = [
+        glx::arb::GLX_CONTEXT_MAJOR_VERSION_ARB, 4,
+        glx::arb::GLX_CONTEXT_MINOR_VERSION_ARB, 3,
+        glx::arb::GLX_CONTEXT_FLAGS_ARB, flags,
+        0,
+    ];
+
+    let context = create_context_attribs(
+        display,
+        fb_config,
+        std::ptr::null_mut(),
+        xlib::True,
+        context_attribs.as_ptr(),
+    );
+
+    if context.is_null() {
+        Err(std::io::Error::other("Failed to create the GL context"))
+    } else {
+        Ok(context)
+    }
+}
+
+struct XlibWindow {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    context: glx::GLXContext,
+    atom_delete_window: xlib::Atom,
+}
+
+fn init_display_and_window(window_desc: &WindowDesc) -> Result<XlibWindow, std::io::Error> {
+    unsafe {
+        let display = xlib::XOpenDisplay(std::ptr::null());
+        if display.is_null() {
+            return Err(std::io::Error::other("Unable to open the X display"));
+        }
+
+        #[rustfmt::skip]
+        let visual_attribs = [
+            glx::GLX_RENDER_TYPE,   glx::GLX_RGBA_BIT,
+            glx::GLX_DRAWABLE_TYPE, glx::GLX_WINDOW_BIT,
+            glx::GLX_DOUBLEBUFFER,  1,
+
+            // The largest available total RGBA color buffer size (sum of GLX_RED_SIZE,
+            // GLX_GREEN_SIZE, GLX_BLUE_SIZE, and GLX_ALPHA_SIZE) of at least the minimum
+            // size specified for each color component is preferred.
+            glx::GLX_RED_SIZE,   8,
+            glx::GLX_GREEN_SIZE, 8,
+            glx::GLX_BLUE_SIZE,  8,
+            glx::GLX_ALPHA_SIZE, 8,
+
+            // The largest available depth buffer of at least GLX_DEPTH_SIZE is preferred
+            glx::GLX_DEPTH_SIZE,   24,
+            glx::GLX_STENCIL_SIZE, 8,
+
+            glx::GLX_SAMPLE_BUFFERS, 0,
+            0,
+        ];
+
+        let mut fb_count = 0;
+        let fb_configs = glx::glXChooseFBConfig(
+            display,
+            xlib::XDefaultScreen(display),
+            visual_attribs.as_ptr(),
+            std::ptr::addr_of_mut!(fb_count),
+        );
+        if fb_configs.is_null() || fb_count == 0 {
+            xlib::XCloseDisplay(display);
+            return Err(std::io::Error::other(
+                "Failed to retrieve a framebuffer config",
+            ));
+        }
+        let fb_config = *fb_configs;
+
+        let visual_info = glx::glXGetVisualFromFBConfig(display, fb_config);
+
+        let root = xlib::XRootWindow(display, (*visual_info).screen);
+
+        let mut window_attribs: xlib::XSetWindowAttributes = std::mem::zeroed();
+        window_attribs.colormap =
+            xlib::XCreateColormap(display, root, (*visual_info).visual, xlib::AllocNone);
+        window_attribs.border_pixel = 0;
+        window_attribs.event_mask = xlib::StructureNotifyMask
+            | xlib::ExposureMask
+            | xlib::KeyPressMask
+            | xlib::KeyReleaseMask
+            | xlib::ButtonPressMask
+            | xlib::ButtonReleaseMask
+            | xlib::PointerMotionMask;
+
+        let window = xlib::XCreateWindow(
+            display,
+            root,
+            0,
+            0,
+            window_desc.width as u32,
+            window_desc.height as u32,
+            0,
+            (*visual_info).depth,
+            xlib::InputOutput as u32,
+            (*visual_info).visual,
+            xlib::CWBorderPixel | xlib::CWColormap | xlib::CWEventMask,
+            std::ptr::addr_of_mut!(window_attribs),
+        );
+
+        xlib::XFree(visual_info as *mut c_void);
+
+        if window == 0 {
+            xlib::XFree(fb_configs as *mut c_void);
+            xlib::XCloseDisplay(display);
+            return Err(std::io::Error::other("Failed to create the window"));
+        }
+
+        {
+            let size_hints = xlib::XAllocSizeHints();
+            if window_desc.resizable {
+                (*size_hints).flags = xlib::PMinSize;
+                (*size_hints).min_width = window_desc.min_width as c_int;
+                (*size_hints).min_height = window_desc.min_height as c_int;
+            } else {
+                // A window that is not resizable has its maximum size equal to its minimum size
+                (*size_hints).flags = xlib::PMinSize | xlib::PMaxSize;
+                (*size_hints).min_width = window_desc.width as c_int;
+                (*size_hints).min_height = window_desc.height as c_int;
+                (*size_hints).max_width = window_desc.width as c_int;
+                (*size_hints).max_height = window_desc.height as c_int;
+            }
+            xlib::XSetWMNormalHints(display, window, size_hints);
+            xlib::XFree(size_hints as *mut c_void);
+        }
+
+        // Holding a key down sends repeated presses without the intermediate releases
+        xlib::XkbSetDetectableAutoRepeat(display, xlib::True, std::ptr::null_mut());
+
+        // The window manager reads the initial state of the window when it is mapped
+        if window_desc.fullscreen {
+            let atom_wm_state = xlib::XInternAtom(display, c"_NET_WM_STATE".as_ptr(), xlib::False);
+            let atom_wm_state_fullscreen =
+                xlib::XInternAtom(display, c"_NET_WM_STATE_FULLSCREEN".as_ptr(), xlib::False);
+
+            xlib::XChangeProperty(
+                display,
+                window,
+                atom_wm_state,
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                std::ptr::addr_of!(atom_wm_state_fullscreen) as *const u8,
+                1,
+            );
+        }
+
+        xlib::XMapWindow(display, window);
+
+        let context = create_context(display, fb_config);
+
+        xlib::XFree(fb_configs as *mut c_void);
+
+        let context = match context {
+            Ok(context) => context,
+            Err(error) => {
+                xlib::XDestroyWindow(display, window);
+                xlib::XCloseDisplay(display);
+                return Err(error);
+            }
+        };
+
+        glx::glXMakeCurrent(display, window, context);
+
+        // Titles with interior nul characters are ignored
+        if let Ok(title) = CString::new(window_desc.title.as_str()) {
+            xlib::XStoreName(display, window, title.as_ptr());
+        }
+
+        let mut atom_delete_window =
+            xlib::XInternAtom(display, c"WM_DELETE_WINDOW".as_ptr(), xlib::False);
+        xlib::XSetWMProtocols(
+            display,
+            window,
+            std::ptr::addr_of_mut!(atom_delete_window),
+            1,
+        );
+
+        Ok(XlibWindow {
+            display,
+            window,
+            context,
+            atom_delete_window,
+        })
+    }
+}
+
+impl Drop for XlibWindow {
+    fn drop(&mut self) {
+        unsafe {
+            glx::glXMakeCurrent(self.display, 0, std::ptr::null_mut());
+            glx::glXDestroyContext(self.display, self.context);
+            xlib::XDestroyWindow(self.display, self.window);
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+// A GL context without a window, current on a 1x1 pbuffer: the engine renders into its own
+// offscreen render targets and never presents the default framebuffer.
+//
+// The GL backend of the engine loads its functions through GLX, so the context is a GLX context
+// and an X server is still required, e.g. `xvfb-run` on machines without a display. The other
+// backends do not need a display to run headless.
+pub(crate) struct HeadlessGLContext {
+    display: *mut xlib::Display,
+    pbuffer: glx::GLXPbuffer,
+    context: glx::GLXContext,
+}
+
+pub(crate) fn create_headless_gl_context() -> Result<HeadlessGLContext, std::io::Error> {
+    unsafe {
+        let display = xlib::XOpenDisplay(std::ptr::null());
+        if display.is_null() {
+            return Err(std::io::Error::other(
+                "Unable to open the X display, headless GL rendering requires an X server \
+                 (e.g. xvfb-run)",
+            ));
+        }
+
+        #[rustfmt::skip]
+        let fb_attribs = [
+            glx::GLX_RENDER_TYPE,   glx::GLX_RGBA_BIT,
+            glx::GLX_DRAWABLE_TYPE, glx::GLX_PBUFFER_BIT,
+            glx::GLX_RED_SIZE,   8,
+            glx::GLX_GREEN_SIZE, 8,
+            glx::GLX_BLUE_SIZE,  8,
+            glx::GLX_ALPHA_SIZE, 8,
+            0,
+        ];
+
+        let mut fb_count = 0;
+        let fb_configs = glx::glXChooseFBConfig(
+            display,
+            xlib::XDefaultScreen(display),
+            fb_attribs.as_ptr(),
+            std::ptr::addr_of_mut!(fb_count),
+        );
+        if fb_configs.is_null() || fb_count == 0 {
+            xlib::XCloseDisplay(display);
+            return Err(std::io::Error::other(
+                "Failed to retrieve a framebuffer config",
+            ));
+        }
+        let fb_config = *fb_configs;
+
+        #[rustfmt::skip]
+        let pbuffer_attribs = [
+            glx::GLX_PBUFFER_WIDTH,  1,
+            glx::GLX_PBUFFER_HEIGHT, 1,
+            0,
+        ];
+
+        let pbuffer = glx::glXCreatePbuffer(display, fb_config, pbuffer_attribs.as_ptr());
+
+        let context = create_context(display, fb_config);
+
+        xlib::XFree(fb_configs as *mut c_void);
+
+        let context = match context {
+            Ok(context) => context,
+            Err(error) => {
+                glx::glXDestroyPbuffer(display, pbuffer);
+                xlib::XCloseDisplay(display);
+                return Err(error);
+            }
+        };
+
+        if glx::glXMakeContextCurrent(display, pbuffer, pbuffer, context) == 0 {
+            glx::glXDestroyContext(display, context);
+            glx::glXDestroyPbuffer(display, pbuffer);
+            xlib::XCloseDisplay(display);
+            return Err(std::io::Error::other(
+                "Failed to make the GL context current",
+            ));
+        }
+
+        Ok(HeadlessGLContext {
+            display,
+            pbuffer,
+            context,
+        })
+    }
+}
+
+impl Drop for HeadlessGLContext {
+    fn drop(&mut self) {
+        unsafe {
+            glx::glXMakeContextCurrent(self.display, 0, 0, std::ptr::null_mut());
+            glx::glXDestroyContext(self.display, self.context);
+            glx::glXDestroyPbuffer(self.display, self.pbuffer);
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+unsafe fn create_invisible_cursor(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+) -> xlib::Cursor {
+    let data = [0 as c_char];
+    let pixmap = xlib::XCreateBitmapFromData(display, window, data.as_ptr(), 1, 1);
+
+    let mut color: xlib::XColor = std::mem::zeroed();
+    let cursor = xlib::XCreatePixmapCursor(
+        display,
+        pixmap,
+        pixmap,
+        std::ptr::addr_of_mut!(color),
+        std::ptr::addr_of_mut!(color),
+        0,
+        0,
+    );
+
+    xlib::XFreePixmap(display, pixmap);
+
+    cursor
+}
+
+// The event handler does not own the display: the GL context must outlive the
+// render device, which is only released once `App::run` has returned.
+struct XlibEventHandler {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    atom_delete_window: xlib::Atom,
+    pressed_keys: PressedKeys,
+    double_click_detector: DoubleClickDetector,
+
+    width: u16,
+    height: u16,
+
+    invisible_cursor: xlib::Cursor,
+    cursor_visible: bool,
+    cursor_grab: bool,
+    relative_mouse_mode: bool,
+    pointer_grabbed: bool,
+}
+
+impl XlibEventHandler {
+    fn new(
+        display: *mut xlib::Display,
+        window: xlib::Window,
+        atom_delete_window: xlib::Atom,
+        window_desc: &WindowDesc,
+    ) -> Self {
+        XlibEventHandler {
+            display,
+            window,
+            atom_delete_window,
+            pressed_keys: PressedKeys::new(),
+            double_click_detector: DoubleClickDetector::new(),
+            width: window_desc.width,
+            height: window_desc.height,
+            invisible_cursor: unsafe { create_invisible_cursor(display, window) },
+            cursor_visible: true,
+            cursor_grab: false,
+            relative_mouse_mode: false,
+            pointer_grabbed: false,
+        }
+    }
+
+    fn get_window_center(&self) -> (i16, i16) {
+        ((self.width / 2) as i16, (self.height / 2) as i16)
+    }
+
+    fn warp_pointer_to_center(&self) {
+        let (x, y) = self.get_window_center();
+        unsafe {
+            xlib::XWarpPointer(
+                self.display,
+                0,
+                self.window,
+                0,
+                0,
+                0,
+                0,
+                x as c_int,
+                y as c_int,
+            );
+        }
+    }
+
+    fn grab_pointer(&self) -> bool {
+        let result = unsafe {
+            xlib::XGrabPointer(
+                self.display,
+                self.window,
+                xlib::True,
+                (xlib::PointerMotionMask | xlib::ButtonPressMask | xlib::ButtonReleaseMask) as u32,
+                xlib::GrabModeAsync,
+                xlib::GrabModeAsync,
+                self.window,
+                0,
+                xlib::CurrentTime,
+            )
+        };
+        result == xlib::GrabSuccess
+    }
+
+    // The grab fails while the window is not viewable or another client holds the pointer,
+    // so it is retried when the pointer moves over the window
+    fn retry_pointer_grab(&mut self) {
+        if (self.cursor_grab || self.relative_mouse_mode) && !self.pointer_grabbed {
+            self.pointer_grabbed = self.grab_pointer();
+        }
+    }
+
+    // Relative mouse mode overrides the cursor visibility and grab requested by the application
+    fn update_cursor(&mut self) {
+        unsafe {
+            if self.cursor_visible && !self.relative_mouse_mode {
+                xlib::XUndefineCursor(self.display, self.window);
+            } else {
+                xlib::XDefineCursor(self.display, self.window, self.invisible_cursor);
+            }
+
+            if self.cursor_grab || self.relative_mouse_mode {
+                self.pointer_grabbed = self.grab_pointer();
+            } else {
+                xlib::XUngrabPointer(self.display, xlib::CurrentTime);
+                self.pointer_grabbed = false;
+            }
+
+            if self.relative_mouse_mode {
+                self.warp_pointer_to_center();
+            }
+
+            xlib::XFlush(self.display);
+        }
+    }
+
+    fn handle_key(&mut self, key_event: &mut xlib::XKeyEvent, pressed: bool) -> EventResult {
+        let keycode = key_event.keycode;
+        let modifiers = modifiers_from_state(key_event.state);
+
+        let key = key_from_keysym(unsafe {
+            xlib::XkbKeycodeToKeysym(self.display, keycode as u8, 0, 0) as u32
+        });
+
+        if pressed {
+            let repeat = self.pressed_keys.press(keycode);
+
+            // The keysym of the key with the current modifiers applied
+            let mut keysym: xlib::KeySym = 0;
+            unsafe {
+                xlib::XLookupString(
+                    key_event,
+                    std::ptr::null_mut(),
+                    0,
+                    std::ptr::addr_of_mut!(keysym),
+                    std::ptr::null_mut(),
+                );
+            }
+
+            EventResult::KeyPress {
+                key,
+                modifiers,
+                repeat,
+                text: text_input(
+                    xkb::keysym_to_utf8(xkb::Keysym::new(keysym as u32)),
+                    modifiers,
+                ),
+            }
+        } else {
+            self.pressed_keys.release(keycode);
+            EventResult::KeyRelease { key, modifiers }
+        }
+    }
+}
+
+impl Drop for XlibEventHandler {
+    fn drop(&mut self) {
+        unsafe {
+            xlib::XFreeCursor(self.display, self.invisible_cursor);
+        }
+    }
+}
+
+impl EventHandler for XlibEventHandler {
+    type EventType = xlib::XEvent;
+
+    fn poll_event(&self) -> Option<xlib::XEvent> {
+        unsafe {
+            if xlib::XPending(self.display) > 0 {
+                let mut event: xlib::XEvent = std::mem::zeroed();
+                xlib::XNextEvent(self.display, std::ptr::addr_of_mut!(event));
+                Some(event)
+            } else {
+                None
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: &xlib::XEvent) -> EventResult {
+        match event.get_type() {
+            xlib::ClientMessage => {
+                let message_event = xlib::XClientMessageEvent::from(*event);
+                if message_event.data.get_long(0) as xlib::Atom == self.atom_delete_window {
+                    EventResult::Quit
+                } else {
+                    EventResult::Continue
+                }
+            }
+
+            xlib::DestroyNotify => EventResult::Quit,
+
+            xlib::ConfigureNotify => {
+                let configure_event = xlib::XConfigureEvent::from(*event);
+                self.width = configure_event.width as u16;
+                self.height = configure_event.height as u16;
+                EventResult::Resize {
+                    width: configure_event.width as u16,
+                    height: configure_event.height as u16,
+                }
+            }
+
+            xlib::KeyPress => self.handle_key(&mut xlib::XKeyEvent::from(*event), true),
+
+            xlib::KeyRelease => self.handle_key(&mut xlib::XKeyEvent::from(*event), false),
+
+            xlib::MotionNotify => {
+                let motion_event = xlib::XMotionEvent::from(*event);
+                self.retry_pointer_grab();
+
+                if self.relative_mouse_mode {
+                    let (center_x, center_y) = self.get_window_center();
+                    let (x, y) = (motion_event.x as i16, motion_event.y as i16);
+
+                    // Warping the pointer back to the center generates a motion event as well
+                    if (x, y) == (center_x, center_y) {
+                        return EventResult::Continue;
+                    }
+
+                    self.warp_pointer_to_center();
+                    unsafe {
+                        xlib::XFlush(self.display);
+                    }
+
+                    EventResult::MouseMoveRelative {
+                        dx: x - center_x,
+                        dy: y - center_y,
+                    }
+                } else {
+                    EventResult::MouseMove {
+                        x: motion_event.x as i16,
+                        y: motion_event.y as i16,
+                    }
+                }
+            }
+
+            xlib::ButtonPress | xlib::ButtonRelease => {
+                let button_event = xlib::XButtonEvent::from(*event);
+                mouse::button_event(
+                    &mut self.double_click_detector,
+                    button_event.button,
+                    event.get_type() == xlib::ButtonPress,
+                    button_event.time as u32,
+                    button_event.x as i16,
+                    button_event.y as i16,
+                )
+            }
+
+            _ => EventResult::Continue,
+        }
+    }
+
+    fn set_title(&self, title: &str) {
+        // Titles with interior nul characters are ignored
+        if let Ok(title) = CString::new(title) {
+            unsafe {
+                xlib::XStoreName(self.display, self.window, title.as_ptr());
+                xlib::XFlush(self.display);
+            }
+        }
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+        self.update_cursor();
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) {
+        self.cursor_grab = grab;
+        self.update_cursor();
+    }
+
+    fn set_relative_mouse_mode(&mut self, enabled: bool) {
+        self.relative_mouse_mode = enabled;
+        self.update_cursor();
+    }
+}
+
+pub(super) fn main<Application>(
+    settings: &AppSettings,
+) -> Result<std::process::ExitCode, std::io::Error>
+where
+    Application: App,
+{
+    let window_desc = Application::get_window_desc(settings);
+
+    let window = init_display_and_window(&window_desc)?;
+
+    let native_window = bindings::NativeWindow {
+        WindowId: window.window as u32,
+        pDisplay: window.display as *mut c_void,
+        pXCBConnection: std::ptr::null_mut(),
+        pWaylandSurface: std::ptr::null_mut(),
+    };
+
+    let app = Application::new(settings, EngineCreateInfo::default(), Some(&native_window));
+
+    // `run` releases the application, and therefore the render device, and the event handler
+    // before returning: the GL context and the display can then safely be destroyed when
+    // `window` goes out of scope.
+    recording::run(
+        app,
+        settings,
+        XlibEventHandler::new(
+            window.display,
+            window.window,
+            window.atom_delete_window,
+            &window_desc,
+        ),
+    )
+}
@@ -1,15 +1,73 @@
-use super::app::App;
+use crate::core::graphics_types::RenderDeviceType;
+
+use super::app::{App, AppSettings, WindowSystem};
+
+mod keyboard;
+mod mouse;
 
 #[cfg(feature = "VULKAN_SUPPORTED")]
 mod linux_xcb;
 
-#[cfg(not(feature = "VULKAN_SUPPORTED"))]
+#[cfg(feature = "WAYLAND_SUPPORTED")]
+mod linux_wayland;
+
+#[cfg(feature = "OPENGL_SUPPORTED")]
 mod linux_xlib;
 
-#[cfg(feature = "VULKAN_SUPPORTED")]
-pub fn main<Application>() -> Result<(), std::io::Error>
+#[cfg(feature = "OPENGL_SUPPORTED")]
+pub(super) use linux_xlib::create_headless_gl_context;
+
+// Wayland is tried first in a Wayland session
+#[cfg(feature = "WAYLAND_SUPPORTED")]
+fn use_wayland(window_system: WindowSystem) -> bool {
+    match window_system {
+        WindowSystem::Auto => {
+            std::env::var_os("WAYLAND_DISPLAY").is_some()
+                || std::env::var_os("WAYLAND_SOCKET").is_some()
+        }
+        WindowSystem::X11 => false,
+        WindowSystem::Wayland => true,
+    }
+}
+
+pub fn main<Application>(settings: &AppSettings) -> Result<std::process::ExitCode, std::io::Error>
 where
     Application: App,
 {
-    linux_xcb::main::<Application>()
+    let wayland_supported = cfg!(feature = "WAYLAND_SUPPORTED")
+        && matches!(settings.device_type, RenderDeviceType::VULKAN);
+
+    if settings.window_system == WindowSystem::Wayland && !wayland_supported {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Wayland windows require the WAYLAND_SUPPORTED feature and the Vulkan backend",
+        ));
+    }
+
+    match settings.device_type {
+        #[cfg(feature = "VULKAN_SUPPORTED")]
+        RenderDeviceType::VULKAN => {
+            #[cfg(feature = "WAYLAND_SUPPORTED")]
+            if use_wayland(settings.window_system) {
+                let window_desc = Application::get_window_desc(settings);
+                match linux_wayland::WaylandEventHandler::new(&window_desc) {
+                    Ok(event_handler) => {
+                        return linux_wayland::main::<Application>(settings, event_handler)
+                    }
+                    Err(error) if settings.window_system == WindowSystem::Auto => {
+                        println!("Failed to create a Wayland window ({error}), falling back to XCB")
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+
+            linux_xcb::main::<Application>(settings)
+        }
+        #[cfg(feature = "OPENGL_SUPPORTED")]
+        RenderDeviceType::GL => linux_xlib::main::<Application>(settings),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "The requested device type is not supported on this platform",
+        )),
+    }
 }
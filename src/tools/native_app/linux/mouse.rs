@@ -0,0 +1,268 @@
+use crate::tools::native_app::events::{DoubleClickDetector, EventResult, MouseButton};
+
+// Translates the buttons of core X button events. The wheels are reported as buttons 4 to 7,
+// with one press and release per notch.
+pub(super) fn button_event(
+    double_click_detector: &mut DoubleClickDetector,
+    button: u32,
+    pressed: bool,
+    time: u32,
+    x: i16,
+    y: i16,
+) -> EventResult {
+    let button = match button {
+        1 => MouseButton::Left,
+        2 => MouseButton::Middle,
+        3 => MouseButton::Right,
+        8 => MouseButton::Back,
+        9 => MouseButton::Forward,
+
+        4..=7 if !pressed => return EventResult::Continue,
+        4 => {
+            return EventResult::MouseWheel {
+                delta_x: 0.0,
+                delta_y: 1.0,
+            }
+        }
+        5 => {
+            return EventResult::MouseWheel {
+                delta_x: 0.0,
+                delta_y: -1.0,
+            }
+        }
+        6 => {
+            return EventResult::MouseWheel {
+                delta_x: -1.0,
+                delta_y: 0.0,
+            }
+        }
+        7 => {
+            return EventResult::MouseWheel {
+                delta_x: 1.0,
+                delta_y: 0.0,
+            }
+        }
+
+        _ => return EventResult::Continue,
+    };
+
+    if pressed {
+        EventResult::MouseDown {
+            button,
+            double_click: double_click_detector.press(button, time, x, y),
+        }
+    } else {
+        EventResult::MouseUp { button }
+    }
+}
+
+// Non-button events are ignored
+#[cfg(feature = "VULKAN_SUPPORTED")]
+pub(super) fn xcb_button_event(
+    double_click_detector: &mut DoubleClickDetector,
+    event: &xcb::Event,
+) -> EventResult {
+    let (button_event_data, pressed) = match event {
+        xcb::Event::X(xcb::x::Event::ButtonPress(press_event)) => (press_event, true),
+        xcb::Event::X(xcb::x::Event::ButtonRelease(release_event)) => (release_event, false),
+        _ => return EventResult::Continue,
+    };
+
+    button_event(
+        double_click_detector,
+        button_event_data.detail() as u32,
+        pressed,
+        button_event_data.time(),
+        button_event_data.event_x(),
+        button_event_data.event_y(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(button: u32) -> EventResult {
+        button_event(&mut DoubleClickDetector::new(), button, true, 0, 0, 0)
+    }
+
+    fn release(button: u32) -> EventResult {
+        button_event(&mut DoubleClickDetector::new(), button, false, 0, 0, 0)
+    }
+
+    #[test]
+    fn buttons() {
+        for (button, expected) in [
+            (1, MouseButton::Left),
+            (2, MouseButton::Middle),
+            (3, MouseButton::Right),
+            (8, MouseButton::Back),
+            (9, MouseButton::Forward),
+        ] {
+            assert!(matches!(
+                press(button),
+                EventResult::MouseDown { button, double_click: false } if button == expected
+            ));
+            assert!(matches!(
+                release(button),
+                EventResult::MouseUp { button } if button == expected
+            ));
+        }
+    }
+
+    #[test]
+    fn wheels() {
+        for (button, expected_x, expected_y) in
+            [(4, 0.0, 1.0), (5, 0.0, -1.0), (6, -1.0, 0.0), (7, 1.0, 0.0)]
+        {
+            assert!(matches!(
+                press(button),
+                EventResult::MouseWheel { delta_x, delta_y }
+                    if delta_x == expected_x && delta_y == expected_y
+            ));
+            assert!(matches!(release(button), EventResult::Continue));
+        }
+    }
+
+    #[test]
+    fn unknown_buttons_are_ignored() {
+        for button in [0, 10, 255] {
+            assert!(matches!(press(button), EventResult::Continue));
+            assert!(matches!(release(button), EventResult::Continue));
+        }
+    }
+
+    #[test]
+    fn double_click() {
+        let mut double_click_detector = DoubleClickDetector::new();
+
+        assert!(matches!(
+            button_event(&mut double_click_detector, 1, true, 100, 10, 10),
+            EventResult::MouseDown {
+                double_click: false,
+                ..
+            }
+        ));
+        // Releases do not reset the detector
+        button_event(&mut double_click_detector, 1, false, 150, 10, 10);
+        assert!(matches!(
+            button_event(&mut double_click_detector, 1, true, 200, 10, 10),
+            EventResult::MouseDown {
+                double_click: true,
+                ..
+            }
+        ));
+    }
+
+    #[cfg(feature = "VULKAN_SUPPORTED")]
+    mod xcb_events {
+        use super::*;
+
+        use xcb::x;
+
+        fn button_press(button: x::Button, time: x::Timestamp, x: i16, y: i16) -> xcb::Event {
+            xcb::Event::X(x::Event::ButtonPress(x::ButtonPressEvent::new(
+                button,
+                time,
+                x::WINDOW_NONE,
+                x::WINDOW_NONE,
+                x::WINDOW_NONE,
+                x,
+                y,
+                x,
+                y,
+                x::KeyButMask::empty(),
+                true,
+            )))
+        }
+
+        fn button_release(button: x::Button, time: x::Timestamp, x: i16, y: i16) -> xcb::Event {
+            xcb::Event::X(x::Event::ButtonRelease(x::ButtonReleaseEvent::new(
+                button,
+                time,
+                x::WINDOW_NONE,
+                x::WINDOW_NONE,
+                x::WINDOW_NONE,
+                x,
+                y,
+                x,
+                y,
+                x::KeyButMask::BUTTON1,
+                true,
+            )))
+        }
+
+        #[test]
+        fn presses_and_releases() {
+            let mut double_click_detector = DoubleClickDetector::new();
+
+            assert!(matches!(
+                xcb_button_event(&mut double_click_detector, &button_press(3, 100, 5, 5)),
+                EventResult::MouseDown {
+                    button: MouseButton::Right,
+                    double_click: false
+                }
+            ));
+            assert!(matches!(
+                xcb_button_event(&mut double_click_detector, &button_release(3, 150, 5, 5)),
+                EventResult::MouseUp {
+                    button: MouseButton::Right
+                }
+            ));
+        }
+
+        #[test]
+        fn wheel_notches() {
+            let mut double_click_detector = DoubleClickDetector::new();
+
+            assert!(matches!(
+                xcb_button_event(&mut double_click_detector, &button_press(5, 100, 0, 0)),
+                EventResult::MouseWheel { delta_x, delta_y } if delta_x == 0.0 && delta_y == -1.0
+            ));
+            assert!(matches!(
+                xcb_button_event(&mut double_click_detector, &button_release(5, 100, 0, 0)),
+                EventResult::Continue
+            ));
+        }
+
+        // The time and position of the events are given to the detector
+        #[test]
+        fn double_clicks() {
+            let mut double_click_detector = DoubleClickDetector::new();
+
+            let mut press = |time, x| match xcb_button_event(
+                &mut double_click_detector,
+                &button_press(1, time, x, 10),
+            ) {
+                EventResult::MouseDown { double_click, .. } => double_click,
+                _ => panic!("Expected a mouse down event"),
+            };
+
+            assert!(!press(1000, 10));
+            assert!(press(1100, 11));
+            assert!(!press(5000, 11));
+            assert!(!press(5100, 200));
+        }
+
+        #[test]
+        fn other_events_are_ignored() {
+            let event = xcb::Event::X(x::Event::MotionNotify(x::MotionNotifyEvent::new(
+                x::Motion::Normal,
+                0,
+                x::WINDOW_NONE,
+                x::WINDOW_NONE,
+                x::WINDOW_NONE,
+                0,
+                0,
+                0,
+                0,
+                x::KeyButMask::empty(),
+                true,
+            )));
+            assert!(matches!(
+                xcb_button_event(&mut DoubleClickDetector::new(), &event),
+                EventResult::Continue
+            ));
+        }
+    }
+}
@@ -1,15 +1,35 @@
-use app::App;
+use app::{App, AppSettings};
 pub mod app;
 
 pub mod events;
 
+pub mod headless;
+
+pub mod recording;
+
 #[cfg(target_os = "linux")]
 mod linux;
 
 #[cfg(target_os = "linux")]
-pub fn main<Application>() -> Result<(), std::io::Error>
+pub fn main<Application>() -> Result<std::process::ExitCode, std::io::Error>
 where
     Application: App,
 {
-    linux::main::<Application>()
+    let mut settings = AppSettings::from_command_line()?;
+
+    // Replays are deterministic: the recorded events are delivered at the same frames, in a
+    // window of the recorded size, and the frames advance by the recorded time step or times
+    if let Some(path) = &settings.replay_path {
+        let session_desc = recording::read_session(path)?;
+        settings.headless = true;
+        settings.width = session_desc.width;
+        settings.height = session_desc.height;
+        settings.fixed_time_step = session_desc.fixed_time_step;
+    }
+
+    if settings.headless {
+        headless::main::<Application>(&settings)
+    } else {
+        linux::main::<Application>(&settings)
+    }
 }
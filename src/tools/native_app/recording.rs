@@ -0,0 +1,511 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    process::ExitCode,
+};
+
+use crate::tools::timer::Timer;
+
+use super::{
+    app::{App, AppSettings},
+    events::{EventHandler, EventResult, Key, KeyModifiers, MouseButton},
+};
+
+// Event files are text files. The first line describes the session:
+//   Session <width> <height> <fixed time step in seconds, 0 if none>
+// and the next ones contain one event per line:
+//   <frame> <seconds> <event> <fields...>
+// Events are replayed at the frame they were recorded at. Without a fixed time step, the end
+// of every frame is recorded as a `Frame` event, whose time is the time of the frame: the
+// replayed frames then advance by the recorded frame times.
+
+const SESSION_HEADER: &str = "Session";
+const END_OF_FRAME: &str = "Frame";
+
+pub struct SessionDesc {
+    pub width: u16,
+    pub height: u16,
+    pub fixed_time_step: Option<f64>,
+}
+
+fn format_session_desc(session_desc: &SessionDesc) -> String {
+    format!(
+        "{SESSION_HEADER} {} {} {}",
+        session_desc.width,
+        session_desc.height,
+        session_desc.fixed_time_step.unwrap_or(0.0)
+    )
+}
+
+fn parse_session_desc(line: &str) -> Option<SessionDesc> {
+    let mut fields = line.split(' ');
+    if fields.next()? != SESSION_HEADER {
+        return None;
+    }
+
+    let width = next_field(&mut fields)?;
+    let height = next_field(&mut fields)?;
+    let fixed_time_step: f64 = next_field(&mut fields)?;
+
+    Some(SessionDesc {
+        width,
+        height,
+        fixed_time_step: (fixed_time_step > 0.0).then_some(fixed_time_step),
+    })
+}
+
+fn mouse_button_name(button: MouseButton) -> &'static str {
+    match button {
+        MouseButton::Left => "Left",
+        MouseButton::Right => "Right",
+        MouseButton::Middle => "Middle",
+        MouseButton::Back => "Back",
+        MouseButton::Forward => "Forward",
+    }
+}
+
+fn parse_mouse_button(name: &str) -> Option<MouseButton> {
+    match name {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        "Back" => Some(MouseButton::Back),
+        "Forward" => Some(MouseButton::Forward),
+        _ => None,
+    }
+}
+
+// Keys are written as their `Debug` name and modifiers as their bits. The text of a key press is the
+// rest of the line.
+fn format_event(event: &EventResult) -> Option<String> {
+    match event {
+        EventResult::MouseMove { x, y } => Some(format!("MouseMove {x} {y}")),
+        EventResult::MouseMoveRelative { dx, dy } => Some(format!("MouseMoveRelative {dx} {dy}")),
+        EventResult::MouseDown {
+            button,
+            double_click,
+        } => Some(format!(
+            "MouseDown {} {}",
+            mouse_button_name(*button),
+            *double_click as u8
+        )),
+        EventResult::MouseUp { button } => Some(format!("MouseUp {}", mouse_button_name(*button))),
+        EventResult::MouseWheel { delta_x, delta_y } => {
+            Some(format!("MouseWheel {delta_x} {delta_y}"))
+        }
+        EventResult::KeyPress {
+            key,
+            modifiers,
+            repeat,
+            text,
+        } => {
+            let mut line = format!("KeyPress {key:?} {} {}", modifiers.bits(), *repeat as u8);
+            if let Some(text) = text {
+                line.push(' ');
+                line.push_str(text);
+            }
+            Some(line)
+        }
+        EventResult::KeyRelease { key, modifiers } => {
+            Some(format!("KeyRelease {key:?} {}", modifiers.bits()))
+        }
+        EventResult::Resize { width, height } => Some(format!("Resize {width} {height}")),
+        EventResult::Quit => Some("Quit".to_string()),
+        EventResult::Continue => None,
+    }
+}
+
+fn next_field<'a, T, I>(fields: &mut I) -> Option<T>
+where
+    T: std::str::FromStr,
+    I: Iterator<Item = &'a str>,
+{
+    fields.next()?.parse().ok()
+}
+
+enum RecordedEvent {
+    Event(EventResult),
+    // The time of the frame, in seconds
+    EndOfFrame(f64),
+}
+
+// Returns the frame of the event and the event
+fn parse_event(line: &str) -> Option<(u32, RecordedEvent)> {
+    // The text of a key press, which may contain spaces, is the seventh field
+    let mut fields = line.splitn(7, ' ');
+
+    let frame = next_field(&mut fields)?;
+    let time: f64 = next_field(&mut fields)?;
+
+    let event = match fields.next()? {
+        END_OF_FRAME => return Some((frame, RecordedEvent::EndOfFrame(time))),
+        "MouseMove" => EventResult::MouseMove {
+            x: next_field(&mut fields)?,
+            y: next_field(&mut fields)?,
+        },
+        "MouseMoveRelative" => EventResult::MouseMoveRelative {
+            dx: next_field(&mut fields)?,
+            dy: next_field(&mut fields)?,
+        },
+        "MouseDown" => EventResult::MouseDown {
+            button: parse_mouse_button(fields.next()?)?,
+            double_click: next_field::<u8, _>(&mut fields)? != 0,
+        },
+        "MouseUp" => EventResult::MouseUp {
+            button: parse_mouse_button(fields.next()?)?,
+        },
+        "MouseWheel" => EventResult::MouseWheel {
+            delta_x: next_field(&mut fields)?,
+            delta_y: next_field(&mut fields)?,
+        },
+        "KeyPress" => {
+            let key = Key::from_name(fields.next()?)?;
+            let modifiers = KeyModifiers::from_bits(next_field(&mut fields)?)?;
+            let repeat = next_field::<u8, _>(&mut fields)? != 0;
+
+            EventResult::KeyPress {
+                key,
+                modifiers,
+                repeat,
+                text: fields.next().map(str::to_string),
+            }
+        }
+        "KeyRelease" => EventResult::KeyRelease {
+            key: Key::from_name(fields.next()?)?,
+            modifiers: KeyModifiers::from_bits(next_field(&mut fields)?)?,
+        },
+        "Resize" => EventResult::Resize {
+            width: next_field(&mut fields)?,
+            height: next_field(&mut fields)?,
+        },
+        "Quit" => EventResult::Quit,
+        _ => return None,
+    };
+
+    Some((frame, RecordedEvent::Event(event)))
+}
+
+// Writes the events of the wrapped event handler to a file, to be replayed by
+// `ReplayEventHandler`
+pub struct RecordingEventHandler<EH: EventHandler> {
+    event_handler: EH,
+    writer: RefCell<BufWriter<File>>,
+    timer: Timer,
+    frame: Cell<u32>,
+    // The frame times are only recorded without a fixed time step
+    record_frame_times: bool,
+}
+
+impl<EH: EventHandler> RecordingEventHandler<EH> {
+    pub fn new(
+        event_handler: EH,
+        path: &Path,
+        session_desc: &SessionDesc,
+    ) -> Result<Self, std::io::Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", format_session_desc(session_desc))?;
+
+        Ok(RecordingEventHandler {
+            event_handler,
+            writer: RefCell::new(writer),
+            timer: Timer::new(),
+            frame: Cell::new(0),
+            record_frame_times: session_desc.fixed_time_step.is_none(),
+        })
+    }
+
+    fn write(&self, line: &str) {
+        if let Err(error) = writeln!(
+            self.writer.borrow_mut(),
+            "{} {:.6} {line}",
+            self.frame.get(),
+            self.timer.get_elapsed_time()
+        ) {
+            println!("Failed to record event: {error}");
+        }
+    }
+}
+
+impl<EH: EventHandler> EventHandler for RecordingEventHandler<EH> {
+    type EventType = EH::EventType;
+
+    // The event queue is drained once per frame
+    fn poll_event(&self) -> Option<EH::EventType> {
+        let event = self.event_handler.poll_event();
+        if event.is_none() {
+            if self.record_frame_times {
+                self.write(END_OF_FRAME);
+            }
+            self.frame.set(self.frame.get() + 1);
+        }
+        event
+    }
+
+    fn handle_event(&mut self, event: &EH::EventType) -> EventResult {
+        let result = self.event_handler.handle_event(event);
+
+        if let Some(line) = format_event(&result) {
+            self.write(&line);
+        }
+
+        result
+    }
+
+    fn set_title(&self, title: &str) {
+        self.event_handler.set_title(title)
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.event_handler.set_cursor_visible(visible)
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) {
+        self.event_handler.set_cursor_grab(grab)
+    }
+
+    fn set_relative_mouse_mode(&mut self, enabled: bool) {
+        self.event_handler.set_relative_mouse_mode(enabled)
+    }
+
+    fn get_frame_time(&self) -> Option<f64> {
+        self.event_handler.get_frame_time()
+    }
+}
+
+// Replays the events recorded by `RecordingEventHandler`, frame by frame. The application
+// quits after the last recorded event.
+pub struct ReplayEventHandler {
+    events: RefCell<VecDeque<(u32, EventResult)>>,
+    frame_times: Vec<f64>,
+    frame: Cell<u32>,
+}
+
+fn invalid_data(path: &Path, line_index: usize, message: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("{}:{}: {message}", path.display(), line_index + 1),
+    )
+}
+
+fn read_session_desc(
+    path: &Path,
+    lines: &mut impl Iterator<Item = Result<String, std::io::Error>>,
+) -> Result<SessionDesc, std::io::Error> {
+    let header = lines.next().transpose()?.unwrap_or_default();
+    parse_session_desc(&header)
+        .ok_or_else(|| invalid_data(path, 0, &format!("invalid session '{header}'")))
+}
+
+// The application replaying a session should be created with its window size and time step
+pub fn read_session(path: &Path) -> Result<SessionDesc, std::io::Error> {
+    read_session_desc(path, &mut BufReader::new(File::open(path)?).lines())
+}
+
+impl ReplayEventHandler {
+    pub fn new(path: &Path) -> Result<Self, std::io::Error> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut lines = reader.lines();
+
+        read_session_desc(path, &mut lines)?;
+
+        let mut events = VecDeque::new();
+        let mut frame_times = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+
+            match parse_event(&line)
+                .ok_or_else(|| invalid_data(path, index + 1, &format!("invalid event '{line}'")))?
+            {
+                (frame, RecordedEvent::Event(event)) => events.push_back((frame, event)),
+                (frame, RecordedEvent::EndOfFrame(time)) => {
+                    frame_times.resize(frame as usize + 1, time);
+                    frame_times[frame as usize] = time;
+                }
+            }
+        }
+
+        Ok(ReplayEventHandler {
+            events: RefCell::new(events),
+            frame_times,
+            frame: Cell::new(0),
+        })
+    }
+}
+
+impl EventHandler for ReplayEventHandler {
+    type EventType = EventResult;
+
+    fn poll_event(&self) -> Option<EventResult> {
+        let mut events = self.events.borrow_mut();
+        match events.front() {
+            Some((frame, _)) if *frame <= self.frame.get() => {
+                events.pop_front().map(|(_, event)| event)
+            }
+            Some(_) => {
+                self.frame.set(self.frame.get() + 1);
+                None
+            }
+            None => Some(EventResult::Quit),
+        }
+    }
+
+    fn handle_event(&mut self, event: &EventResult) -> EventResult {
+        event.clone()
+    }
+
+    // The events of a frame have been polled when this is called: the current frame is the
+    // previous one
+    fn get_frame_time(&self) -> Option<f64> {
+        let frame = self.frame.get().checked_sub(1)?;
+        self.frame_times.get(frame as usize).copied()
+    }
+}
+
+// Runs the application, recording its events if `settings.record_path` is set
+pub fn run<Application, EH>(
+    app: Application,
+    settings: &AppSettings,
+    event_handler: EH,
+) -> Result<ExitCode, std::io::Error>
+where
+    Application: App,
+    EH: EventHandler,
+{
+    match &settings.record_path {
+        Some(path) => {
+            let session_desc = SessionDesc {
+                width: settings.width,
+                height: settings.height,
+                fixed_time_step: settings.fixed_time_step,
+            };
+            app.run(RecordingEventHandler::new(
+                event_handler,
+                path,
+                &session_desc,
+            )?)
+        }
+        None => app.run(event_handler),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(event: EventResult) {
+        let line = format!("3 0.5 {}", format_event(&event).unwrap());
+
+        let Some((frame, RecordedEvent::Event(parsed))) = parse_event(&line) else {
+            panic!("'{line}' was not parsed as an event");
+        };
+
+        assert_eq!(frame, 3);
+        assert_eq!(format_event(&parsed), format_event(&event));
+    }
+
+    #[test]
+    fn events_round_trip() {
+        round_trip(EventResult::MouseMove { x: 10, y: -20 });
+        round_trip(EventResult::MouseMoveRelative { dx: -3, dy: 4 });
+        for button in [MouseButton::Left, MouseButton::Middle, MouseButton::Right] {
+            round_trip(EventResult::MouseDown {
+                button,
+                double_click: true,
+            });
+            round_trip(EventResult::MouseUp { button });
+        }
+        round_trip(EventResult::MouseWheel {
+            delta_x: 0.0,
+            delta_y: -1.5,
+        });
+        round_trip(EventResult::KeyPress {
+            key: Key::A,
+            modifiers: KeyModifiers::Shift | KeyModifiers::Control,
+            repeat: true,
+            text: None,
+        });
+        round_trip(EventResult::KeyPress {
+            key: Key::Space,
+            modifiers: KeyModifiers::None,
+            repeat: false,
+            text: Some("a b ".to_string()),
+        });
+        round_trip(EventResult::KeyRelease {
+            key: Key::Escape,
+            modifiers: KeyModifiers::Alt,
+        });
+        round_trip(EventResult::Resize {
+            width: 640,
+            height: 480,
+        });
+        round_trip(EventResult::Quit);
+    }
+
+    #[test]
+    fn continue_is_not_recorded() {
+        assert!(format_event(&EventResult::Continue).is_none());
+    }
+
+    #[test]
+    fn end_of_frame_is_parsed() {
+        assert!(matches!(
+            parse_event("7 0.25 Frame"),
+            Some((7, RecordedEvent::EndOfFrame(time))) if time == 0.25
+        ));
+    }
+
+    #[test]
+    fn invalid_events_are_rejected() {
+        for line in [
+            "",
+            "0 0.0",
+            "0 0.0 Unknown",
+            "x 0.0 Quit",
+            "0 0.0 MouseMove 1",
+            "0 0.0 MouseDown Fourth 0",
+            "0 0.0 KeyPress NotAKey 0 0",
+            "0 0.0 KeyPress 0 0 0",
+            "0 0.0 KeyRelease A 255",
+        ] {
+            assert!(parse_event(line).is_none(), "'{line}' was parsed");
+        }
+    }
+
+    #[test]
+    fn keys_are_parsed_from_their_names() {
+        for key in [
+            Key::A,
+            Key::Z,
+            Key::Num0,
+            Key::F12,
+            Key::Escape,
+            Key::Unknown,
+        ] {
+            assert_eq!(Key::from_name(&format!("{key:?}")), Some(key));
+        }
+        assert_eq!(Key::from_name("a"), None);
+    }
+
+    #[test]
+    fn session_round_trip() {
+        for fixed_time_step in [None, Some(1.0 / 30.0)] {
+            let session_desc = SessionDesc {
+                width: 800,
+                height: 600,
+                fixed_time_step,
+            };
+            let parsed = parse_session_desc(&format_session_desc(&session_desc)).unwrap();
+
+            assert_eq!(parsed.width, 800);
+            assert_eq!(parsed.height, 600);
+            assert_eq!(parsed.fixed_time_step, fixed_time_step);
+        }
+        assert!(parse_session_desc("0 0.0 Quit").is_none());
+    }
+}
@@ -0,0 +1,312 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Condvar, Mutex,
+};
+
+use crate::core::thread_pool::{AsyncTask, AsyncTaskStatus, ThreadPool};
+
+#[derive(Default)]
+struct TaskCounters {
+    // The tasks that can be run. A job is spawned on rayon for each of them, which runs the task
+    // of the queue with the highest priority when it starts.
+    queue: Mutex<Vec<AsyncTask>>,
+    // The enqueued tasks that are not finished, including the running ones
+    pending: Mutex<u32>,
+    all_finished: Condvar,
+    running: AtomicU32,
+    // The tasks whose prerequisites are not finished. They are spawned again when another task
+    // finishes.
+    waiting: Mutex<Vec<AsyncTask>>,
+}
+
+// Runs the tasks of the engine on a rayon thread pool. Rayon runs its jobs in no particular
+// order, the priority of the tasks is only taken into account when a job starts: a running task
+// is never interrupted by a task with a higher priority.
+pub struct RayonThreadPool {
+    // The global thread pool of rayon when None
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    counters: Arc<TaskCounters>,
+}
+
+impl RayonThreadPool {
+    pub fn new() -> Self {
+        RayonThreadPool {
+            thread_pool: None,
+            counters: Arc::new(TaskCounters::default()),
+        }
+    }
+
+    pub fn from_thread_pool(thread_pool: Arc<rayon::ThreadPool>) -> Self {
+        RayonThreadPool {
+            thread_pool: Some(thread_pool),
+            counters: Arc::new(TaskCounters::default()),
+        }
+    }
+}
+
+impl Default for RayonThreadPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn spawn(
+    thread_pool: &Option<Arc<rayon::ThreadPool>>,
+    counters: &Arc<TaskCounters>,
+    task: AsyncTask,
+) {
+    counters.queue.lock().unwrap().push(task);
+
+    let job = {
+        let thread_pool = thread_pool.clone();
+        let counters = counters.clone();
+        move || run_next(&thread_pool, &counters)
+    };
+
+    match thread_pool {
+        Some(thread_pool) => thread_pool.spawn(job),
+        None => rayon::spawn(job),
+    }
+}
+
+// The priorities are read when the task is taken from the queue, so reprioritized tasks do not
+// have to be moved
+fn take_highest_priority_task(queue: &mut Vec<AsyncTask>) -> Option<AsyncTask> {
+    let index = queue
+        .iter()
+        .enumerate()
+        .max_by(|(_, task0), (_, task1)| task0.get_priority().total_cmp(&task1.get_priority()))
+        .map(|(index, _)| index)?;
+    Some(queue.swap_remove(index))
+}
+
+fn finish_task(counters: &TaskCounters) {
+    let mut pending = counters.pending.lock().unwrap();
+    *pending -= 1;
+    if *pending == 0 {
+        counters.all_finished.notify_all();
+    }
+}
+
+fn run_next(thread_pool: &Option<Arc<rayon::ThreadPool>>, counters: &Arc<TaskCounters>) {
+    // The queue is empty when the task of the job has been removed
+    let Some(task) = take_highest_priority_task(&mut counters.queue.lock().unwrap()) else {
+        return;
+    };
+
+    let thread_id = rayon::current_thread_index().unwrap_or(0) as u32;
+
+    counters.running.fetch_add(1, Ordering::Relaxed);
+    let status = task.run(thread_id);
+    counters.running.fetch_sub(1, Ordering::Relaxed);
+
+    if status == AsyncTaskStatus::NotStarted {
+        // The prerequisites are checked again with the lock held, so that a prerequisite
+        // finishing in the meantime does not leave the task waiting forever
+        let mut waiting = counters.waiting.lock().unwrap();
+        if task.are_prerequisites_finished() {
+            // The task yielded
+            drop(waiting);
+            spawn(thread_pool, counters, task);
+        } else {
+            waiting.push(task);
+        }
+        return;
+    }
+
+    drop(task);
+
+    for task in counters.waiting.lock().unwrap().drain(..) {
+        spawn(thread_pool, counters, task);
+    }
+
+    finish_task(counters);
+}
+
+fn remove(tasks: &Mutex<Vec<AsyncTask>>, task: &AsyncTask) -> Option<AsyncTask> {
+    let mut tasks = tasks.lock().unwrap();
+    let index = tasks.iter().position(|queued_task| queued_task == task)?;
+    Some(tasks.swap_remove(index))
+}
+
+impl ThreadPool for RayonThreadPool {
+    fn enqueue_task(&self, task: AsyncTask) {
+        *self.counters.pending.lock().unwrap() += 1;
+        spawn(&self.thread_pool, &self.counters, task);
+    }
+
+    fn reprioritize_task(&self, task: &AsyncTask) -> bool {
+        let is_queued = |tasks: &Mutex<Vec<AsyncTask>>| tasks.lock().unwrap().contains(task);
+        is_queued(&self.counters.queue) || is_queued(&self.counters.waiting)
+    }
+
+    fn remove_task(&self, task: &AsyncTask) -> bool {
+        let removed = remove(&self.counters.queue, task)
+            .or_else(|| remove(&self.counters.waiting, task))
+            .is_some();
+
+        if removed {
+            finish_task(&self.counters);
+        }
+        removed
+    }
+
+    // Must not be called from a thread of the pool
+    fn wait_for_all_tasks(&self) {
+        let pending = self.counters.pending.lock().unwrap();
+        let _pending = self
+            .counters
+            .all_finished
+            .wait_while(pending, |pending| *pending > 0)
+            .unwrap();
+    }
+
+    fn get_queue_size(&self) -> u32 {
+        let pending = *self.counters.pending.lock().unwrap();
+        pending.saturating_sub(self.counters.running.load(Ordering::Relaxed))
+    }
+
+    fn get_running_task_count(&self) -> u32 {
+        self.counters.running.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::core::thread_pool::tests::TestTask;
+
+    fn single_thread_pool() -> RayonThreadPool {
+        RayonThreadPool::from_thread_pool(Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        ))
+    }
+
+    // Occupies the thread of the pool until the returned sender is dropped
+    fn enqueue_blocking_task(thread_pool: &RayonThreadPool) -> (TestTask, mpsc::Sender<()>) {
+        let (sender, receiver) = mpsc::channel::<()>();
+        let receiver = Mutex::new(receiver);
+        let task = TestTask::new(move |_| {
+            let _ = receiver.lock().unwrap().recv();
+            AsyncTaskStatus::Complete
+        });
+        task.set_priority(f32::MAX);
+        thread_pool.enqueue_task(task.async_task(&[]));
+        (task, sender)
+    }
+
+    // The task records its name in the log when it runs
+    fn logging_task(log: &Arc<Mutex<Vec<&'static str>>>, name: &'static str) -> TestTask {
+        let log = log.clone();
+        TestTask::new(move |_| {
+            log.lock().unwrap().push(name);
+            AsyncTaskStatus::Complete
+        })
+    }
+
+    #[test]
+    fn prerequisites_are_run_first() {
+        let thread_pool = RayonThreadPool::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let prerequisite = logging_task(&log, "prerequisite");
+        let task = logging_task(&log, "task");
+        let (blocking_task, sender) = enqueue_blocking_task(&thread_pool);
+
+        // The task waits for the prerequisite, which waits for the blocking task
+        thread_pool.enqueue_task(task.async_task(&[&prerequisite]));
+        thread_pool.enqueue_task(prerequisite.async_task(&[&blocking_task]));
+        drop(sender);
+
+        thread_pool.wait_for_all_tasks();
+
+        assert_eq!(*log.lock().unwrap(), ["prerequisite", "task"]);
+        assert_eq!(task.status(), AsyncTaskStatus::Complete);
+        assert_eq!(task.run_count(), 1);
+        assert_eq!(thread_pool.get_queue_size(), 0);
+    }
+
+    #[test]
+    fn yielding_tasks_are_requeued() {
+        let thread_pool = single_thread_pool();
+
+        let task = TestTask::new(|run_count| match run_count {
+            0..=2 => AsyncTaskStatus::NotStarted,
+            _ => AsyncTaskStatus::Complete,
+        });
+        thread_pool.enqueue_task(task.async_task(&[]));
+        thread_pool.wait_for_all_tasks();
+
+        assert_eq!(task.run_count(), 4);
+        assert_eq!(task.status(), AsyncTaskStatus::Complete);
+        assert_eq!(task.reference_count(), 1);
+    }
+
+    #[test]
+    fn wait_for_all_tasks() {
+        let thread_pool = RayonThreadPool::new();
+
+        let tasks: Vec<TestTask> = (0..32).map(|_| TestTask::complete()).collect();
+        for task in &tasks {
+            thread_pool.enqueue_task(task.async_task(&[]));
+        }
+        thread_pool.wait_for_all_tasks();
+
+        for task in &tasks {
+            assert_eq!(task.status(), AsyncTaskStatus::Complete);
+            assert_eq!(task.reference_count(), 1);
+        }
+        assert_eq!(thread_pool.get_queue_size(), 0);
+        assert_eq!(thread_pool.get_running_task_count(), 0);
+    }
+
+    #[test]
+    fn higher_priorities_first() {
+        let thread_pool = single_thread_pool();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let (_blocking_task, sender) = enqueue_blocking_task(&thread_pool);
+
+        let low = logging_task(&log, "low");
+        let high = logging_task(&log, "high");
+        let reprioritized = logging_task(&log, "reprioritized");
+        high.set_priority(1.0);
+
+        thread_pool.enqueue_task(low.async_task(&[]));
+        thread_pool.enqueue_task(reprioritized.async_task(&[]));
+        thread_pool.enqueue_task(high.async_task(&[]));
+
+        reprioritized.set_priority(2.0);
+        assert!(thread_pool.reprioritize_task(&reprioritized.async_task(&[])));
+
+        drop(sender);
+        thread_pool.wait_for_all_tasks();
+
+        assert_eq!(*log.lock().unwrap(), ["reprioritized", "high", "low"]);
+        assert!(!thread_pool.reprioritize_task(&low.async_task(&[])));
+    }
+
+    #[test]
+    fn removed_tasks_are_not_run() {
+        let thread_pool = single_thread_pool();
+
+        let (_blocking_task, sender) = enqueue_blocking_task(&thread_pool);
+
+        let task = TestTask::complete();
+        thread_pool.enqueue_task(task.async_task(&[]));
+
+        assert!(thread_pool.remove_task(&task.async_task(&[])));
+        assert!(!thread_pool.remove_task(&task.async_task(&[])));
+
+        drop(sender);
+        thread_pool.wait_for_all_tasks();
+
+        assert_eq!(task.run_count(), 0);
+        assert_eq!(task.reference_count(), 1);
+    }
+}
@@ -0,0 +1,888 @@
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    bindings,
+    core::{
+        data_blob::DataBlob,
+        object::Object,
+        pipeline_state::{
+            GraphicsPipelineStateCreateInfo, GraphicsPipelineStateCreateInfoWrapper, PipelineState,
+        },
+        render_device::RenderDevice,
+        shader::{Shader, ShaderCreateInfo, ShaderSource},
+        shader_source_factory::ShaderSourceInputStreamFactory,
+    },
+};
+
+use super::{
+    shader_cache::{
+        create_shader_with_entry, read_bytes, read_u64, shader_key, write_atomically, CacheEntry,
+        CreatedShader,
+    },
+    shader_hot_reload::{creation_error, find_file, FileProvider, ShaderReloadError, WatchedFiles},
+};
+
+// Changed whenever the layout of the cache files changes
+const MAGIC: &[u8; 8] = b"DERSC002";
+
+// The keys of the pipelines contain every field of their create info but the name, like the
+// keys of the shaders. Pointers to other objects, like shaders and resource signatures, are
+// compared by address: the cache keeps a reference to them so that their address is not
+// reused.
+#[derive(Default)]
+struct KeyWriter {
+    key: Vec<u8>,
+}
+
+impl KeyWriter {
+    fn u64(&mut self, value: u64) {
+        self.key.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.u64(value.to_bits() as u64);
+    }
+
+    fn str(&mut self, value: *const std::os::raw::c_char) {
+        let bytes = if value.is_null() {
+            &[]
+        } else {
+            unsafe { CStr::from_ptr(value) }.to_bytes()
+        };
+        self.u64(bytes.len() as u64);
+        self.key.extend_from_slice(bytes);
+    }
+
+    fn pointer<T>(&mut self, value: *mut T) {
+        self.u64(value as usize as u64);
+    }
+
+    fn sampler_desc(&mut self, desc: &bindings::SamplerDesc) {
+        for value in [
+            desc.MinFilter as u64,
+            desc.MagFilter as u64,
+            desc.MipFilter as u64,
+            desc.AddressU as u64,
+            desc.AddressV as u64,
+            desc.AddressW as u64,
+            desc.Flags as u64,
+            desc.UnnormalizedCoords as u64,
+            desc.MaxAnisotropy as u64,
+            desc.ComparisonFunc as u64,
+        ] {
+            self.u64(value);
+        }
+        for value in [desc.MipLODBias, desc.MinLOD, desc.MaxLOD] {
+            self.f32(value);
+        }
+        for value in desc.BorderColor {
+            self.f32(value);
+        }
+    }
+
+    fn pipeline_state_create_info(&mut self, ci: &bindings::PipelineStateCreateInfo) {
+        let desc = &ci.PSODesc;
+        self.u64(desc.PipelineType as u64);
+        self.u64(desc.SRBAllocationGranularity as u64);
+        self.u64(desc.ImmediateContextMask);
+
+        let layout = &desc.ResourceLayout;
+        self.u64(layout.DefaultVariableType as u64);
+        self.u64(layout.DefaultVariableMergeStages as u64);
+
+        self.u64(layout.NumVariables as u64);
+        if layout.NumVariables > 0 {
+            let variables = unsafe {
+                std::slice::from_raw_parts(layout.Variables, layout.NumVariables as usize)
+            };
+            for variable in variables {
+                self.str(variable.Name);
+                self.u64(variable.ShaderStages as u64);
+                self.u64(variable.Type as u64);
+                self.u64(variable.Flags as u64);
+            }
+        }
+
+        self.u64(layout.NumImmutableSamplers as u64);
+        if layout.NumImmutableSamplers > 0 {
+            let samplers = unsafe {
+                std::slice::from_raw_parts(
+                    layout.ImmutableSamplers,
+                    layout.NumImmutableSamplers as usize,
+                )
+            };
+            for sampler in samplers {
+                self.u64(sampler.ShaderStages as u64);
+                self.str(sampler.SamplerOrTextureName);
+                self.sampler_desc(&sampler.Desc);
+            }
+        }
+
+        self.u64(ci.Flags as u64);
+
+        self.u64(ci.ResourceSignaturesCount as u64);
+        for &signature in resource_signatures(ci) {
+            self.pointer(signature);
+        }
+    }
+
+    fn graphics_pipeline_desc(&mut self, desc: &bindings::GraphicsPipelineDesc) {
+        let blend_desc = &desc.BlendDesc;
+        self.u64(blend_desc.AlphaToCoverageEnable as u64);
+        self.u64(blend_desc.IndependentBlendEnable as u64);
+        for render_target in &blend_desc.RenderTargets {
+            for value in [
+                render_target.BlendEnable as u64,
+                render_target.LogicOperationEnable as u64,
+                render_target.SrcBlend as u64,
+                render_target.DestBlend as u64,
+                render_target.BlendOp as u64,
+                render_target.SrcBlendAlpha as u64,
+                render_target.DestBlendAlpha as u64,
+                render_target.BlendOpAlpha as u64,
+                render_target.LogicOp as u64,
+                render_target.RenderTargetWriteMask as u64,
+            ] {
+                self.u64(value);
+            }
+        }
+
+        self.u64(desc.SampleMask as u64);
+
+        let rasterizer_desc = &desc.RasterizerDesc;
+        for value in [
+            rasterizer_desc.FillMode as u64,
+            rasterizer_desc.CullMode as u64,
+            rasterizer_desc.FrontCounterClockwise as u64,
+            rasterizer_desc.DepthClipEnable as u64,
+            rasterizer_desc.ScissorEnable as u64,
+            rasterizer_desc.AntialiasedLineEnable as u64,
+            rasterizer_desc.DepthBias as u64,
+        ] {
+            self.u64(value);
+        }
+        self.f32(rasterizer_desc.DepthBiasClamp);
+        self.f32(rasterizer_desc.SlopeScaledDepthBias);
+
+        let depth_stencil_desc = &desc.DepthStencilDesc;
+        for value in [
+            depth_stencil_desc.DepthEnable as u64,
+            depth_stencil_desc.DepthWriteEnable as u64,
+            depth_stencil_desc.DepthFunc as u64,
+            depth_stencil_desc.StencilEnable as u64,
+            depth_stencil_desc.StencilReadMask as u64,
+            depth_stencil_desc.StencilWriteMask as u64,
+        ] {
+            self.u64(value);
+        }
+        for face in [&depth_stencil_desc.FrontFace, &depth_stencil_desc.BackFace] {
+            self.u64(face.StencilFailOp as u64);
+            self.u64(face.StencilDepthFailOp as u64);
+            self.u64(face.StencilPassOp as u64);
+            self.u64(face.StencilFunc as u64);
+        }
+
+        let input_layout = &desc.InputLayout;
+        self.u64(input_layout.NumElements as u64);
+        if input_layout.NumElements > 0 {
+            let elements = unsafe {
+                std::slice::from_raw_parts(
+                    input_layout.LayoutElements,
+                    input_layout.NumElements as usize,
+                )
+            };
+            for element in elements {
+                self.str(element.HLSLSemantic);
+                for value in [
+                    element.InputIndex as u64,
+                    element.BufferSlot as u64,
+                    element.NumComponents as u64,
+                    element.ValueType as u64,
+                    element.IsNormalized as u64,
+                    element.RelativeOffset as u64,
+                    element.Stride as u64,
+                    element.Frequency as u64,
+                    element.InstanceDataStepRate as u64,
+                ] {
+                    self.u64(value);
+                }
+            }
+        }
+
+        for value in [
+            desc.PrimitiveTopology as u64,
+            desc.NumViewports as u64,
+            desc.NumRenderTargets as u64,
+            desc.SubpassIndex as u64,
+            desc.ShadingRateFlags as u64,
+        ] {
+            self.u64(value);
+        }
+        for format in desc.RTVFormats {
+            self.u64(format as u64);
+        }
+        self.u64(desc.DSVFormat as u64);
+        self.u64(desc.ReadOnlyDSV as u64);
+        self.u64(desc.SmplDesc.Count as u64);
+        self.u64(desc.SmplDesc.Quality as u64);
+        self.pointer(desc.pRenderPass);
+        self.u64(desc.NodeMask as u64);
+    }
+}
+
+fn resource_signatures(
+    ci: &bindings::PipelineStateCreateInfo,
+) -> &[*mut bindings::IPipelineResourceSignature] {
+    if ci.ResourceSignaturesCount == 0 || ci.ppResourceSignatures.is_null() {
+        &[]
+    } else {
+        unsafe {
+            std::slice::from_raw_parts(ci.ppResourceSignatures, ci.ResourceSignaturesCount as usize)
+        }
+    }
+}
+
+// Keeps the objects a pipeline was created from alive
+fn add_references(objects: impl IntoIterator<Item = *mut bindings::IObject>) -> Vec<Object> {
+    objects
+        .into_iter()
+        .filter(|object| !object.is_null())
+        .map(|object| {
+            let object = Object::new(object);
+            object.add_ref();
+            object
+        })
+        .collect()
+}
+
+// Shaders loaded through a source stream factory keep a reference to it
+struct CachedShader {
+    shader: Rc<Shader>,
+    _references: Vec<Object>,
+}
+
+struct CachedPipelineState {
+    pipeline_state: Rc<PipelineState>,
+    _references: Vec<Object>,
+}
+
+struct ReloadableShader {
+    key: Vec<u8>,
+    persistent_key: Vec<u8>,
+    create_info: Box<dyn Fn() -> ShaderCreateInfo<'static>>,
+    // Index of the factory of the search directories
+    directories: usize,
+    files: WatchedFiles,
+    shader: Rc<Shader>,
+}
+
+type CreatePipelineState =
+    dyn Fn(&mut RenderStateCache, &RenderDevice, &[Rc<Shader>]) -> Option<Rc<PipelineState>>;
+
+struct ReloadablePipelineState {
+    shaders: Vec<Rc<Shader>>,
+    create_pipeline_state: Rc<CreatePipelineState>,
+    pipeline_state: Rc<PipelineState>,
+}
+
+// The objects replaced by `RenderStateCache::reload`, as (previous, new) pairs. The callers
+// replace their references to the previous objects, e.g. create again the shader resource
+// bindings of the new pipelines.
+#[derive(Default)]
+pub struct ReloadedStates {
+    pub shaders: Vec<(Rc<Shader>, Rc<Shader>)>,
+    pub pipeline_states: Vec<(Rc<PipelineState>, Rc<PipelineState>)>,
+    // The shaders that failed to compile, the previous versions are kept
+    pub shader_errors: Vec<ShaderReloadError>,
+    // The pipelines that failed to be created with the new shaders, the previous versions are
+    // kept
+    pub pipeline_errors: Vec<Rc<PipelineState>>,
+}
+
+// Creates the shaders and pipelines through a render device and returns the same object for
+// identical create infos, e.g. for materials that share their shaders. Shaders and pipelines
+// are identified without their name: an object created again with another name is the object
+// created first, with the first name. Shaders loaded from files are only shared if they are
+// loaded through the same source stream factory.
+//
+// The bytecode of the compiled shaders can be saved to a file and loaded in the next runs, so
+// that the shaders are not compiled again. Pipelines are not saved: they are quickly created
+// again from the loaded shaders.
+//
+// The shaders and pipelines created by the `create_reloadable_*` functions are created again by
+// `reload` when the files of the shaders change, like with the `ShaderHotReloader`.
+#[derive(Default)]
+pub struct RenderStateCache {
+    shaders: HashMap<Vec<u8>, CachedShader>,
+    pipeline_states: HashMap<Vec<u8>, CachedPipelineState>,
+
+    // The bytecode of the compiled shaders, loaded from or saved to a file, by persistent key
+    entries: HashMap<Vec<u8>, CacheEntry>,
+    // The names of the files the shaders were compiled from
+    shader_files: HashMap<Vec<u8>, Vec<String>>,
+
+    // The factories of the reloadable shaders, one for each list of search directories, so that
+    // the shaders loaded from the same directories are shared
+    directory_factories: Vec<(Vec<PathBuf>, Rc<ShaderSourceInputStreamFactory>)>,
+    reloadable_shaders: Vec<ReloadableShader>,
+    reloadable_pipeline_states: Vec<ReloadablePipelineState>,
+}
+
+impl RenderStateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The persistent key identifies the shader across runs, for the bytecode of the shaders that
+    // are compiled. The key of the cache also identifies the source stream factory the files
+    // are loaded with.
+    fn shader_keys(
+        render_device: &RenderDevice,
+        shader_ci: &ShaderCreateInfo,
+    ) -> (Vec<u8>, Option<Vec<u8>>) {
+        let persistent_key = shader_key(render_device, shader_ci);
+
+        let key = match &persistent_key {
+            Some(persistent_key) => {
+                let mut key = persistent_key.clone();
+                let factory = shader_ci
+                    .source_stream_factory
+                    .map_or(std::ptr::null_mut(), |factory| factory.factory);
+                key.extend_from_slice(&(factory as usize as u64).to_le_bytes());
+                key
+            }
+            None => {
+                let mut key = Vec::new();
+                shader_ci.write_compile_options(&mut key);
+                match &shader_ci.source {
+                    ShaderSource::ByteCode(code) => key.extend_from_slice(code),
+                    ShaderSource::OwnedByteCode(code) => key.extend_from_slice(code),
+                    ShaderSource::FilePath(_) | ShaderSource::SourceCode(_) => {}
+                }
+                key
+            }
+        };
+
+        (key, persistent_key)
+    }
+
+    // Shaders created from bytecode are shared, but their bytecode is not saved
+    pub fn create_shader(
+        &mut self,
+        render_device: &RenderDevice,
+        shader_ci: ShaderCreateInfo,
+    ) -> Result<Rc<Shader>, Option<DataBlob>> {
+        let (key, persistent_key) = Self::shader_keys(render_device, &shader_ci);
+
+        if let Some(cached) = self.shaders.get(&key) {
+            return Ok(cached.shader.clone());
+        }
+
+        let source_stream_factory = shader_ci
+            .source_stream_factory
+            .map(|factory| factory.factory as *mut bindings::IObject);
+
+        let shader = match persistent_key {
+            Some(persistent_key) => {
+                let cached_entry = self.entries.get(&persistent_key);
+                let created_shader = create_shader_with_entry(
+                    render_device,
+                    shader_ci,
+                    persistent_key,
+                    cached_entry,
+                )?;
+                self.add_created_shader(&key, created_shader)
+            }
+            None => render_device.create_shader(&shader_ci)?,
+        };
+
+        Ok(self.insert_shader(key, shader, source_stream_factory))
+    }
+
+    fn add_created_shader(&mut self, key: &[u8], created_shader: CreatedShader) -> Shader {
+        self.shader_files.insert(
+            key.to_vec(),
+            created_shader
+                .files
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect(),
+        );
+        if let Some(entry) = created_shader.entry {
+            self.entries.insert(entry.key.clone(), entry);
+        }
+        created_shader.shader
+    }
+
+    fn insert_shader(
+        &mut self,
+        key: Vec<u8>,
+        shader: Shader,
+        source_stream_factory: Option<*mut bindings::IObject>,
+    ) -> Rc<Shader> {
+        let shader = Rc::new(shader);
+        self.shaders.insert(
+            key,
+            CachedShader {
+                shader: shader.clone(),
+                _references: add_references(source_stream_factory),
+            },
+        );
+        shader
+    }
+
+    fn directory_factory(&mut self, search_directories: &[&Path]) -> usize {
+        let search_directories: Vec<_> = search_directories
+            .iter()
+            .map(|directory| directory.to_path_buf())
+            .collect();
+
+        match self
+            .directory_factories
+            .iter()
+            .position(|(directories, _)| *directories == search_directories)
+        {
+            Some(index) => index,
+            None => {
+                let factory = Rc::new(ShaderSourceInputStreamFactory::from_provider(
+                    FileProvider {
+                        search_directories: search_directories.clone(),
+                    },
+                ));
+                self.directory_factories.push((search_directories, factory));
+                self.directory_factories.len() - 1
+            }
+        }
+    }
+
+    fn watch_files(&self, key: &[u8], directories: usize) -> WatchedFiles {
+        let (search_directories, _) = &self.directory_factories[directories];
+        WatchedFiles::new(
+            self.shader_files
+                .get(key)
+                .into_iter()
+                .flatten()
+                .filter_map(|name| find_file(search_directories, name)),
+        )
+    }
+
+    // The files are loaded from the working directory or the search directories, and
+    // `create_info` is called again whenever the shader is compiled. The source of the shader
+    // should be a `ShaderSource::FilePath`.
+    pub fn create_reloadable_shader<F>(
+        &mut self,
+        render_device: &RenderDevice,
+        search_directories: &[&Path],
+        create_info: F,
+    ) -> Result<Rc<Shader>, Option<DataBlob>>
+    where
+        F: Fn() -> ShaderCreateInfo<'static> + 'static,
+    {
+        let directories = self.directory_factory(search_directories);
+
+        let source_stream_factory = self.directory_factories[directories].1.clone();
+        let shader_ci = create_info().source_stream_factory(&source_stream_factory);
+        let (key, persistent_key) = Self::shader_keys(render_device, &shader_ci);
+
+        let shader = self.create_shader(render_device, shader_ci)?;
+
+        let already_reloadable = self
+            .reloadable_shaders
+            .iter()
+            .any(|reloadable_shader| reloadable_shader.key == key);
+
+        // Shaders created from bytecode have no files
+        if let (Some(persistent_key), false) = (persistent_key, already_reloadable) {
+            let files = self.watch_files(&key, directories);
+            self.reloadable_shaders.push(ReloadableShader {
+                key,
+                persistent_key,
+                create_info: Box::new(create_info),
+                directories,
+                files,
+                shader: shader.clone(),
+            });
+        }
+
+        Ok(shader)
+    }
+
+    // `create_pipeline_state` is given the current versions of `shaders`, in the same order,
+    // and should create the pipeline through the cache.
+    pub fn create_reloadable_pipeline_state<F>(
+        &mut self,
+        render_device: &RenderDevice,
+        shaders: &[Rc<Shader>],
+        create_pipeline_state: F,
+    ) -> Option<Rc<PipelineState>>
+    where
+        F: Fn(&mut RenderStateCache, &RenderDevice, &[Rc<Shader>]) -> Option<Rc<PipelineState>>
+            + 'static,
+    {
+        let pipeline_state = create_pipeline_state(self, render_device, shaders)?;
+
+        self.reloadable_pipeline_states
+            .push(ReloadablePipelineState {
+                shaders: shaders.to_vec(),
+                create_pipeline_state: Rc::new(create_pipeline_state),
+                pipeline_state: pipeline_state.clone(),
+            });
+
+        Some(pipeline_state)
+    }
+
+    // Compiles again the reloadable shaders whose files changed, and creates again the
+    // reloadable pipelines that use them. Must be called between frames, when the previous
+    // objects are not in use by the GPU.
+    pub fn reload(&mut self, render_device: &RenderDevice) -> ReloadedStates {
+        let mut result = ReloadedStates::default();
+
+        for index in 0..self.reloadable_shaders.len() {
+            if !self.reloadable_shaders[index].files.changed() {
+                continue;
+            }
+
+            let reloadable_shader = &self.reloadable_shaders[index];
+            let directories = reloadable_shader.directories;
+            let (_, source_stream_factory) = &self.directory_factories[directories];
+            let shader_ci =
+                (reloadable_shader.create_info)().source_stream_factory(source_stream_factory);
+
+            // The cached bytecode was compiled from the previous files
+            match create_shader_with_entry(
+                render_device,
+                shader_ci,
+                reloadable_shader.persistent_key.clone(),
+                None,
+            ) {
+                Ok(created_shader) => {
+                    let key = reloadable_shader.key.clone();
+                    let source_stream_factory = source_stream_factory.factory;
+
+                    let shader = self.add_created_shader(&key, created_shader);
+                    let files = self.watch_files(&key, directories);
+                    let shader = self.insert_shader(
+                        key,
+                        shader,
+                        Some(source_stream_factory as *mut bindings::IObject),
+                    );
+
+                    let reloadable_shader = &mut self.reloadable_shaders[index];
+                    reloadable_shader.files = files;
+                    let previous_shader =
+                        std::mem::replace(&mut reloadable_shader.shader, shader.clone());
+                    result.shaders.push((previous_shader, shader));
+                }
+                Err(log) => {
+                    let desc = reloadable_shader.shader.get_desc();
+                    let name = unsafe { CStr::from_ptr(desc._DeviceObjectAttribs.Name) };
+
+                    result.shader_errors.push(ShaderReloadError {
+                        shader_name: name.to_string_lossy().into_owned(),
+                        log: creation_error(log),
+                    });
+                }
+            }
+        }
+
+        let new_shader = |shader: &Rc<Shader>| {
+            result
+                .shaders
+                .iter()
+                .find(|(previous_shader, _)| Rc::ptr_eq(previous_shader, shader))
+                .map(|(_, new_shader)| new_shader.clone())
+        };
+
+        // The closures get the cache, so they are called once the pipelines are collected
+        let mut updates = Vec::new();
+        for (index, reloadable) in self.reloadable_pipeline_states.iter().enumerate() {
+            if reloadable
+                .shaders
+                .iter()
+                .any(|shader| new_shader(shader).is_some())
+            {
+                let shaders: Vec<_> = reloadable
+                    .shaders
+                    .iter()
+                    .map(|shader| new_shader(shader).unwrap_or_else(|| shader.clone()))
+                    .collect();
+                updates.push((index, shaders, reloadable.create_pipeline_state.clone()));
+            }
+        }
+
+        for (index, shaders, create_pipeline_state) in updates {
+            match create_pipeline_state(self, render_device, &shaders) {
+                Some(pipeline_state) => {
+                    let reloadable = &mut self.reloadable_pipeline_states[index];
+                    reloadable.shaders = shaders;
+                    let previous_pipeline_state =
+                        std::mem::replace(&mut reloadable.pipeline_state, pipeline_state.clone());
+                    result
+                        .pipeline_states
+                        .push((previous_pipeline_state, pipeline_state));
+                }
+                None => result.pipeline_errors.push(
+                    self.reloadable_pipeline_states[index]
+                        .pipeline_state
+                        .clone(),
+                ),
+            }
+        }
+
+        result
+    }
+
+    pub fn create_graphics_pipeline_state(
+        &mut self,
+        render_device: &RenderDevice,
+        pipeline_ci: &GraphicsPipelineStateCreateInfo,
+    ) -> Option<Rc<PipelineState>> {
+        let pipeline_ci_wrapper = GraphicsPipelineStateCreateInfoWrapper::from(pipeline_ci);
+        let ci = pipeline_ci_wrapper.get();
+
+        let shaders = [ci.pVS, ci.pPS, ci.pDS, ci.pHS, ci.pGS, ci.pAS, ci.pMS];
+
+        let mut key_writer = KeyWriter::default();
+        key_writer.u64(bindings::PIPELINE_TYPE_GRAPHICS as u64);
+        key_writer.pipeline_state_create_info(&ci._PipelineStateCreateInfo);
+        key_writer.graphics_pipeline_desc(&ci.GraphicsPipeline);
+        for shader in shaders {
+            key_writer.pointer(shader);
+        }
+
+        self.get_or_create_pipeline_state(
+            key_writer.key,
+            shaders
+                .into_iter()
+                .map(|shader| shader as *mut bindings::IObject)
+                .chain(std::iter::once(
+                    ci.GraphicsPipeline.pRenderPass as *mut bindings::IObject,
+                ))
+                .chain(
+                    resource_signatures(&ci._PipelineStateCreateInfo)
+                        .iter()
+                        .map(|&signature| signature as *mut bindings::IObject),
+                ),
+            || render_device.create_graphics_pipeline_state(pipeline_ci),
+        )
+    }
+
+    pub fn create_compute_pipeline_state(
+        &mut self,
+        render_device: &RenderDevice,
+        pipeline_ci: &bindings::ComputePipelineStateCreateInfo,
+    ) -> Option<Rc<PipelineState>> {
+        let mut key_writer = KeyWriter::default();
+        key_writer.u64(bindings::PIPELINE_TYPE_COMPUTE as u64);
+        key_writer.pipeline_state_create_info(&pipeline_ci._PipelineStateCreateInfo);
+        key_writer.pointer(pipeline_ci.pCS);
+
+        self.get_or_create_pipeline_state(
+            key_writer.key,
+            std::iter::once(pipeline_ci.pCS as *mut bindings::IObject).chain(
+                resource_signatures(&pipeline_ci._PipelineStateCreateInfo)
+                    .iter()
+                    .map(|&signature| signature as *mut bindings::IObject),
+            ),
+            || render_device.create_compute_pipeline_state(pipeline_ci),
+        )
+    }
+
+    fn get_or_create_pipeline_state(
+        &mut self,
+        key: Vec<u8>,
+        references: impl IntoIterator<Item = *mut bindings::IObject>,
+        create: impl FnOnce() -> Option<PipelineState>,
+    ) -> Option<Rc<PipelineState>> {
+        if let Some(cached) = self.pipeline_states.get(&key) {
+            return Some(cached.pipeline_state.clone());
+        }
+
+        let pipeline_state = Rc::new(create()?);
+        self.pipeline_states.insert(
+            key,
+            CachedPipelineState {
+                pipeline_state: pipeline_state.clone(),
+                _references: add_references(references),
+            },
+        );
+        Some(pipeline_state)
+    }
+
+    // Releases the shaders and pipelines that are only used by the cache. The bytecode of the
+    // shaders is kept, and so are the reloadable shaders and pipelines.
+    pub fn purge(&mut self) {
+        self.pipeline_states
+            .retain(|_, cached| Rc::strong_count(&cached.pipeline_state) > 1);
+        self.shaders
+            .retain(|_, cached| Rc::strong_count(&cached.shader) > 1);
+    }
+
+    // Cache files are:
+    //   magic, number of entries, (entry size, entry)...
+    // with the entries of the `ShaderCache`
+    pub fn save(&self, path: &Path) -> Result<(), io::Error> {
+        let mut data = MAGIC.to_vec();
+
+        data.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        for entry in self.entries.values() {
+            let mut entry_data = Vec::new();
+            entry.encode(&mut entry_data);
+
+            data.extend_from_slice(&(entry_data.len() as u64).to_le_bytes());
+            data.extend_from_slice(&entry_data);
+        }
+
+        write_atomically(path, data)
+    }
+
+    // Adds the shaders of the file to the cache. They are checked against the files they were
+    // compiled from when they are created.
+    pub fn load(&mut self, path: &Path) -> Result<(), io::Error> {
+        let data = fs::read(path)?;
+
+        let entries = Self::decode(&data).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid render state cache {}", path.display()),
+            )
+        })?;
+
+        self.entries
+            .extend(entries.into_iter().map(|entry| (entry.key.clone(), entry)));
+        Ok(())
+    }
+
+    fn decode(mut data: &[u8]) -> Option<Vec<CacheEntry>> {
+        if read_bytes(&mut data, MAGIC.len())? != MAGIC {
+            return None;
+        }
+
+        let num_entries = read_u64(&mut data)?;
+        let mut entries = Vec::new();
+        for _ in 0..num_entries {
+            let entry_size = read_u64(&mut data)? as usize;
+            entries.push(CacheEntry::decode(read_bytes(&mut data, entry_size)?)?);
+        }
+
+        Some(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &[u8]) -> CacheEntry {
+        CacheEntry {
+            key: key.to_vec(),
+            files: vec![("shader.psh".to_string(), 1), ("common.fxh".to_string(), 2)],
+            bytecode: key.iter().rev().copied().collect(),
+        }
+    }
+
+    fn cache_with_entries(keys: &[&[u8]]) -> RenderStateCache {
+        let mut cache = RenderStateCache::new();
+        for key in keys {
+            cache.entries.insert(key.to_vec(), entry(key));
+        }
+        cache
+    }
+
+    // Key, files and bytecode
+    type EntryFields = (Vec<u8>, Vec<(String, u64)>, Vec<u8>);
+
+    fn sorted_entries(cache: &RenderStateCache) -> Vec<EntryFields> {
+        let mut entries: Vec<_> = cache
+            .entries
+            .iter()
+            .map(|(key, entry)| {
+                assert_eq!(*key, entry.key);
+                (
+                    entry.key.clone(),
+                    entry.files.clone(),
+                    entry.bytecode.clone(),
+                )
+            })
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "render_state_cache_test_{name}_{}",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = temporary_path("round_trip");
+        let cache = cache_with_entries(&[b"first", b"second", b""]);
+        cache.save(&path).unwrap();
+
+        let mut loaded = RenderStateCache::new();
+        loaded.load(&path).unwrap();
+        assert_eq!(sorted_entries(&loaded), sorted_entries(&cache));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn empty_cache_round_trip() {
+        let path = temporary_path("empty");
+        RenderStateCache::new().save(&path).unwrap();
+
+        let mut loaded = cache_with_entries(&[b"kept"]);
+        loaded.load(&path).unwrap();
+        assert_eq!(
+            sorted_entries(&loaded),
+            sorted_entries(&cache_with_entries(&[b"kept"]))
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncated_data_is_rejected() {
+        let path = temporary_path("truncated");
+        cache_with_entries(&[b"first", b"second"])
+            .save(&path)
+            .unwrap();
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(RenderStateCache::decode(&data).is_some());
+        for size in 0..data.len() {
+            assert!(
+                RenderStateCache::decode(&data[..size]).is_none(),
+                "size {size}"
+            );
+        }
+    }
+
+    #[test]
+    fn file_with_wrong_magic_is_rejected() {
+        let path = temporary_path("magic");
+        cache_with_entries(&[b"first"]).save(&path).unwrap();
+
+        let mut data = fs::read(&path).unwrap();
+        data[0] ^= 1;
+        assert!(RenderStateCache::decode(&data).is_none());
+        fs::write(&path, data).unwrap();
+
+        let mut loaded = RenderStateCache::new();
+        let error = loaded.load(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(loaded.entries.is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
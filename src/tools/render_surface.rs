@@ -0,0 +1,153 @@
+use crate::{
+    bindings,
+    core::{
+        graphics_types::BindFlags,
+        object::AsObject,
+        render_device::RenderDevice,
+        swap_chain::SwapChain,
+        texture::{Texture, TextureDesc, TextureDimension},
+        texture_view::{TextureView, TextureViewType},
+    },
+};
+
+// The surface the application renders into: either the swap chain of a window or an offscreen
+// render target when running headless.
+pub trait RenderSurface {
+    fn get_desc(&self) -> &bindings::SwapChainDesc;
+
+    fn get_current_back_buffer_rtv(&self) -> TextureView;
+
+    fn get_depth_buffer_dsv(&self) -> TextureView;
+
+    fn resize(&mut self, width: u32, height: u32);
+
+    fn present(&self, sync_interval: u32);
+}
+
+impl RenderSurface for SwapChain {
+    fn get_desc(&self) -> &bindings::SwapChainDesc {
+        SwapChain::get_desc(self)
+    }
+
+    fn get_current_back_buffer_rtv(&self) -> TextureView {
+        SwapChain::get_current_back_buffer_rtv(self)
+    }
+
+    fn get_depth_buffer_dsv(&self) -> TextureView {
+        SwapChain::get_depth_buffer_dsv(self)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        SwapChain::resize(self, width, height, None)
+    }
+
+    fn present(&self, sync_interval: u32) {
+        SwapChain::present(self, sync_interval)
+    }
+}
+
+pub struct OffscreenSurface {
+    render_device: RenderDevice,
+    desc: bindings::SwapChainDesc,
+
+    color_buffer: Texture,
+    depth_buffer: Texture,
+}
+
+impl OffscreenSurface {
+    pub fn new(render_device: &RenderDevice, desc: &bindings::SwapChainDesc) -> Option<Self> {
+        let render_device = RenderDevice::new(render_device.render_device);
+        render_device.as_object().add_ref();
+
+        let (color_buffer, depth_buffer) = Self::create_buffers(&render_device, desc)?;
+
+        Some(OffscreenSurface {
+            render_device,
+            desc: *desc,
+            color_buffer,
+            depth_buffer,
+        })
+    }
+
+    fn create_buffers(
+        render_device: &RenderDevice,
+        desc: &bindings::SwapChainDesc,
+    ) -> Option<(Texture, Texture)> {
+        let color_buffer = render_device.create_texture(
+            &TextureDesc::new(
+                c"Offscreen color buffer",
+                TextureDimension::Texture2D,
+                desc.Width,
+                desc.Height,
+                desc.ColorBufferFormat as bindings::_TEXTURE_FORMAT,
+            )
+            .bind_flags(BindFlags::RenderTarget | BindFlags::ShaderResourcec),
+            &[],
+            None,
+        )?;
+
+        let depth_buffer = render_device.create_texture(
+            &TextureDesc::new(
+                c"Offscreen depth buffer",
+                TextureDimension::Texture2D,
+                desc.Width,
+                desc.Height,
+                desc.DepthBufferFormat as bindings::_TEXTURE_FORMAT,
+            )
+            .bind_flags(BindFlags::DepthStencil)
+            .clear_depth(desc.DefaultDepthValue)
+            .clear_stencil(desc.DefaultStencilValue),
+            &[],
+            None,
+        )?;
+
+        Some((color_buffer, depth_buffer))
+    }
+
+    pub fn get_color_buffer(&self) -> &Texture {
+        &self.color_buffer
+    }
+
+    pub fn get_depth_buffer(&self) -> &Texture {
+        &self.depth_buffer
+    }
+}
+
+impl RenderSurface for OffscreenSurface {
+    fn get_desc(&self) -> &bindings::SwapChainDesc {
+        &self.desc
+    }
+
+    fn get_current_back_buffer_rtv(&self) -> TextureView {
+        self.color_buffer
+            .get_default_view(TextureViewType::RenderTarget)
+            .unwrap()
+    }
+
+    fn get_depth_buffer_dsv(&self) -> TextureView {
+        self.depth_buffer
+            .get_default_view(TextureViewType::DepthStencil)
+            .unwrap()
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 || (width == self.desc.Width && height == self.desc.Height) {
+            return;
+        }
+
+        let desc = bindings::SwapChainDesc {
+            Width: width,
+            Height: height,
+            ..self.desc
+        };
+
+        if let Some((color_buffer, depth_buffer)) = Self::create_buffers(&self.render_device, &desc)
+        {
+            self.desc = desc;
+            self.color_buffer = color_buffer;
+            self.depth_buffer = depth_buffer;
+        }
+    }
+
+    fn present(&self, _sync_interval: u32) {}
+}
@@ -0,0 +1,240 @@
+use std::{collections::VecDeque, fmt, path::PathBuf};
+
+use crate::{
+    bindings,
+    core::{
+        device_context::DeviceContext, fence::Fence, object::AsObject, render_device::RenderDevice,
+        texture::Texture, texture_view::TextureView,
+    },
+    tools::{
+        image_utils::save_png,
+        texture_reader::{
+            copy_to_staging_texture, create_staging_texture, is_staging_texture_compatible,
+            read_staging_texture,
+        },
+    },
+};
+
+// Number of captures that can be in flight before `capture` blocks on the oldest one
+const STAGING_TEXTURE_RING_SIZE: usize = 3;
+
+pub struct CapturedFrame {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    // Tightly packed RGBA8 rows, top row first
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    DirectoryCreation {
+        directory: PathBuf,
+        error: std::io::Error,
+    },
+    FenceCreation,
+    StagingTextureCreation {
+        id: u32,
+    },
+    ReadBack {
+        id: u32,
+    },
+    // Returned by the callback, e.g. when a captured frame cannot be saved
+    Callback {
+        id: u32,
+        error: std::io::Error,
+    },
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::DirectoryCreation { directory, error } => {
+                write!(f, "Failed to create {}: {error}", directory.display())
+            }
+            CaptureError::FenceCreation => write!(f, "Failed to create the screen capture fence"),
+            CaptureError::StagingTextureCreation { id } => {
+                write!(f, "Failed to create a staging texture for capture {id}")
+            }
+            CaptureError::ReadBack { id } => write!(f, "Failed to read back capture {id}"),
+            CaptureError::Callback { id, error } => write!(f, "Capture {id}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+struct PendingCapture {
+    id: u32,
+    staging_texture: Texture,
+    fence_value: u64,
+}
+
+// Asynchronously copies render targets into staging textures. Completed captures are
+// delivered to the callback from `capture` and `flush`, without stalling the GPU.
+pub struct ScreenCapture {
+    render_device: RenderDevice,
+
+    fence: Fence,
+    last_fence_value: u64,
+
+    pending_captures: VecDeque<PendingCapture>,
+    available_staging_textures: Vec<Texture>,
+
+    on_frame_captured: Box<dyn FnMut(CapturedFrame) -> std::io::Result<()>>,
+
+    // Failures since the last call to `take_errors`
+    errors: Vec<CaptureError>,
+}
+
+impl ScreenCapture {
+    pub fn new<F>(render_device: &RenderDevice, on_frame_captured: F) -> Result<Self, CaptureError>
+    where
+        F: FnMut(CapturedFrame) -> std::io::Result<()> + 'static,
+    {
+        let render_device = RenderDevice::new(render_device.render_device);
+        render_device.as_object().add_ref();
+
+        let fence = render_device
+            .create_fence(&bindings::FenceDesc {
+                _DeviceObjectAttribs: bindings::DeviceObjectAttribs {
+                    Name: c"Screen capture fence".as_ptr(),
+                },
+                Type: bindings::FENCE_TYPE_CPU_WAIT_ONLY as bindings::FENCE_TYPE,
+            })
+            .ok_or(CaptureError::FenceCreation)?;
+
+        Ok(ScreenCapture {
+            render_device,
+            fence,
+            last_fence_value: 0,
+            pending_captures: VecDeque::new(),
+            available_staging_textures: Vec::new(),
+            on_frame_captured: Box::new(on_frame_captured),
+            errors: Vec::new(),
+        })
+    }
+
+    // Writes every captured frame to `directory` as `frame_<id>.png`
+    pub fn to_directory(
+        render_device: &RenderDevice,
+        directory: PathBuf,
+    ) -> Result<Self, CaptureError> {
+        if let Err(error) = std::fs::create_dir_all(&directory) {
+            return Err(CaptureError::DirectoryCreation { directory, error });
+        }
+
+        Self::new(render_device, move |frame| {
+            let file_path = directory.join(format!("frame_{:05}.png", frame.id));
+            save_png(&file_path, frame.width, frame.height, &frame.pixels).map_err(|error| {
+                std::io::Error::new(
+                    error.kind(),
+                    format!("Failed to save {}: {error}", file_path.display()),
+                )
+            })
+        })
+    }
+
+    // Returns and clears the failures of `capture`, `poll` and `flush`
+    pub fn take_errors(&mut self) -> Vec<CaptureError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn has_pending_captures(&self) -> bool {
+        !self.pending_captures.is_empty()
+    }
+
+    // Must be called before the back buffer is presented
+    pub fn capture(&mut self, device_context: &DeviceContext, texture_view: &TextureView, id: u32) {
+        self.poll(device_context);
+
+        if self.pending_captures.len() >= STAGING_TEXTURE_RING_SIZE {
+            let oldest_fence_value = self.pending_captures.front().unwrap().fence_value;
+            device_context.flush();
+            self.fence.wait(oldest_fence_value);
+            self.poll(device_context);
+        }
+
+        let texture = texture_view.query_texture();
+        let desc = texture.get_desc();
+
+        let staging_texture = match self
+            .available_staging_textures
+            .iter()
+            .position(|staging_texture| is_staging_texture_compatible(staging_texture, desc))
+        {
+            Some(index) => self.available_staging_textures.swap_remove(index),
+            None => {
+                // The back buffer has been resized: textures of the previous size are useless
+                self.available_staging_textures.clear();
+
+                let Some(staging_texture) = create_staging_texture(&self.render_device, desc)
+                else {
+                    self.errors
+                        .push(CaptureError::StagingTextureCreation { id });
+                    return;
+                };
+                staging_texture
+            }
+        };
+
+        copy_to_staging_texture(device_context, &texture, &staging_texture);
+
+        self.last_fence_value += 1;
+        device_context.enqueue_signal(&self.fence, self.last_fence_value);
+
+        self.pending_captures.push_back(PendingCapture {
+            id,
+            staging_texture,
+            fence_value: self.last_fence_value,
+        });
+    }
+
+    // Delivers the captures the GPU has completed, in submission order
+    pub fn poll(&mut self, device_context: &DeviceContext) {
+        let completed_fence_value = self.fence.get_completed_value();
+
+        while self
+            .pending_captures
+            .front()
+            .is_some_and(|capture| capture.fence_value <= completed_fence_value)
+        {
+            let mut capture = self.pending_captures.pop_front().unwrap();
+
+            let desc = *capture.staging_texture.get_desc();
+
+            match read_staging_texture(
+                &self.render_device,
+                device_context,
+                &mut capture.staging_texture,
+            ) {
+                Some(pixels) => {
+                    if let Err(error) = (self.on_frame_captured)(CapturedFrame {
+                        id: capture.id,
+                        width: desc.Width,
+                        height: desc.Height,
+                        pixels,
+                    }) {
+                        self.errors.push(CaptureError::Callback {
+                            id: capture.id,
+                            error,
+                        });
+                    }
+                }
+                None => self.errors.push(CaptureError::ReadBack { id: capture.id }),
+            }
+
+            self.available_staging_textures
+                .push(capture.staging_texture);
+        }
+    }
+
+    // Waits for and delivers every pending capture
+    pub fn flush(&mut self, device_context: &DeviceContext) {
+        if self.has_pending_captures() {
+            device_context.flush();
+            self.fence.wait(self.last_fence_value);
+            self.poll(device_context);
+        }
+    }
+}
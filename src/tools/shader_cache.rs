@@ -0,0 +1,437 @@
+use std::{
+    cell::Cell,
+    fs,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    bindings,
+    core::{
+        data_blob::DataBlob,
+        graphics_types::RenderDeviceType,
+        object::AsObject,
+        render_device::RenderDevice,
+        shader::{Shader, ShaderCreateInfo, ShaderSource},
+        shader_source_factory::{ShaderSourceInputStreamFactory, ShaderSourceProvider},
+    },
+};
+
+// Changed whenever the layout of the cache files changes
+const MAGIC: &[u8; 8] = b"DESHC002";
+
+// 64 bit FNV-1a, which is stable across runs and versions of Rust
+pub(crate) fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn device_type_name(device_type: &RenderDeviceType) -> &'static str {
+    match device_type {
+        RenderDeviceType::D3D11 => "d3d11",
+        RenderDeviceType::D3D12 => "d3d12",
+        RenderDeviceType::GL => "gl",
+        RenderDeviceType::GLES => "gles",
+        RenderDeviceType::VULKAN => "vk",
+        RenderDeviceType::METAL => "mtl",
+        RenderDeviceType::WEBGPU => "wgpu",
+    }
+}
+
+// Cache files are:
+//   magic, key length, key, number of files, (name length, name, content hash)...,
+//   bytecode length, bytecode hash, bytecode
+// The lengths and counts are little endian u64s. The whole key is stored, so that a hash
+// collision of the file names is a cache miss. The bytecode is checked against its length and
+// hash, so that truncated or corrupted files are cache misses.
+pub(crate) struct CacheEntry {
+    pub(crate) key: Vec<u8>,
+    // The source file and the files it includes, with the hashes of their content
+    pub(crate) files: Vec<(String, u64)>,
+    pub(crate) bytecode: Vec<u8>,
+}
+
+pub(crate) fn read_u64(data: &mut &[u8]) -> Option<u64> {
+    let bytes = read_bytes(data, std::mem::size_of::<u64>())?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+pub(crate) fn read_bytes<'a>(data: &mut &'a [u8], size: usize) -> Option<&'a [u8]> {
+    if data.len() < size {
+        return None;
+    }
+    let (bytes, rest) = data.split_at(size);
+    *data = rest;
+    Some(bytes)
+}
+
+impl CacheEntry {
+    fn read(path: &Path) -> Option<Self> {
+        let data = fs::read(path).ok()?;
+        let mut data = data.as_slice();
+
+        if read_bytes(&mut data, MAGIC.len())? != MAGIC {
+            return None;
+        }
+
+        Self::decode(data)
+    }
+
+    // Everything after the magic. The data must contain a single entry.
+    pub(crate) fn decode(mut data: &[u8]) -> Option<Self> {
+        let key_size = read_u64(&mut data)? as usize;
+        let key = read_bytes(&mut data, key_size)?.to_vec();
+
+        let num_files = read_u64(&mut data)?;
+        let mut files = Vec::new();
+        for _ in 0..num_files {
+            let name_size = read_u64(&mut data)? as usize;
+            let name = String::from_utf8(read_bytes(&mut data, name_size)?.to_vec()).ok()?;
+            files.push((name, read_u64(&mut data)?));
+        }
+
+        let bytecode_size = read_u64(&mut data)? as usize;
+        let bytecode_hash = read_u64(&mut data)?;
+        if data.len() != bytecode_size || hash(data) != bytecode_hash {
+            return None;
+        }
+
+        Some(CacheEntry {
+            key,
+            files,
+            bytecode: data.to_vec(),
+        })
+    }
+
+    fn write(&self, path: &Path) -> Result<(), std::io::Error> {
+        let mut data = MAGIC.to_vec();
+        self.encode(&mut data);
+
+        write_atomically(path, data)
+    }
+
+    pub(crate) fn encode(&self, data: &mut Vec<u8>) {
+        data.extend_from_slice(&(self.key.len() as u64).to_le_bytes());
+        data.extend_from_slice(&self.key);
+
+        data.extend_from_slice(&(self.files.len() as u64).to_le_bytes());
+        for (name, content_hash) in &self.files {
+            data.extend_from_slice(&(name.len() as u64).to_le_bytes());
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&content_hash.to_le_bytes());
+        }
+
+        data.extend_from_slice(&(self.bytecode.len() as u64).to_le_bytes());
+        data.extend_from_slice(&hash(&self.bytecode).to_le_bytes());
+        data.extend_from_slice(&self.bytecode);
+    }
+}
+
+// The data is written to a temporary file first, so that other processes never read a
+// partially written file
+pub(crate) fn write_atomically(path: &Path, data: Vec<u8>) -> Result<(), std::io::Error> {
+    let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temporary_path, data)?;
+    fs::rename(&temporary_path, path)
+}
+
+// Reads the files through the source stream factory of the create info and remembers their
+// hashes
+struct RecordingProvider {
+    factory: ShaderSourceInputStreamFactory,
+    loaded_files: Arc<Mutex<Vec<(String, u64)>>>,
+}
+
+impl ShaderSourceProvider for RecordingProvider {
+    fn load(&self, name: &str) -> Option<Vec<u8>> {
+        let data = self.factory.load(name)?;
+        self.loaded_files
+            .lock()
+            .unwrap()
+            .push((name.to_string(), hash(&data)));
+        Some(data)
+    }
+}
+
+// None for sources that are not compiled
+pub(crate) fn shader_key(
+    render_device: &RenderDevice,
+    shader_ci: &ShaderCreateInfo,
+) -> Option<Vec<u8>> {
+    let mut key = Vec::new();
+
+    key.extend_from_slice(&(bindings::DILIGENT_API_VERSION as u64).to_le_bytes());
+    key.extend_from_slice(
+        device_type_name(render_device.get_device_info().device_type()).as_bytes(),
+    );
+    key.push(0);
+
+    shader_ci.write_compile_options(&mut key);
+
+    match &shader_ci.source {
+        ShaderSource::FilePath(path) => {
+            key.push(0);
+            key.extend_from_slice(path.as_os_str().as_bytes());
+        }
+        ShaderSource::SourceCode(code) => {
+            key.push(1);
+            key.extend_from_slice(code.as_bytes());
+        }
+        ShaderSource::ByteCode(_) | ShaderSource::OwnedByteCode(_) => return None,
+    }
+
+    Some(key)
+}
+
+// The files are read again to check that they have not changed
+fn are_files_unchanged(shader_ci: &ShaderCreateInfo, files: &[(String, u64)]) -> bool {
+    files.iter().all(|(name, content_hash)| {
+        shader_ci
+            .source_stream_factory
+            .and_then(|factory| factory.load(name))
+            .is_some_and(|data| hash(&data) == *content_hash)
+    })
+}
+
+pub(crate) struct CreatedShader {
+    pub(crate) shader: Shader,
+    // The entry of the compiled shader, that should then be cached. None if the shader was
+    // created from the cached entry or has no bytecode.
+    pub(crate) entry: Option<CacheEntry>,
+    // The files the shader was compiled from, with the hashes of their content
+    pub(crate) files: Vec<(String, u64)>,
+    pub(crate) from_cached_entry: bool,
+    // The device failed to create the shader from the bytecode of the cached entry
+    pub(crate) rejected_entry: bool,
+}
+
+// Creates the shader from the bytecode of the cached entry if it is still valid, compiles it
+// otherwise
+pub(crate) fn create_shader_with_entry(
+    render_device: &RenderDevice,
+    mut shader_ci: ShaderCreateInfo,
+    key: Vec<u8>,
+    cached_entry: Option<&CacheEntry>,
+) -> Result<CreatedShader, Option<DataBlob>> {
+    let mut rejected_entry = false;
+
+    if let Some(entry) = cached_entry {
+        if entry.key == key && are_files_unchanged(&shader_ci, &entry.files) {
+            let source = std::mem::replace(
+                &mut shader_ci.source,
+                ShaderSource::OwnedByteCode(entry.bytecode.clone()),
+            );
+            let file_path = shader_ci.file_path.take();
+
+            match render_device.create_shader(&shader_ci) {
+                Ok(shader) => {
+                    return Ok(CreatedShader {
+                        shader,
+                        entry: None,
+                        files: entry.files.clone(),
+                        from_cached_entry: true,
+                        rejected_entry: false,
+                    })
+                }
+                Err(_) => {
+                    rejected_entry = true;
+                    shader_ci.source = source;
+                    shader_ci.file_path = file_path;
+                }
+            }
+        }
+    }
+
+    let loaded_files = Arc::new(Mutex::new(Vec::new()));
+
+    let recording_factory = shader_ci.source_stream_factory.map(|factory| {
+        let factory = ShaderSourceInputStreamFactory::new(factory.factory);
+        factory.as_object().add_ref();
+
+        ShaderSourceInputStreamFactory::from_provider(RecordingProvider {
+            factory,
+            loaded_files: loaded_files.clone(),
+        })
+    });
+
+    let mut shader_ci = shader_ci;
+    if let Some(recording_factory) = &recording_factory {
+        shader_ci.source_stream_factory = Some(recording_factory);
+    }
+
+    let shader = render_device.create_shader(&shader_ci)?;
+
+    let files: Vec<_> = loaded_files.lock().unwrap().drain(..).collect();
+
+    let bytecode = shader.bytecode();
+    let entry = (!bytecode.is_empty()).then(|| CacheEntry {
+        key,
+        files: files.clone(),
+        bytecode: bytecode.to_vec(),
+    });
+
+    Ok(CreatedShader {
+        shader,
+        entry,
+        files,
+        from_cached_entry: false,
+        rejected_entry,
+    })
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct ShaderCacheStats {
+    // Shaders created from their cached bytecode
+    pub hits: u32,
+    // Shaders compiled because they were not cached, or their files changed
+    pub misses: u32,
+    // Cached bytecode the device could not create a shader from: the shaders were compiled
+    pub rejected_entries: u32,
+    // Compiled shaders whose bytecode could not be written to the cache directory
+    pub write_errors: u32,
+}
+
+// Stores the bytecode of the compiled shaders in a directory, and creates the shaders from it
+// in the next runs instead of compiling them again. Shaders are identified by their source,
+// the content of the files they include, their macros, compiler and compile options, the
+// device type and the version of the engine.
+pub struct ShaderCache {
+    directory: PathBuf,
+    stats: Cell<ShaderCacheStats>,
+}
+
+impl ShaderCache {
+    pub fn new(directory: &Path) -> Result<Self, std::io::Error> {
+        fs::create_dir_all(directory)?;
+        Ok(ShaderCache {
+            directory: directory.to_path_buf(),
+            stats: Cell::new(ShaderCacheStats::default()),
+        })
+    }
+
+    pub fn get_stats(&self) -> ShaderCacheStats {
+        self.stats.get()
+    }
+
+    // Shaders created asynchronously are only cached if they are compiled when this returns.
+    // Backends that do not compile shaders to bytecode, like OpenGL, are not cached.
+    pub fn create_shader(
+        &self,
+        render_device: &RenderDevice,
+        shader_ci: ShaderCreateInfo,
+    ) -> Result<Shader, Option<DataBlob>> {
+        let Some(key) = shader_key(render_device, &shader_ci) else {
+            return render_device.create_shader(&shader_ci);
+        };
+
+        let path = self.directory.join(format!("{:016x}.bin", hash(&key)));
+
+        let cached_entry = CacheEntry::read(&path);
+        let created_shader =
+            create_shader_with_entry(render_device, shader_ci, key, cached_entry.as_ref())?;
+
+        let mut stats = self.stats.get();
+        if created_shader.from_cached_entry {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        if created_shader.rejected_entry {
+            stats.rejected_entries += 1;
+        }
+        if let Some(entry) = created_shader.entry {
+            if entry.write(&path).is_err() {
+                stats.write_errors += 1;
+            }
+        }
+        self.stats.set(stats);
+
+        Ok(created_shader.shader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> CacheEntry {
+        CacheEntry {
+            key: b"key".to_vec(),
+            files: vec![
+                ("shader.psh".to_string(), hash(b"source")),
+                ("common.fxh".to_string(), hash(b"include")),
+            ],
+            bytecode: (0..=255).collect(),
+        }
+    }
+
+    fn encode(entry: &CacheEntry) -> Vec<u8> {
+        let mut data = Vec::new();
+        entry.encode(&mut data);
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let entry = entry();
+        let decoded = CacheEntry::decode(&encode(&entry)).unwrap();
+
+        assert_eq!(decoded.key, entry.key);
+        assert_eq!(decoded.files, entry.files);
+        assert_eq!(decoded.bytecode, entry.bytecode);
+    }
+
+    #[test]
+    fn empty_entry_round_trip() {
+        let entry = CacheEntry {
+            key: Vec::new(),
+            files: Vec::new(),
+            bytecode: Vec::new(),
+        };
+        let decoded = CacheEntry::decode(&encode(&entry)).unwrap();
+
+        assert!(decoded.key.is_empty());
+        assert!(decoded.files.is_empty());
+        assert!(decoded.bytecode.is_empty());
+    }
+
+    #[test]
+    fn truncated_entry_is_rejected() {
+        let data = encode(&entry());
+        for size in 0..data.len() {
+            assert!(CacheEntry::decode(&data[..size]).is_none(), "size {size}");
+        }
+    }
+
+    #[test]
+    fn trailing_data_is_rejected() {
+        let mut data = encode(&entry());
+        data.push(0);
+        assert!(CacheEntry::decode(&data).is_none());
+    }
+
+    #[test]
+    fn corrupted_bytecode_is_rejected() {
+        let mut data = encode(&entry());
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(CacheEntry::decode(&data).is_none());
+    }
+
+    #[test]
+    fn file_with_wrong_magic_is_rejected() {
+        let path = std::env::temp_dir().join(format!("shader_cache_test_{}", std::process::id()));
+
+        entry().write(&path).unwrap();
+        assert!(CacheEntry::read(&path).is_some());
+
+        let mut data = fs::read(&path).unwrap();
+        data[0] ^= 1;
+        fs::write(&path, data).unwrap();
+        assert!(CacheEntry::read(&path).is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
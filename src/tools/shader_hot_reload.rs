@@ -0,0 +1,293 @@
+use std::{
+    ffi::CStr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::core::{
+    data_blob::{compile_log, DataBlob},
+    object::AsObject,
+    pipeline_state::PipelineState,
+    render_device::RenderDevice,
+    shader::{Shader, ShaderCreateInfo},
+    shader_source_factory::{ShaderSourceInputStreamFactory, ShaderSourceProvider},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ShaderId(usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PipelineId(usize);
+
+#[derive(Debug)]
+pub struct ShaderReloadError {
+    pub shader_name: String,
+    pub log: String,
+}
+
+#[derive(Default, Debug)]
+pub struct ReloadResult {
+    pub reloaded_shaders: Vec<ShaderId>,
+    // The pipelines that have been replaced: their shader resource bindings have to be
+    // created again
+    pub reloaded_pipelines: Vec<PipelineId>,
+    // The shaders that failed to compile, the previous versions are kept
+    pub shader_errors: Vec<ShaderReloadError>,
+    // The pipelines that failed to be rebuilt with the new shaders, the previous versions are
+    // kept
+    pub pipeline_errors: Vec<PipelineId>,
+}
+
+// Names are relative to the working directory or to one of the search directories
+pub(crate) fn find_file(search_directories: &[PathBuf], name: &str) -> Option<PathBuf> {
+    std::iter::once(PathBuf::from(name))
+        .chain(
+            search_directories
+                .iter()
+                .map(|directory| directory.join(name)),
+        )
+        .find(|path| path.is_file())
+}
+
+// Loads the files from the disk
+pub(crate) struct FileProvider {
+    pub(crate) search_directories: Vec<PathBuf>,
+}
+
+impl ShaderSourceProvider for FileProvider {
+    fn load(&self, name: &str) -> Option<Vec<u8>> {
+        std::fs::read(find_file(&self.search_directories, name)?).ok()
+    }
+}
+
+// Loads the files from the disk and remembers which ones the compiler read
+struct RecordingFileProvider {
+    search_directories: Vec<PathBuf>,
+    loaded_files: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl ShaderSourceProvider for RecordingFileProvider {
+    fn load(&self, name: &str) -> Option<Vec<u8>> {
+        let path = find_file(&self.search_directories, name)?;
+
+        let source = std::fs::read(&path).ok()?;
+        self.loaded_files.lock().unwrap().push(path);
+        Some(source)
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// The source file of a shader and the files it includes, with their modification times
+pub(crate) struct WatchedFiles(Vec<(PathBuf, Option<SystemTime>)>);
+
+impl WatchedFiles {
+    pub(crate) fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        WatchedFiles(
+            paths
+                .into_iter()
+                .map(|path| {
+                    let time = modification_time(&path);
+                    (path, time)
+                })
+                .collect(),
+        )
+    }
+
+    // Whether a file changed since the previous call. The new times are kept, so that a shader
+    // that fails to compile is not compiled again until its files change again.
+    pub(crate) fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for (path, time) in self.0.iter_mut() {
+            let new_time = modification_time(path);
+            if new_time != *time {
+                *time = new_time;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+pub(crate) fn creation_error(log: Option<DataBlob>) -> String {
+    match log {
+        Some(log) => compile_log(&log),
+        // The engine fails before compiling, e.g. when a file is missing
+        None => "The shader could not be created, a file may be missing".to_string(),
+    }
+}
+
+struct WatchedShader {
+    name: String,
+    create_info: Box<dyn Fn() -> ShaderCreateInfo<'static>>,
+    shader: Shader,
+    files: WatchedFiles,
+}
+
+type CreatePipeline = dyn Fn(&RenderDevice, &[&Shader]) -> Option<PipelineState>;
+
+struct WatchedPipeline {
+    shaders: Vec<ShaderId>,
+    create_pipeline: Box<CreatePipeline>,
+    pipeline_state: PipelineState,
+}
+
+// Recompiles the shaders whose files have changed and rebuilds the pipelines that use them.
+// Shaders are loaded from the search directories through a source stream factory, which
+// also tells which files they include. When a shader or a pipeline fails to compile, the
+// previous one is kept and the failure is reported by `update`.
+pub struct ShaderHotReloader {
+    render_device: RenderDevice,
+    search_directories: Vec<PathBuf>,
+
+    shaders: Vec<WatchedShader>,
+    pipelines: Vec<WatchedPipeline>,
+}
+
+impl ShaderHotReloader {
+    pub fn new(render_device: &RenderDevice, search_directories: &[&Path]) -> Self {
+        let render_device = RenderDevice::new(render_device.render_device);
+        render_device.as_object().add_ref();
+
+        ShaderHotReloader {
+            render_device,
+            search_directories: search_directories
+                .iter()
+                .map(|directory| directory.to_path_buf())
+                .collect(),
+            shaders: Vec::new(),
+            pipelines: Vec::new(),
+        }
+    }
+
+    // Returns the files the shader was loaded from, or the compile log
+    fn compile(
+        &self,
+        create_info: &dyn Fn() -> ShaderCreateInfo<'static>,
+    ) -> Result<(Shader, WatchedFiles), String> {
+        let loaded_files = Arc::new(Mutex::new(Vec::new()));
+
+        let source_stream_factory =
+            ShaderSourceInputStreamFactory::from_provider(RecordingFileProvider {
+                search_directories: self.search_directories.clone(),
+                loaded_files: loaded_files.clone(),
+            });
+
+        let shader = self
+            .render_device
+            .create_shader(&create_info().source_stream_factory(&source_stream_factory))
+            .map_err(creation_error)?;
+
+        let files = WatchedFiles::new(loaded_files.lock().unwrap().drain(..));
+
+        Ok((shader, files))
+    }
+
+    // The create info is created again, with the source stream factory of the reloader, every
+    // time the shader is recompiled. The source of the shader should be a
+    // `ShaderSource::FilePath`.
+    pub fn add_shader<F>(&mut self, create_info: F) -> Result<ShaderId, String>
+    where
+        F: Fn() -> ShaderCreateInfo<'static> + 'static,
+    {
+        let (shader, files) = self.compile(&create_info)?;
+
+        let name = unsafe { CStr::from_ptr(shader.get_desc()._DeviceObjectAttribs.Name) };
+
+        self.shaders.push(WatchedShader {
+            name: name.to_string_lossy().to_string(),
+            create_info: Box::new(create_info),
+            shader,
+            files,
+        });
+
+        Ok(ShaderId(self.shaders.len() - 1))
+    }
+
+    // `create_pipeline` is given the current versions of `shaders`, in the same order
+    pub fn add_pipeline<F>(
+        &mut self,
+        shaders: &[ShaderId],
+        create_pipeline: F,
+    ) -> Option<PipelineId>
+    where
+        F: Fn(&RenderDevice, &[&Shader]) -> Option<PipelineState> + 'static,
+    {
+        let pipeline_state = create_pipeline(&self.render_device, &self.get_shaders(shaders))?;
+
+        self.pipelines.push(WatchedPipeline {
+            shaders: shaders.to_vec(),
+            create_pipeline: Box::new(create_pipeline),
+            pipeline_state,
+        });
+
+        Some(PipelineId(self.pipelines.len() - 1))
+    }
+
+    fn get_shaders(&self, shaders: &[ShaderId]) -> Vec<&Shader> {
+        shaders.iter().map(|&id| self.get_shader(id)).collect()
+    }
+
+    pub fn get_shader(&self, id: ShaderId) -> &Shader {
+        &self.shaders[id.0].shader
+    }
+
+    pub fn get_pipeline(&self, id: PipelineId) -> &PipelineState {
+        &self.pipelines[id.0].pipeline_state
+    }
+
+    // Must be called between frames, when the pipelines are not in use
+    pub fn update(&mut self) -> ReloadResult {
+        let mut result = ReloadResult::default();
+
+        for index in 0..self.shaders.len() {
+            if !self.shaders[index].files.changed() {
+                continue;
+            }
+
+            match self.compile(self.shaders[index].create_info.as_ref()) {
+                Ok((shader, files)) => {
+                    let watched_shader = &mut self.shaders[index];
+                    watched_shader.shader = shader;
+                    watched_shader.files = files;
+                    result.reloaded_shaders.push(ShaderId(index));
+                }
+                Err(log) => result.shader_errors.push(ShaderReloadError {
+                    shader_name: self.shaders[index].name.clone(),
+                    log,
+                }),
+            }
+        }
+
+        for index in 0..self.pipelines.len() {
+            let watched_pipeline = &self.pipelines[index];
+            if !watched_pipeline
+                .shaders
+                .iter()
+                .any(|shader| result.reloaded_shaders.contains(shader))
+            {
+                continue;
+            }
+
+            let pipeline_state = (watched_pipeline.create_pipeline)(
+                &self.render_device,
+                &self.get_shaders(&watched_pipeline.shaders),
+            );
+
+            match pipeline_state {
+                Some(pipeline_state) => {
+                    self.pipelines[index].pipeline_state = pipeline_state;
+                    result.reloaded_pipelines.push(PipelineId(index));
+                }
+                None => result.pipeline_errors.push(PipelineId(index)),
+            }
+        }
+
+        result
+    }
+}
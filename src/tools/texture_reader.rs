@@ -0,0 +1,273 @@
+use crate::{
+    bindings,
+    core::{
+        device_context::{DeviceContext, ResourceStateTransitionMode},
+        graphics_types::{BindFlags, CpuAccessFlags, MapFlags, MapType, RenderDeviceType, Usage},
+        render_device::RenderDevice,
+        texture::{Texture, TextureDesc, TextureDimension},
+        texture_view::TextureView,
+    },
+};
+
+// Synchronously copies the content of a render target into CPU memory as tightly packed
+// RGBA8 rows, top row first.
+pub struct TextureReader {
+    staging_texture: Option<Texture>,
+}
+
+impl Default for TextureReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextureReader {
+    pub fn new() -> Self {
+        TextureReader {
+            staging_texture: None,
+        }
+    }
+
+    fn get_staging_texture(
+        &mut self,
+        render_device: &RenderDevice,
+        desc: &bindings::TextureDesc,
+    ) -> Option<&mut Texture> {
+        if !self
+            .staging_texture
+            .as_ref()
+            .is_some_and(|staging_texture| is_staging_texture_compatible(staging_texture, desc))
+        {
+            self.staging_texture = create_staging_texture(render_device, desc);
+        }
+
+        self.staging_texture.as_mut()
+    }
+
+    // Returns the width, the height and the pixels of the texture the view was created for.
+    // The copy waits for the GPU to go idle, this is meant for tests and tools, not for
+    // per-frame use.
+    pub fn read(
+        &mut self,
+        render_device: &RenderDevice,
+        device_context: &DeviceContext,
+        texture_view: &TextureView,
+    ) -> Option<(u32, u32, Vec<u8>)> {
+        let texture = texture_view.query_texture();
+        let desc = *texture.get_desc();
+
+        let staging_texture = self.get_staging_texture(render_device, &desc)?;
+
+        copy_to_staging_texture(device_context, &texture, staging_texture);
+
+        device_context.wait_for_idle();
+
+        read_staging_texture(render_device, device_context, staging_texture)
+            .map(|pixels| (desc.Width, desc.Height, pixels))
+    }
+}
+
+pub(crate) fn is_staging_texture_compatible(
+    staging_texture: &Texture,
+    desc: &bindings::TextureDesc,
+) -> bool {
+    let staging_desc = staging_texture.get_desc();
+    staging_desc.Width == desc.Width
+        && staging_desc.Height == desc.Height
+        && staging_desc.Format == desc.Format
+}
+
+pub(crate) fn create_staging_texture(
+    render_device: &RenderDevice,
+    desc: &bindings::TextureDesc,
+) -> Option<Texture> {
+    render_device.create_texture(
+        &TextureDesc::new(
+            c"Staging texture",
+            TextureDimension::Texture2D,
+            desc.Width,
+            desc.Height,
+            desc.Format as bindings::_TEXTURE_FORMAT,
+        )
+        .bind_flags(BindFlags::None)
+        .usage(Usage::Staging)
+        .cpu_access_flags(CpuAccessFlags::Read),
+        &[],
+        None,
+    )
+}
+
+pub(crate) fn copy_to_staging_texture(
+    device_context: &DeviceContext,
+    texture: &Texture,
+    staging_texture: &Texture,
+) {
+    let copy_attribs = bindings::CopyTextureAttribs {
+        pSrcTexture: texture.texture,
+        SrcMipLevel: 0,
+        SrcSlice: 0,
+        pSrcBox: std::ptr::null(),
+        SrcTextureTransitionMode: bindings::RESOURCE_STATE_TRANSITION_MODE::from(
+            &ResourceStateTransitionMode::Transition,
+        ),
+        pDstTexture: staging_texture.texture,
+        DstMipLevel: 0,
+        DstSlice: 0,
+        DstX: 0,
+        DstY: 0,
+        DstZ: 0,
+        DstTextureTransitionMode: bindings::RESOURCE_STATE_TRANSITION_MODE::from(
+            &ResourceStateTransitionMode::Transition,
+        ),
+    };
+
+    device_context.copy_texture(&copy_attribs);
+}
+
+// The copy into the staging texture must have completed on the GPU
+pub(crate) fn read_staging_texture(
+    render_device: &RenderDevice,
+    device_context: &DeviceContext,
+    staging_texture: &mut Texture,
+) -> Option<Vec<u8>> {
+    let desc = *staging_texture.get_desc();
+
+    let flip_vertically = matches!(
+        render_device.get_device_info().device_type(),
+        RenderDeviceType::GL | RenderDeviceType::GLES
+    );
+
+    let mapped_data = device_context.map_texture_subresource(
+        staging_texture,
+        0,
+        0,
+        MapType::Read,
+        MapFlags::None,
+        None,
+    );
+
+    if mapped_data.pData.is_null() {
+        return None;
+    }
+
+    let data = unsafe {
+        std::slice::from_raw_parts(
+            mapped_data.pData as *const u8,
+            mapped_data.Stride as usize * desc.Height as usize,
+        )
+    };
+
+    let pixels = copy_rgba8_pixels(
+        data,
+        mapped_data.Stride as usize,
+        desc.Width as usize,
+        desc.Height as usize,
+        desc.Format as bindings::_TEXTURE_FORMAT,
+        flip_vertically,
+    );
+
+    device_context.unmap_texture_subresource(staging_texture, 0, 0);
+
+    pixels
+}
+
+// Only 8 bit per channel RGBA and BGRA formats are supported.
+fn copy_rgba8_pixels(
+    data: &[u8],
+    stride: usize,
+    width: usize,
+    height: usize,
+    format: bindings::_TEXTURE_FORMAT,
+    flip_vertically: bool,
+) -> Option<Vec<u8>> {
+    let swap_red_blue = match format {
+        bindings::TEX_FORMAT_RGBA8_UNORM | bindings::TEX_FORMAT_RGBA8_UNORM_SRGB => false,
+        bindings::TEX_FORMAT_BGRA8_UNORM | bindings::TEX_FORMAT_BGRA8_UNORM_SRGB => true,
+        _ => return None,
+    };
+
+    let row_size = width * 4;
+    let mut pixels = Vec::with_capacity(row_size * height);
+
+    for row in 0..height {
+        let src_row = if flip_vertically {
+            height - 1 - row
+        } else {
+            row
+        };
+        let src = &data[src_row * stride..src_row * stride + row_size];
+
+        if swap_red_blue {
+            for pixel in src.chunks_exact(4) {
+                pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+            }
+        } else {
+            pixels.extend_from_slice(src);
+        }
+    }
+
+    Some(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two rows of two pixels with a padded stride of 12 bytes
+    const DATA: [u8; 24] = [
+        1, 2, 3, 4, 5, 6, 7, 8, 0xAA, 0xAA, 0xAA, 0xAA, //
+        9, 10, 11, 12, 13, 14, 15, 16, 0xAA, 0xAA, 0xAA, 0xAA,
+    ];
+
+    #[test]
+    fn rgba_rows_are_packed() {
+        let pixels =
+            copy_rgba8_pixels(&DATA, 12, 2, 2, bindings::TEX_FORMAT_RGBA8_UNORM, false).unwrap();
+        assert_eq!(
+            pixels,
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]
+        );
+    }
+
+    #[test]
+    fn bgra_is_swapped() {
+        let pixels = copy_rgba8_pixels(
+            &DATA,
+            12,
+            2,
+            2,
+            bindings::TEX_FORMAT_BGRA8_UNORM_SRGB,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            pixels,
+            [3, 2, 1, 4, 7, 6, 5, 8, 11, 10, 9, 12, 15, 14, 13, 16]
+        );
+    }
+
+    #[test]
+    fn rows_are_flipped() {
+        let pixels =
+            copy_rgba8_pixels(&DATA, 12, 2, 2, bindings::TEX_FORMAT_RGBA8_UNORM_SRGB, true)
+                .unwrap();
+        assert_eq!(
+            pixels,
+            [9, 10, 11, 12, 13, 14, 15, 16, 1, 2, 3, 4, 5, 6, 7, 8]
+        );
+
+        let pixels =
+            copy_rgba8_pixels(&DATA, 12, 2, 2, bindings::TEX_FORMAT_BGRA8_UNORM, true).unwrap();
+        assert_eq!(
+            pixels,
+            [11, 10, 9, 12, 15, 14, 13, 16, 3, 2, 1, 4, 7, 6, 5, 8]
+        );
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        assert!(
+            copy_rgba8_pixels(&DATA, 12, 2, 2, bindings::TEX_FORMAT_RGBA16_FLOAT, false).is_none()
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::Instant;
+
+// Monotonic timer measuring the time since it was created or last restarted
+pub struct Timer {
+    start: Instant,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer {
+            start: Instant::now(),
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    // In seconds
+    pub fn get_elapsed_time(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
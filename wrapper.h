@@ -1,4 +1,16 @@
 #define DILIGENT_C_INTERFACE 1
 
 #include <DiligentCore/Graphics/GraphicsEngine/interface/EngineFactory.h>
-#include <DiligentCore/Graphics/GraphicsEngineVulkan/interface/EngineFactoryVk.h>
\ No newline at end of file
+#include <DiligentCore/Common/interface/ThreadPool.h>
+
+#if VULKAN_SUPPORTED
+#include <DiligentCore/Graphics/GraphicsEngineVulkan/interface/EngineFactoryVk.h>
+#endif
+
+#if GL_SUPPORTED
+#include <DiligentCore/Graphics/GraphicsEngineOpenGL/interface/EngineFactoryOpenGL.h>
+#endif
+
+#if ARCHIVER_SUPPORTED
+#include <DiligentCore/Graphics/Archiver/interface/ArchiverFactoryLoader.h>
+#endif